@@ -14,11 +14,17 @@
 }
 #import bevy_core_pipeline::tonemapping::tone_mapping
 
-#import "shaders/voxel_data.wgsl"::{voxel_data_extract_normal, voxel_data_extract_material_index}
+#import "shaders/voxel_data.wgsl"::{voxel_data_extract_normal, voxel_data_extract_material_index, voxel_data_extract_light, voxel_data_extract_ao}
 #import "shaders/terrain_uniforms.wgsl"::{VoxelMat, voxel_materials, render_distance, TERRAIN_CHUNK_LENGTH}
 #import "shaders/noise.wgsl"::hash
 #import "shaders/fog.wgsl"::ffog_apply_fog
 
+// Brightness of faces receiving no light at all
+const MIN_LIGHT_FACTOR: f32 = 0.06;
+
+// Brightness of fully occluded vertices
+const MIN_AO_FACTOR: f32 = 0.4;
+
 struct Vertex {
     @builtin(instance_index) instance_index: u32,
     @location(0) position: vec3<f32>,
@@ -31,6 +37,7 @@ struct VertexOutput {
     @location(1) voxel_data: u32,
     @location(2) world_position: vec3<f32>,
     @location(3) instance_index: u32,
+    @location(4) ambient_occlusion: f32,
 };
 
 @vertex
@@ -45,6 +52,7 @@ fn vertex(vertex: Vertex) -> VertexOutput {
     out.voxel_data = vertex.voxel_data;
     out.world_position = world_position.xyz;
     out.instance_index = vertex.instance_index;
+    out.ambient_occlusion = voxel_data_extract_ao(vertex.voxel_data);
 
     return out;
 }
@@ -59,6 +67,8 @@ struct Fragment {
     /// The world position of the voxel vertex.
     @location(2) world_position: vec3<f32>,
     @location(3) instance_index: u32,
+    /// The ambient occlusion interpolated between the vertices of the face.
+    @location(4) ambient_occlusion: f32,
 };
 
 fn prepare_pbr_input_from_voxel_mat(voxel_mat: VoxelMat, frag: Fragment) -> PbrInput {
@@ -92,7 +102,13 @@ fn fragment(frag: Fragment) -> @location(0) vec4<f32> {
 
     /// PBR lighting input data preparation
     var pbr_input = prepare_pbr_input_from_voxel_mat(material, frag);
-    let pbr_colour = tone_mapping(apply_pbr_lighting(pbr_input), view.color_grading);
+    var pbr_colour = tone_mapping(apply_pbr_lighting(pbr_input), view.color_grading);
+
+    // Darken faces hidden from the sky and away from light sources, keeping a bit of ambient
+    let light = voxel_data_extract_light(frag.voxel_data);
+    let light_factor = mix(MIN_LIGHT_FACTOR, 1.0, pow(max(light.x, light.y), 1.4));
+    let ao_factor = mix(MIN_AO_FACTOR, 1.0, frag.ambient_occlusion);
+    pbr_colour = vec4<f32>(pbr_colour.rgb * light_factor * ao_factor, pbr_colour.a);
 
     // @todo: switch to bevy_pbr::fog
 
@@ -4,15 +4,15 @@
 // Layout of voxel information encoded into a single u32
 //
 //  00000000    00000000    00000000    00000000    
-//  XXXXXYYY    YYZZZZZ          NNN    MATERIAL
+//              SSSSBBBB       AANNN    MATERIAL
 //
-// X: X position
-// Y: Y position
-// Z: Z position
+// S: sky light level (0-15) of the voxel in front of the face
+// B: block light level (0-15) of the voxel in front of the face
+// A: ambient occlusion of the vertex (0 fully occluded, 3 unoccluded)
 // N: normal index in the VOXEL_NORMALS array
 // MATERIAL: material index in the palette
 // 
-// The remaining 5 free bits could be used to store UV data or additional info or even extend voxel material id size.
+// The remaining free bits could be used to store UV data or additional info or even extend voxel material id size.
 
 // An array of voxel face normals 
 var<private> VOXEL_NORMALS: array<vec3<f32>, 6> = array<vec3<f32>, 6>(
@@ -37,6 +37,19 @@ fn voxel_data_extract_normal(voxel_data: u32) -> vec3<f32> {
 //     );
 // }
 
+// Extracts the sky and block light levels from the encoded voxel data, normalized in [0, 1]
+fn voxel_data_extract_light(voxel_data: u32) -> vec2<f32> {
+    return vec2<f32>(
+        f32(voxel_data >> 20u & 15u),
+        f32(voxel_data >> 16u & 15u)
+    ) / 15.0;
+}
+
+// Extracts the ambient occlusion of the vertex from the encoded voxel data, normalized in [0, 1]
+fn voxel_data_extract_ao(voxel_data: u32) -> f32 {
+    return f32(voxel_data >> 11u & 3u) / 3.0;
+}
+
 // Extracts the material index from the encoded voxel data
 fn voxel_data_extract_material_index(voxel_data: u32) -> u32 {
     return voxel_data & 255u;
@@ -1,17 +1,47 @@
-use std::f32::consts::PI;
-use voxel_engine::*;
+use std::{f32::consts::PI, net::SocketAddr, path::Path, time::Duration};
+use voxel_engine::{
+    client::{
+        network, render,
+        systems::{self, sky, MovementInput, PlayerController},
+    },
+    *,
+};
 use bevy::{core_pipeline::fxaa::Fxaa, log::LogPlugin, prelude::*};
-
-use crate::systems::{sky, PlayerController};
-
-mod render;
-mod systems;
+use bevy_renet::netcode::ConnectToken;
 
 fn main() {
     let _guard = setup_file_logging();
+
+    // Passing a server address joins it instead of playing a local world
+    let server_addr = std::env::args()
+        .nth(1)
+        .and_then(|addr| {
+            addr.parse::<SocketAddr>()
+                .map_err(|e| GameError::Parser(e.to_string()))
+                .log_err_with("Invalid server address")
+        });
+
+    // Servers in secure mode only let in clients with a token issued for them
+    let connect_token = std::env::args()
+        .nth(2)
+        .and_then(|path| {
+            read_connect_token(Path::new(&path)).log_err_with("Invalid connect token")
+        });
+
+    // Lets prediction be tried out against a far away server while running one locally
+    let simulated_latency = std::env::var("SIMULATED_LATENCY_MS")
+        .ok()
+        .and_then(|ms| ms.parse().ok())
+        .map_or(Duration::ZERO, Duration::from_millis);
+
     App::new()
         .add_plugins(DefaultPlugins.build().disable::<LogPlugin>())
-        .add_plugins(WorldPlugin)
+        .insert_resource(Time::<Fixed>::from_hz(MOVEMENT_RATE))
+        .add_plugins(WorldPlugin {
+            server_addr,
+            connect_token,
+            simulated_latency,
+        })
         .add_systems(Startup, setup)
         .run();
 }
@@ -31,6 +61,13 @@ fn setup(mut commands: Commands) {
     .insert(DisplayName("player".to_string()))
     .insert(Health::new(20))
     .insert(PlayerController::default())
+    .insert(MovementInput::default())
+    .insert((
+        Velocity::default(),
+        IsOnGround(false),
+        Collider::default(),
+        MovementMode::default(),
+    ))
     .insert(Fxaa::default())
     .insert(bevy_atmosphere::plugin::AtmosphereCamera::default());
 
@@ -41,16 +78,50 @@ fn setup(mut commands: Commands) {
     });
 }
 
-pub struct WorldPlugin;
+pub struct WorldPlugin {
+    /// Server streaming the world, the world is generated and saved locally when `None`.
+    pub server_addr: Option<SocketAddr>,
+    /// Token presented to the server when joining one.
+    pub connect_token: Option<ConnectToken>,
+    /// Delay added to the messages exchanged with the server.
+    pub simulated_latency: Duration,
+}
 
 impl Plugin for WorldPlugin {
     fn build(&self, app: &mut bevy::app::App) {
+        match self.server_addr {
+            Some(server_addr) => {
+                app.add_plugins(network::ClientNetcodePlugin {
+                    server_addr,
+                    connect_token: self.connect_token.clone(),
+                })
+                .add_plugins(network::ClientNetworkPlugin {
+                    simulated_latency: self.simulated_latency,
+                })
+                .add_plugins(network::RemotePlayerModelPlugin)
+                .add_plugins(systems::ChatPlugin);
+            }
+            None => {
+                let storage = RegionStorage::open(DEFAULT_WORLD_DIR)
+                    .log_err_with("Failed to open world storage");
+
+                app.insert_resource(WorldSeed::load_or_create(storage.as_ref(), None));
+                if let Some(storage) = storage {
+                    app.insert_resource(storage);
+                }
+
+                // Ordering of plugins is important here;
+                app.add_plugins(generation::TerrainGeneratorPlugin)
+                    .add_plugins(terrain::WorldTerrainGenPlugin);
+            }
+        }
+
         app.insert_resource(ChunkMap::<Block, ChunkShape>::new(ChunkShape {}))
             .add_plugins(chunk::ChunkingPlugin)
             .add_plugins(render::chunk_meshing::WorldMeshingPlugin)
-            // Ordering of plugins is important here;
-            .add_plugins(generation::TerrainGeneratorPlugin)
-            .add_plugins(terrain::WorldTerrainGenPlugin)
+            .add_plugins(physics::PhysicsPlugin)
+            .add_plugins(light::LightingPlugin)
+            .add_plugins(day_cycle::TimeOfDayPlugin)
             .add_plugins(material::BlockMaterialPlugin)
             .add_plugins(render::shaders::ChunkMaterialPlugin)
             .add_plugins(world::blocks::BlockBaseMaterialsPlugin)
@@ -0,0 +1,70 @@
+use bevy::{ecs::system::SystemParam, prelude::*};
+use crate::{NetworkMessage, RELIABLE_CHANNEL};
+
+use crate::client::{
+    network::ServerSender,
+    systems::{ChatInput, ChatLog, ChatSubmitted, CompletionRequested},
+};
+
+/// A line the server wants shown in the chat.
+#[derive(Event, Clone)]
+pub struct ServerChatMessage {
+    pub sender: Option<String>,
+    pub text: String,
+}
+
+/// Words the server suggested to complete a command line.
+#[derive(Event, Clone)]
+pub struct ServerCompletions {
+    pub input: String,
+    pub suggestions: Vec<String>,
+}
+
+/// Writers of the chat events received from the server.
+#[derive(SystemParam)]
+pub struct ChatEvents<'w> {
+    pub messages: EventWriter<'w, ServerChatMessage>,
+    pub completions: EventWriter<'w, ServerCompletions>,
+}
+
+pub fn send_chat(
+    mut submitted: EventReader<ChatSubmitted>,
+    mut completions: EventReader<CompletionRequested>,
+    mut sender: ServerSender,
+) {
+    for ChatSubmitted(text) in submitted.read() {
+        sender.send(
+            RELIABLE_CHANNEL,
+            &NetworkMessage::Chat { text: text.clone() },
+        );
+    }
+
+    for CompletionRequested(input) in completions.read() {
+        sender.send(
+            RELIABLE_CHANNEL,
+            &NetworkMessage::CompleteCommand {
+                input: input.clone(),
+            },
+        );
+    }
+}
+
+pub fn show_chat_messages(
+    mut messages: EventReader<ServerChatMessage>,
+    mut completions: EventReader<ServerCompletions>,
+    mut log: ResMut<ChatLog>,
+    mut input: ResMut<ChatInput>,
+) {
+    for ServerChatMessage { sender, text } in messages.read() {
+        for line in text.lines() {
+            match sender {
+                Some(sender) => log.push(format!("<{sender}> {line}")),
+                None => log.push(line.to_string()),
+            }
+        }
+    }
+
+    for ServerCompletions { input: line, suggestions } in completions.read() {
+        input.complete(line, suggestions.clone());
+    }
+}
@@ -0,0 +1,338 @@
+use bevy::{ecs::system::SystemParam, prelude::*};
+use bevy_renet::{
+    client_connected, client_just_connected,
+    netcode::{ConnectToken, NetcodeClientPlugin},
+    renet::{Bytes, RenetClient},
+    RenetClientPlugin, RenetSend,
+};
+use std::{collections::VecDeque, net::SocketAddr, time::Duration};
+use tracing::{error, info, warn};
+use crate::{
+    create_client_connection, decode_chunk, Block, BlockMaterialRegistry, Chunk,
+    ChunkCommandQueue, ChunkEntities, ChunkLoadingSet, ChunkMap, ChunkShape, ClientError,
+    DirtyChunks, GameError, Lobby, LocalClientId, MaterialIdTable, NetworkConfig,
+    NetworkMessage, PhysicsSet, ResultExt, StreamedWorld, TimeOfDay, WorldEditSet, WorldSeed,
+    PROTOCOL_VERSION, RELIABLE_CHANNEL, UNRELIABLE_CHANNEL,
+};
+
+use crate::client::{
+    network::{
+        apply_server_edits, buffer_player_snapshots, interpolate_remote_players,
+        reconcile_player_state, send_chat, send_edit_requests, send_player_inputs,
+        show_chat_messages, spawn_remote_players, ChatEvents, PendingEdits, PlayerJoined,
+        PlayerLeft, PredictionHistory, ServerBlockChanges, ServerChatMessage, ServerClock,
+        ServerCompletions, ServerEditResult, ServerPlayerSnapshots, ServerPlayerState,
+    },
+    systems::{
+        BlockEditRequest, ChatInput, ChatLog, ChatSubmitted, CompletionRequested, PlayerMoved,
+        SelectedMaterial,
+    },
+};
+
+/// Connects the client to a dedicated server over UDP through netcode.
+pub struct ClientNetcodePlugin {
+    pub server_addr: SocketAddr,
+    /// Token issued for the server, required to join servers in secure mode.
+    pub connect_token: Option<ConnectToken>,
+}
+
+impl Plugin for ClientNetcodePlugin {
+    fn build(&self, app: &mut App) {
+        let (client, transport) = create_client_connection(
+            self.server_addr,
+            self.connect_token.clone(),
+            NetworkConfig::default().connection_config(),
+        );
+
+        info!("Connecting to {}", self.server_addr);
+
+        app.add_plugins(NetcodeClientPlugin)
+            .insert_resource(LocalClientId(transport.client_id()))
+            .insert_resource(client)
+            .insert_resource(transport);
+    }
+}
+
+/// Plays on a dedicated server, the world is then streamed from the server instead of being
+/// generated locally. A transport plugin provides the [`RenetClient`].
+///
+/// This also runs headless, without the client systems it exchanges events with.
+pub struct ClientNetworkPlugin {
+    /// Delay added to every message exchanged with the server, both ways, to try out the game
+    /// against a far away server.
+    pub simulated_latency: Duration,
+}
+
+/// Holds back the messages exchanged with the server for a while before handling or sending
+/// them.
+#[derive(Resource, Default)]
+pub struct SimulatedLatency {
+    delay: Duration,
+    incoming: VecDeque<(Duration, Bytes)>,
+    outgoing: VecDeque<(Duration, u8, Bytes)>,
+}
+
+impl SimulatedLatency {
+    /// Takes the messages out of the client and returns the ones which were held back long
+    /// enough, in the order they were received.
+    fn receive(&mut self, client: &mut RenetClient, now: Duration) -> Vec<Bytes> {
+        for channel in [RELIABLE_CHANNEL, UNRELIABLE_CHANNEL] {
+            while let Some(bytes) = client.receive_message(channel) {
+                self.incoming.push_back((now + self.delay, bytes));
+            }
+        }
+
+        let ready = self
+            .incoming
+            .iter()
+            .position(|(release, _)| *release > now)
+            .unwrap_or(self.incoming.len());
+
+        self.incoming.drain(..ready).map(|(_, bytes)| bytes).collect()
+    }
+
+    /// Hands the messages held back long enough over to the client, in the order they were sent.
+    fn send(&mut self, client: &mut RenetClient, now: Duration) {
+        let ready = self
+            .outgoing
+            .iter()
+            .position(|(release, _, _)| *release > now)
+            .unwrap_or(self.outgoing.len());
+
+        for (_, channel, bytes) in self.outgoing.drain(..ready) {
+            client.send_message(channel, bytes);
+        }
+    }
+}
+
+/// Sends messages to the server, through the [`SimulatedLatency`].
+#[derive(SystemParam)]
+pub struct ServerSender<'w> {
+    client: ResMut<'w, RenetClient>,
+    latency: ResMut<'w, SimulatedLatency>,
+    // Inputs are sent from the fixed schedule, they must be held back on the same clock
+    time: Res<'w, Time<Virtual>>,
+}
+
+impl ServerSender<'_> {
+    /// Sends a message to the server on the specified channel.
+    pub fn send(&mut self, channel: u8, message: &NetworkMessage) {
+        let Some(bytes) = message
+            .to_bytes()
+            .log_err_with("Failed to encode network message")
+        else {
+            return;
+        };
+
+        if self.latency.delay.is_zero() {
+            self.client.send_message(channel, bytes);
+        } else {
+            let release = self.time.elapsed() + self.latency.delay;
+            self.latency.outgoing.push_back((release, channel, bytes.into()));
+        }
+    }
+}
+
+impl Plugin for ClientNetworkPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(RenetClientPlugin)
+            .init_resource::<StreamedWorld>()
+            .insert_resource(SimulatedLatency {
+                delay: self.simulated_latency,
+                ..Default::default()
+            })
+            .init_resource::<PendingEdits>()
+            .init_resource::<PredictionHistory>()
+            .init_resource::<Lobby>()
+            .init_resource::<ServerClock>()
+            .init_resource::<ChatLog>()
+            .init_resource::<ChatInput>()
+            .add_event::<BlockEditRequest>()
+            .add_event::<PlayerMoved>()
+            .add_event::<ChatSubmitted>()
+            .add_event::<CompletionRequested>()
+            .add_event::<ServerBlockChanges>()
+            .add_event::<ServerEditResult>()
+            .add_event::<PlayerJoined>()
+            .add_event::<PlayerLeft>()
+            .add_event::<ServerPlayerSnapshots>()
+            .add_event::<ServerPlayerState>()
+            .add_event::<ServerChatMessage>()
+            .add_event::<ServerCompletions>()
+            .add_systems(Update, send_login.run_if(client_just_connected))
+            .add_systems(
+                Update,
+                receive_server_messages
+                    .run_if(client_connected)
+                    .before(ChunkLoadingSet),
+            )
+            .add_systems(Update, apply_server_edits.in_set(WorldEditSet))
+            .add_systems(Update, reconcile_player_state.after(receive_server_messages))
+            .add_systems(Update, show_chat_messages.after(receive_server_messages))
+            .add_systems(Update, send_chat.run_if(client_connected))
+            .add_systems(
+                Update,
+                (
+                    spawn_remote_players,
+                    buffer_player_snapshots,
+                    interpolate_remote_players,
+                )
+                    .chain()
+                    .after(receive_server_messages),
+            )
+            .add_systems(
+                Update,
+                send_edit_requests
+                    .run_if(client_connected)
+                    .after(WorldEditSet),
+            )
+            .add_systems(
+                FixedUpdate,
+                send_player_inputs
+                    .run_if(client_connected)
+                    .after(PhysicsSet),
+            )
+            .add_systems(
+                PostUpdate,
+                send_delayed_messages
+                    .run_if(client_connected)
+                    .before(RenetSend),
+            );
+    }
+}
+
+/// Sends the messages the [`SimulatedLatency`] held back long enough.
+pub fn send_delayed_messages(
+    mut client: ResMut<RenetClient>,
+    mut latency: ResMut<SimulatedLatency>,
+    time: Res<Time<Virtual>>,
+) {
+    latency.send(&mut client, time.elapsed());
+}
+
+/// Asks the server to join the game, nothing else is sent until it lets us in.
+pub fn send_login(mut sender: ServerSender) {
+    sender.send(
+        RELIABLE_CHANNEL,
+        &NetworkMessage::Login {
+            protocol_version: PROTOCOL_VERSION,
+        },
+    );
+}
+
+pub fn receive_server_messages(
+    mut client: ResMut<RenetClient>,
+    mut latency: ResMut<SimulatedLatency>,
+    time: Res<Time>,
+    mut chunks: ResMut<ChunkMap<Block, ChunkShape>>,
+    mut chunk_entities: ResMut<ChunkEntities>,
+    mut dirty_chunks: ResMut<DirtyChunks>,
+    mut chunk_command_queue: ResMut<ChunkCommandQueue>,
+    mut registry: ResMut<BlockMaterialRegistry>,
+    mut block_changes: EventWriter<ServerBlockChanges>,
+    mut edit_results: EventWriter<ServerEditResult>,
+    mut joined: EventWriter<PlayerJoined>,
+    mut left: EventWriter<PlayerLeft>,
+    mut snapshots: EventWriter<ServerPlayerSnapshots>,
+    mut player_states: EventWriter<ServerPlayerState>,
+    mut chat: ChatEvents,
+    mut commands: Commands,
+) {
+    let mut materials = None;
+
+    for bytes in latency.receive(&mut client, time.elapsed()) {
+        let Some(message) =
+            NetworkMessage::from_bytes(&bytes).log_err_with("Received an invalid message")
+        else {
+            continue;
+        };
+
+        match message {
+            NetworkMessage::LoginAccepted {
+                protocol_version,
+                seed,
+                materials: definitions,
+            } => {
+                // Material ids in the messages to come are the ones of the server
+                let synced = if protocol_version == PROTOCOL_VERSION {
+                    registry.apply_definitions(&definitions)
+                } else {
+                    Err(GameError::from(ClientError::IncompatibleServer(format!(
+                        "the server runs protocol version {protocol_version} but the client runs \
+                         version {PROTOCOL_VERSION}"
+                    ))))
+                };
+
+                if synced.log_err_with("Can't join the server").is_none() {
+                    client.disconnect();
+                    return;
+                }
+
+                info!("Joined the server");
+                commands.insert_resource(WorldSeed(seed));
+                materials = None;
+            }
+            NetworkMessage::Disconnect { reason } => {
+                error!("Disconnected by the server: {reason}");
+                client.disconnect();
+                return;
+            }
+            NetworkMessage::ChunkData { key, data } => {
+                let materials = materials.get_or_insert_with(|| MaterialIdTable::from(&*registry));
+                let Some(buffer) = decode_chunk(&data, ChunkShape {}, materials)
+                    .log_err_with("Received an invalid chunk")
+                else {
+                    continue;
+                };
+
+                chunks.insert(key, buffer);
+                if chunk_entities.entity(key).is_none() {
+                    chunk_entities.attach_entity(key, commands.spawn(Chunk(key)).id());
+                }
+
+                dirty_chunks.mark_dirty(key);
+                dirty_chunks.mark_neighbours_dirty(key, &chunks);
+            }
+            NetworkMessage::UnloadChunk { key } => {
+                chunk_command_queue.queue_unload(std::iter::once(&key));
+            }
+            NetworkMessage::PlayerConnected { id } => {
+                info!("Player {id} joined");
+                joined.write(PlayerJoined(id));
+            }
+            NetworkMessage::PlayerDisconnected { id } => {
+                info!("Player {id} left");
+                left.write(PlayerLeft(id));
+            }
+            NetworkMessage::PlayerSnapshots { tick, players } => {
+                snapshots.write(ServerPlayerSnapshots { tick, players });
+            }
+            NetworkMessage::BlockChanges(changes) => {
+                block_changes.write(ServerBlockChanges(changes));
+            }
+            NetworkMessage::EditResult { request, accepted } => {
+                edit_results.write(ServerEditResult { request, accepted });
+            }
+            NetworkMessage::PlayerState { sequence, state } => {
+                player_states.write(ServerPlayerState { sequence, state });
+            }
+            NetworkMessage::ChatMessage { sender, text } => {
+                chat.messages.write(ServerChatMessage { sender, text });
+            }
+            NetworkMessage::Completions { input, suggestions } => {
+                chat.completions.write(ServerCompletions { input, suggestions });
+            }
+            NetworkMessage::GameModeChanged { mode } => {
+                info!("Game mode set to {}", mode.name());
+                commands.insert_resource(mode);
+            }
+            NetworkMessage::HeldBlock { block } => {
+                commands.insert_resource(SelectedMaterial(block));
+            }
+            NetworkMessage::SetTimeOfDay { time } => {
+                commands.insert_resource(TimeOfDay(time));
+            }
+            message => warn!("Unexpected message from the server: {message:?}"),
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use bevy::prelude::*;
+use std::collections::BTreeMap;
+use crate::{Block, NetworkMessage, WorldEdits, RELIABLE_CHANNEL};
+
+use crate::client::{network::ServerSender, systems::BlockEditRequest};
+
+/// Blocks changed on the server in the chunks we have loaded.
+#[derive(Event)]
+pub struct ServerBlockChanges(pub Vec<(IVec3, Block)>);
+
+/// Answer of the server to one of our edit requests.
+#[derive(Event, Clone, Copy)]
+pub struct ServerEditResult {
+    pub request: u32,
+    pub accepted: bool,
+}
+
+struct PendingEdit {
+    pos: IVec3,
+    /// Block restored if the server refuses the edit, kept up to date with the server changes.
+    rollback: Block,
+}
+
+/// Edits applied locally ahead of the server, waiting for its answer.
+#[derive(Resource, Default)]
+pub struct PendingEdits {
+    next_request: u32,
+    edits: BTreeMap<u32, PendingEdit>,
+}
+
+pub fn send_edit_requests(
+    mut edit_requests: EventReader<BlockEditRequest>,
+    mut pending: ResMut<PendingEdits>,
+    mut sender: ServerSender,
+) {
+    for edit in edit_requests.read() {
+        let request = pending.next_request;
+        pending.next_request = request.wrapping_add(1);
+        pending.edits.insert(
+            request,
+            PendingEdit {
+                pos: edit.pos,
+                rollback: edit.previous,
+            },
+        );
+
+        let message = if edit.block.is_empty() {
+            NetworkMessage::BreakBlock {
+                request,
+                pos: edit.pos,
+            }
+        } else {
+            NetworkMessage::PlaceBlock {
+                request,
+                pos: edit.pos,
+                block: edit.block,
+            }
+        };
+
+        sender.send(RELIABLE_CHANNEL, &message);
+    }
+}
+
+/// Applies the changes sent by the server and rolls back the local edits it refused.
+pub fn apply_server_edits(
+    mut block_changes: EventReader<ServerBlockChanges>,
+    mut edit_results: EventReader<ServerEditResult>,
+    mut pending: ResMut<PendingEdits>,
+    mut edits: WorldEdits,
+) {
+    for ServerBlockChanges(changes) in block_changes.read() {
+        for (pos, block) in changes {
+            let mut predicted = false;
+            pending
+                .edits
+                .values_mut()
+                .filter(|edit| edit.pos == *pos)
+                .for_each(|edit| {
+                    edit.rollback = *block;
+                    predicted = true;
+                });
+
+            // Predicted blocks are kept until the server answers their request
+            if !predicted {
+                edits.set_block(*pos, *block);
+            }
+        }
+    }
+
+    for result in edit_results.read() {
+        let Some(edit) = pending.edits.remove(&result.request) else {
+            continue;
+        };
+
+        if result.accepted {
+            continue;
+        }
+
+        // A later edit of the same block now has to restore what this one replaced
+        match pending
+            .edits
+            .range_mut(result.request..)
+            .map(|(_, later)| later)
+            .find(|later| later.pos == edit.pos)
+        {
+            Some(later) => later.rollback = edit.rollback,
+            None => {
+                edits.set_block(edit.pos, edit.rollback);
+            }
+        }
+    }
+}
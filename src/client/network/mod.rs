@@ -0,0 +1,14 @@
+pub mod chat;
+pub use chat::*;
+
+pub mod connection;
+pub use connection::*;
+
+pub mod edits;
+pub use edits::*;
+
+pub mod players;
+pub use players::*;
+
+pub mod prediction;
+pub use prediction::*;
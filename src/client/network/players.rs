@@ -0,0 +1,260 @@
+use bevy::prelude::*;
+use bevy_renet::renet::ClientId;
+use std::{
+    collections::VecDeque,
+    f32::consts::{PI, TAU},
+};
+use crate::{
+    Collider, DisplayName, HeadRotation, Lobby, LocalClientId, PlayerSnapshot, TICK_RATE,
+};
+
+/// How far in the past remote players are rendered, in seconds.
+///
+/// This leaves room for a couple of snapshots to be late or dropped without players stopping.
+const INTERPOLATION_DELAY: f64 = 0.1;
+
+/// Snapshots kept per remote player, more than a second worth of ticks.
+const MAX_BUFFERED_SNAPSHOTS: usize = 32;
+
+/// Weight of a new sample in the estimate of the server clock.
+const CLOCK_SMOOTHING: f64 = 0.05;
+
+/// Clock difference past which the estimate is reset instead of smoothed, in seconds.
+const CLOCK_RESYNC_THRESHOLD: f64 = 1.0;
+
+const REMOTE_PLAYER_COLOR: Color = Color::srgb(0.8, 0.45, 0.3);
+
+/// A player connected to the same server as us.
+#[derive(Component, Clone, Copy, Deref)]
+pub struct RemotePlayer(pub ClientId);
+
+/// Snapshots received for a remote player, oldest first.
+#[derive(Component, Default)]
+pub struct SnapshotBuffer(VecDeque<(u32, PlayerSnapshot)>);
+
+impl SnapshotBuffer {
+    /// Adds a snapshot, snapshots older than the latest one are dropped as they arrived late.
+    pub fn push(&mut self, tick: u32, snapshot: PlayerSnapshot) {
+        if self.0.back().is_some_and(|(latest, _)| *latest >= tick) {
+            return;
+        }
+
+        if self.0.len() == MAX_BUFFERED_SNAPSHOTS {
+            self.0.pop_front();
+        }
+
+        self.0.push_back((tick, snapshot));
+    }
+
+    /// Returns the state of the player at a fractional tick, holding the latest snapshot if the
+    /// tick is past it.
+    pub fn sample(&mut self, tick: f64) -> Option<PlayerSnapshot> {
+        // Only a single snapshot older than the sampled tick is needed to interpolate from
+        while self.0.get(1).is_some_and(|(next, _)| *next as f64 <= tick) {
+            self.0.pop_front();
+        }
+
+        match (self.0.front(), self.0.get(1)) {
+            (Some((from_tick, from)), Some((to_tick, to))) => {
+                let t = (tick - *from_tick as f64) / (*to_tick - *from_tick) as f64;
+                Some(interpolate(from, to, t.clamp(0., 1.) as f32))
+            }
+            (Some((_, latest)), None) => Some(*latest),
+            _ => None,
+        }
+    }
+}
+
+fn interpolate(from: &PlayerSnapshot, to: &PlayerSnapshot, t: f32) -> PlayerSnapshot {
+    PlayerSnapshot {
+        id: to.id,
+        position: from.position.lerp(to.position, t),
+        yaw: lerp_angle(from.yaw, to.yaw, t),
+        pitch: from.pitch + (to.pitch - from.pitch) * t,
+    }
+}
+
+/// Interpolates between two angles the short way around.
+#[inline]
+fn lerp_angle(from: f32, to: f32, t: f32) -> f32 {
+    let delta = (to - from + PI).rem_euclid(TAU) - PI;
+    from + delta * t
+}
+
+/// Estimate of the server clock, used to pick which snapshots to render.
+#[derive(Resource, Default)]
+pub struct ServerClock {
+    /// Server time minus local time, in seconds.
+    offset: Option<f64>,
+}
+
+impl ServerClock {
+    fn observe(&mut self, tick: u32, now: f64) {
+        let sample = tick as f64 / TICK_RATE - now;
+
+        self.offset = Some(match self.offset {
+            Some(offset) if (sample - offset).abs() < CLOCK_RESYNC_THRESHOLD => {
+                offset + (sample - offset) * CLOCK_SMOOTHING
+            }
+            _ => sample,
+        });
+    }
+
+    /// Returns the fractional server tick remote players should currently be rendered at.
+    fn render_tick(&self, now: f64) -> Option<f64> {
+        self.offset.map(|offset| (now + offset - INTERPOLATION_DELAY) * TICK_RATE)
+    }
+}
+
+/// A player joined the server.
+#[derive(Event, Clone, Copy)]
+pub struct PlayerJoined(pub ClientId);
+
+/// A player left the server.
+#[derive(Event, Clone, Copy)]
+pub struct PlayerLeft(pub ClientId);
+
+/// State of every player on the server at a given tick.
+#[derive(Event)]
+pub struct ServerPlayerSnapshots {
+    pub tick: u32,
+    pub players: Vec<PlayerSnapshot>,
+}
+
+#[derive(Resource)]
+pub struct RemotePlayerAssets {
+    mesh: Handle<Mesh>,
+    material: Handle<StandardMaterial>,
+}
+
+impl FromWorld for RemotePlayerAssets {
+    fn from_world(world: &mut World) -> Self {
+        let collider = Collider::default();
+        let mesh = world.resource_mut::<Assets<Mesh>>().add(Capsule3d::new(
+            collider.half_width,
+            collider.height - 2. * collider.half_width,
+        ));
+        let material = world
+            .resource_mut::<Assets<StandardMaterial>>()
+            .add(StandardMaterial::from_color(REMOTE_PLAYER_COLOR));
+
+        Self { mesh, material }
+    }
+}
+
+pub fn spawn_remote_players(
+    mut joined: EventReader<PlayerJoined>,
+    mut left: EventReader<PlayerLeft>,
+    mut lobby: ResMut<Lobby>,
+    local_id: Res<LocalClientId>,
+    mut commands: Commands,
+) {
+    for PlayerJoined(id) in joined.read() {
+        if *id == local_id.0 || lobby.players.contains_key(id) {
+            continue;
+        }
+
+        let player = commands
+            .spawn((
+                RemotePlayer(*id),
+                DisplayName(format!("player-{id}")),
+                HeadRotation::default(),
+                SnapshotBuffer::default(),
+                Transform::default(),
+                Visibility::Hidden,
+            ))
+            .id();
+
+        lobby.players.insert(*id, player);
+    }
+
+    for PlayerLeft(id) in left.read() {
+        if let Some(player) = lobby.players.remove(id) {
+            commands.entity(player).despawn();
+        }
+    }
+}
+
+/// Gives the remote players which just joined a body.
+pub fn attach_remote_player_models(
+    players: Query<Entity, Added<RemotePlayer>>,
+    assets: Res<RemotePlayerAssets>,
+    mut commands: Commands,
+) {
+    let collider = Collider::default();
+
+    // Snapshots carry the eye position, the body is offset down to stand on the feet
+    for player in players.iter() {
+        commands.entity(player).with_children(|parent| {
+            parent.spawn((
+                Mesh3d(assets.mesh.clone()),
+                MeshMaterial3d(assets.material.clone()),
+                Transform::from_xyz(0., collider.height / 2. - collider.eye_height, 0.),
+            ));
+        });
+    }
+}
+
+pub fn buffer_player_snapshots(
+    mut snapshots: EventReader<ServerPlayerSnapshots>,
+    mut buffers: Query<&mut SnapshotBuffer>,
+    mut clock: ResMut<ServerClock>,
+    lobby: Res<Lobby>,
+    time: Res<Time>,
+) {
+    for ServerPlayerSnapshots { tick, players } in snapshots.read() {
+        clock.observe(*tick, time.elapsed_secs_f64());
+
+        for snapshot in players {
+            if let Some(mut buffer) = lobby
+                .players
+                .get(&snapshot.id)
+                .and_then(|player| buffers.get_mut(*player).ok())
+            {
+                buffer.push(*tick, *snapshot);
+            }
+        }
+    }
+}
+
+/// Moves remote players to where they were [`INTERPOLATION_DELAY`] seconds ago.
+pub fn interpolate_remote_players(
+    mut players: Query<(
+        &mut SnapshotBuffer,
+        &mut Transform,
+        &mut HeadRotation,
+        &mut Visibility,
+    )>,
+    clock: Res<ServerClock>,
+    time: Res<Time>,
+) {
+    let Some(render_tick) = clock.render_tick(time.elapsed_secs_f64()) else {
+        return;
+    };
+
+    for (mut buffer, mut transform, mut head, mut visibility) in players.iter_mut() {
+        let Some(snapshot) = buffer.sample(render_tick) else {
+            continue;
+        };
+
+        transform.translation = snapshot.position;
+        transform.rotation = Quat::from_rotation_y(snapshot.yaw);
+        *head = HeadRotation {
+            yaw: snapshot.yaw,
+            pitch: snapshot.pitch,
+        };
+        *visibility = Visibility::Inherited;
+    }
+}
+
+/// Renders the remote players, headless clients go without it.
+pub struct RemotePlayerModelPlugin;
+
+impl Plugin for RemotePlayerModelPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RemotePlayerAssets>().add_systems(
+            Update,
+            attach_remote_player_models.after(spawn_remote_players),
+        );
+    }
+}
@@ -0,0 +1,151 @@
+use bevy::prelude::*;
+use std::collections::VecDeque;
+use crate::{
+    solid_blocks, step_player, Block, BlockMaterialRegistry, BodyState, ChunkMap, ChunkShape,
+    Collider, IsOnGround, NetworkMessage, PlayerInput, Velocity, MOVEMENT_STEP,
+    UNRELIABLE_CHANNEL,
+};
+
+use crate::client::{
+    network::ServerSender,
+    systems::{PlayerController, PlayerMoved},
+};
+
+/// Distance between the predicted and the authoritative position past which the player is
+/// corrected, in blocks.
+const RECONCILE_TOLERANCE: f32 = 0.01;
+
+/// Inputs predicted ahead of the server past which the oldest ones are forgotten, a couple
+/// seconds worth of steps.
+pub const MAX_PREDICTED_INPUTS: usize = 128;
+
+/// Number of inputs sent in every message, so a dropped message doesn't lose any of them.
+const REDUNDANT_INPUTS: usize = 3;
+
+/// Authoritative state of our player sent by the server.
+#[derive(Event, Clone, Copy)]
+pub struct ServerPlayerState {
+    pub sequence: u32,
+    pub state: BodyState,
+}
+
+/// Inputs applied locally and not acknowledged by the server yet, with the state they led to.
+#[derive(Resource, Default)]
+pub struct PredictionHistory {
+    inputs: VecDeque<(PlayerInput, BodyState)>,
+    /// Latest input acknowledged by the server, states for older ones arrived too late.
+    acknowledged: Option<u32>,
+}
+
+impl PredictionHistory {
+    pub fn push(&mut self, input: PlayerInput, state: BodyState) {
+        if self.inputs.len() == MAX_PREDICTED_INPUTS {
+            self.inputs.pop_front();
+        }
+
+        self.inputs.push_back((input, state));
+    }
+
+    /// Drops the inputs up to `sequence` which the server acknowledged with `server_state`.
+    ///
+    /// If our prediction for that input is off by more than [`RECONCILE_TOLERANCE`], or was
+    /// already forgotten, the inputs the server hasn't applied yet are replayed with `step` on
+    /// top of its state and the corrected current state is returned.
+    pub fn reconcile(
+        &mut self,
+        sequence: u32,
+        server_state: BodyState,
+        mut step: impl FnMut(&PlayerInput, &mut BodyState),
+    ) -> Option<BodyState> {
+        if self.acknowledged.is_some_and(|acknowledged| sequence <= acknowledged) {
+            return None;
+        }
+        self.acknowledged = Some(sequence);
+
+        let applied = self
+            .inputs
+            .iter()
+            .take_while(|(input, _)| input.sequence <= sequence)
+            .count();
+        let predicted = self
+            .inputs
+            .drain(..applied)
+            .last()
+            .filter(|(input, _)| input.sequence == sequence);
+
+        // Without a prediction left to compare with, the server state is taken as is
+        if predicted.is_some_and(|(_, predicted)| {
+            predicted.position.distance(server_state.position) <= RECONCILE_TOLERANCE
+        }) {
+            return None;
+        }
+
+        let mut state = server_state;
+        for (input, predicted) in self.inputs.iter_mut() {
+            step(input, &mut state);
+            *predicted = state;
+        }
+
+        Some(state)
+    }
+
+    /// Returns the latest inputs, oldest first.
+    fn latest_inputs(&self, count: usize) -> impl Iterator<Item = PlayerInput> + '_ {
+        self.inputs
+            .iter()
+            .skip(self.inputs.len().saturating_sub(count))
+            .map(|(input, _)| *input)
+    }
+}
+
+/// Records the movement steps predicted this tick and sends their inputs to the server.
+pub fn send_player_inputs(
+    mut moved: EventReader<PlayerMoved>,
+    mut history: ResMut<PredictionHistory>,
+    mut sender: ServerSender,
+) {
+    if moved.is_empty() {
+        return;
+    }
+
+    for PlayerMoved { input, state } in moved.read() {
+        history.push(*input, *state);
+    }
+
+    sender.send(
+        UNRELIABLE_CHANNEL,
+        &NetworkMessage::PlayerInputs(history.latest_inputs(REDUNDANT_INPUTS).collect()),
+    );
+}
+
+/// Corrects the position of the player when the server disagrees with our prediction.
+pub fn reconcile_player_state(
+    mut server_states: EventReader<ServerPlayerState>,
+    mut history: ResMut<PredictionHistory>,
+    mut player: Query<
+        (&mut Transform, &mut Velocity, &mut IsOnGround, &Collider),
+        With<PlayerController>,
+    >,
+    chunks: Res<ChunkMap<Block, ChunkShape>>,
+    registry: Res<BlockMaterialRegistry>,
+) {
+    // States arrive on the unreliable channel, only the most recent one matters
+    let Some(latest) = server_states.read().max_by_key(|state| state.sequence) else {
+        return;
+    };
+
+    let Ok((mut transform, mut velocity, mut on_ground, collider)) = player.single_mut() else {
+        return;
+    };
+
+    let is_solid = solid_blocks(&chunks, &registry);
+    let Some(state) = history.reconcile(latest.sequence, latest.state, |input, state| {
+        step_player(&is_solid, collider, input, state, MOVEMENT_STEP)
+    }) else {
+        return;
+    };
+
+    transform.translation = state.position + Vec3::Y * collider.eye_height;
+    velocity.0 = state.velocity;
+    on_ground.0 = state.on_ground;
+}
@@ -11,9 +11,9 @@ use bevy::{
     time::Time,
     transform::components::Transform,
 };
-use voxel_engine::Chunk;
+use crate::Chunk;
 
-use crate::render::{ChunkMeshingSet, ChunkMeshingTask};
+use crate::client::render::{ChunkMeshingSet, ChunkMeshingTask};
 
 const ANIMATION_DURATION: f32 = 0.8;
 const ANIMATION_HEIGHT: f32 = 128.;
@@ -1,15 +1,17 @@
-use crate::render::{mesh_buffer, ChunkMaterialSingleton, MeshBuffer};
+use crate::client::render::{mesh_buffer, ChunkMaterialSingleton, MeshBuffer};
 use bevy::{
     app::{Plugin, Update},
-    asset::{Assets, RenderAssetUsages},
+    asset::{Assets, Handle, RenderAssetUsages},
     ecs::{
         component::Component,
         entity::Entity,
         query::{Added, With},
+        resource::Resource,
         schedule::{IntoScheduleConfigs, SystemSet},
         system::{Commands, Query, Res, ResMut},
     },
-    math::Vec3,
+    math::{IVec3, Vec3},
+    platform::collections::HashSet,
     pbr::{MeshMaterial3d, NotShadowCaster},
     render::{
         mesh::{Mesh, Mesh3d, PrimitiveTopology},
@@ -23,13 +25,20 @@ use futures_lite::future;
 use once_cell::sync::Lazy;
 use std::cell::RefCell;
 use thread_local::ThreadLocal;
-use voxel_engine::{
-    Block, Chunk, ChunkEntities, ChunkLoadingSet, ChunkMap, ChunkShape, DirtyChunks, TerrainGenSet,
-    CHUNK_SIZE,
+use crate::{
+    Block, BlockBuffer, Chunk, ChunkEntities, ChunkLoadingSet, ChunkMap, ChunkShape, DirtyChunks,
+    LightMap, LightingSet, TerrainGenSet, WorldEditSet, CHUNK_SIZE,
 };
 
 #[derive(Component)]
-pub struct ChunkMeshingTask(Task<Mesh>);
+pub struct ChunkMeshingTask(Task<(Mesh, Mesh)>);
+
+/// Meshes of a chunk, rendered by two child entities of the chunk.
+#[derive(Component)]
+pub struct ChunkMeshes {
+    pub opaque: Handle<Mesh>,
+    pub translucent: Handle<Mesh>,
+}
 
 #[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug, Hash, SystemSet)]
 pub struct ChunkMeshingSet;
@@ -38,16 +47,21 @@ pub struct WorldMeshingPlugin;
 
 impl Plugin for WorldMeshingPlugin {
     fn build(&self, app: &mut bevy::app::App) {
-        app.configure_sets(
-            Update,
-            ChunkMeshingSet.after(TerrainGenSet).after(ChunkLoadingSet),
-        )
-        .add_systems(
-            Update,
-            (prepare_chunks, queue_mesh_tasks, process_mesh_tasks)
-                .chain()
-                .in_set(ChunkMeshingSet),
-        );
+        app.init_resource::<PendingMeshes>()
+            .configure_sets(
+                Update,
+                ChunkMeshingSet
+                    .after(TerrainGenSet)
+                    .after(ChunkLoadingSet)
+                    .after(WorldEditSet)
+                    .after(LightingSet),
+            )
+            .add_systems(
+                Update,
+                (prepare_chunks, queue_mesh_tasks, process_mesh_tasks)
+                    .chain()
+                    .in_set(ChunkMeshingSet),
+            );
     }
 }
 
@@ -58,81 +72,134 @@ pub fn prepare_chunks(
     mut commands: Commands,
 ) {
     for (chunk, chunk_key) in chunks.iter() {
-        let mut entity_commands = commands.entity(chunk);
-
-        entity_commands.insert((
-            Mesh3d(meshes.add(Mesh::new(
+        let chunk_meshes = ChunkMeshes {
+            opaque: meshes.add(Mesh::new(
+                PrimitiveTopology::TriangleList,
+                RenderAssetUsages::default(),
+            )),
+            translucent: meshes.add(Mesh::new(
                 PrimitiveTopology::TriangleList,
                 RenderAssetUsages::default(),
-            ))),
-            MeshMaterial3d((**material).clone()),
-            Transform::from_translation(chunk_key.0.as_vec3()),
-            Visibility::Hidden,
-            InheritedVisibility::default(),
-            ViewVisibility::default(),
-            Aabb::from_min_max(Vec3::ZERO, Vec3::splat(CHUNK_SIZE as f32)),
-        ));
-
-        if chunk_key.0.y <= 64 {
-            entity_commands.insert(NotShadowCaster);
-        }
+            )),
+        };
+
+        commands
+            .entity(chunk)
+            .insert((
+                Transform::from_translation(chunk_key.0.as_vec3()),
+                Visibility::Hidden,
+                InheritedVisibility::default(),
+                ViewVisibility::default(),
+            ))
+            .with_children(|parent| {
+                let mut opaque = parent.spawn((
+                    Mesh3d(chunk_meshes.opaque.clone()),
+                    MeshMaterial3d(material.opaque.clone()),
+                    Transform::default(),
+                    Visibility::Inherited,
+                    Aabb::from_min_max(Vec3::ZERO, Vec3::splat(CHUNK_SIZE as f32)),
+                ));
+
+                if chunk_key.0.y <= 64 {
+                    opaque.insert(NotShadowCaster);
+                }
+
+                parent.spawn((
+                    Mesh3d(chunk_meshes.translucent.clone()),
+                    MeshMaterial3d(material.translucent.clone()),
+                    Transform::default(),
+                    Visibility::Inherited,
+                    Aabb::from_min_max(Vec3::ZERO, Vec3::splat(CHUNK_SIZE as f32)),
+                    NotShadowCaster,
+                ));
+            })
+            .insert(chunk_meshes);
     }
 }
 
 static SHARED_MESH_BUFFERS: Lazy<ThreadLocal<RefCell<MeshBuffer<Block, ChunkShape>>>> =
     Lazy::new(ThreadLocal::default);
 
+/// Chunks meshed per frame at most, copying their blocks and light for the meshing tasks takes
+/// a while.
+const MAX_MESH_TASKS_PER_FRAME: usize = 8;
+
+/// Dirty chunks waiting to be meshed, chunks are only meshed once they are lit.
+#[derive(Resource, Default)]
+pub struct PendingMeshes(HashSet<IVec3>);
+
 fn queue_mesh_tasks(
     mut commands: Commands,
+    mut pending: ResMut<PendingMeshes>,
     dirty_chunks: Res<DirtyChunks>,
     chunk_entities: Res<ChunkEntities>,
     chunks: Res<ChunkMap<Block, ChunkShape>>,
+    light_map: Res<LightMap>,
 ) {
     let task_pool = AsyncComputeTaskPool::get();
 
-    dirty_chunks
-        .iter_dirty()
-        .filter_map(|key| chunk_entities.entity(*key).map(|entity| (key, entity)))
-        .filter_map(|(key, entity)| {
-            chunks
-                .buffer_at(*key)
-                .map(|buffer| (buffer.clone(), entity))
-        })
-        .map(|(buffer, entity)| {
-            (
-                entity,
-                ChunkMeshingTask(task_pool.spawn(async move {
-                    let mut mesh_buffers = SHARED_MESH_BUFFERS
-                        .get_or(|| {
-                            RefCell::new(MeshBuffer::<Block, ChunkShape>::new(ChunkShape {}))
-                        })
-                        .borrow_mut();
-
-                    let mut mesh = Mesh::new(
-                        PrimitiveTopology::TriangleList,
-                        RenderAssetUsages::default(),
-                    );
-                    mesh_buffer(&buffer, &mut mesh_buffers, &mut mesh, 1.0);
-
-                    mesh
-                })),
-            )
+    pending.0.extend(dirty_chunks.iter_dirty().copied());
+    pending.0.retain(|key| chunks.exists(*key));
+
+    let ready: Vec<(IVec3, Entity, BlockBuffer<Block, ChunkShape>)> = pending
+        .0
+        .iter()
+        .filter(|key| light_map.contains(**key))
+        .filter_map(|key| {
+            let entity = chunk_entities.entity(*key)?;
+            Some((*key, entity, chunks.buffer_at(*key)?.clone()))
         })
-        .for_each(|(entity, task)| {
-            commands.entity(entity).insert(task);
+        .take(MAX_MESH_TASKS_PER_FRAME)
+        .collect();
+
+    for (key, entity, buffer) in ready {
+        pending.0.remove(&key);
+
+        let neighbours = chunks.neighbour_borders(key);
+        let light = light_map.padded_light(key);
+
+        let task = task_pool.spawn(async move {
+            let mut mesh_buffers = SHARED_MESH_BUFFERS
+                .get_or(|| RefCell::new(MeshBuffer::<Block, ChunkShape>::new(ChunkShape {})))
+                .borrow_mut();
+
+            let mut opaque = Mesh::new(
+                PrimitiveTopology::TriangleList,
+                RenderAssetUsages::default(),
+            );
+            let mut translucent = Mesh::new(
+                PrimitiveTopology::TriangleList,
+                RenderAssetUsages::default(),
+            );
+            mesh_buffer(
+                &buffer,
+                &neighbours,
+                &light,
+                &mut mesh_buffers,
+                &mut opaque,
+                &mut translucent,
+                1.0,
+            );
+
+            (opaque, translucent)
         });
+        commands.entity(entity).insert(ChunkMeshingTask(task));
+    }
 }
 
 fn process_mesh_tasks(
     mut meshes: ResMut<Assets<Mesh>>,
-    mut chunk_query: Query<(Entity, &Mesh3d, &mut ChunkMeshingTask), With<Chunk>>,
+    mut chunk_query: Query<(Entity, &ChunkMeshes, &mut ChunkMeshingTask), With<Chunk>>,
     mut commands: Commands,
 ) {
     chunk_query
         .iter_mut()
-        .for_each(|(entity, handle, mut mesh_task)| {
-            if let Some(mesh) = future::block_on(future::poll_once(&mut mesh_task.0)) {
-                *meshes.get_mut(handle.id()).unwrap() = mesh;
+        .for_each(|(entity, chunk_meshes, mut mesh_task)| {
+            if let Some((opaque, translucent)) =
+                future::block_on(future::poll_once(&mut mesh_task.0))
+            {
+                *meshes.get_mut(chunk_meshes.opaque.id()).unwrap() = opaque;
+                *meshes.get_mut(chunk_meshes.translucent.id()).unwrap() = translucent;
                 commands.entity(entity).remove::<ChunkMeshingTask>();
             }
         });
@@ -10,16 +10,16 @@ use bevy::{
         world::FromWorld,
     },
     pbr::{Material, MaterialPlugin},
-    prelude::{Deref, DerefMut},
     reflect::TypePath,
     render::{
+        alpha::AlphaMode,
         extract_component::ExtractComponent,
         mesh::{Mesh, MeshVertexAttribute, VertexFormat},
         render_resource::{AsBindGroup, ShaderType},
     },
     utils::default,
 };
-use voxel_engine::BlockMaterialRegistry;
+use crate::BlockMaterialRegistry;
 
 #[derive(Component, Clone, Default, ExtractComponent)]
 pub struct BlockTerrainMesh;
@@ -45,6 +45,7 @@ pub struct GpuTerrainUniforms {
     pub render_distance: u32,
     #[uniform(1)]
     pub materials: [GpuBlockMaterial; 256],
+    pub alpha_mode: AlphaMode,
 }
 
 impl Default for GpuTerrainUniforms {
@@ -52,11 +53,16 @@ impl Default for GpuTerrainUniforms {
         Self {
             render_distance: 16,
             materials: [default(); 256],
+            alpha_mode: AlphaMode::Opaque,
         }
     }
 }
 
 impl Material for GpuTerrainUniforms {
+    fn alpha_mode(&self) -> AlphaMode {
+        self.alpha_mode
+    }
+
     fn vertex_shader() -> bevy::render::render_resource::ShaderRef {
         "shaders/terrain_pipeline.wgsl".into()
     }
@@ -84,13 +90,23 @@ impl Material for GpuTerrainUniforms {
     }
 }
 
-#[derive(Resource, Deref, DerefMut)]
-pub struct ChunkMaterialSingleton(Handle<GpuTerrainUniforms>);
+/// Terrain materials shared by every chunk, translucent faces are drawn with alpha blending.
+#[derive(Resource)]
+pub struct ChunkMaterialSingleton {
+    pub opaque: Handle<GpuTerrainUniforms>,
+    pub translucent: Handle<GpuTerrainUniforms>,
+}
 
 impl FromWorld for ChunkMaterialSingleton {
     fn from_world(world: &mut bevy::ecs::world::World) -> Self {
         let mut materials = world.resource_mut::<Assets<GpuTerrainUniforms>>();
-        Self(materials.add(GpuTerrainUniforms::default()))
+        Self {
+            opaque: materials.add(GpuTerrainUniforms::default()),
+            translucent: materials.add(GpuTerrainUniforms {
+                alpha_mode: AlphaMode::Blend,
+                ..Default::default()
+            }),
+        }
     }
 }
 
@@ -112,63 +128,58 @@ impl Plugin for ChunkMaterialPlugin {
     }
 }
 
+fn terrain_uniforms(
+    block_materials: &BlockMaterialRegistry,
+    alpha_mode: AlphaMode,
+) -> GpuTerrainUniforms {
+    let mut gpu_mats = GpuTerrainUniforms {
+        render_distance: 32,
+        materials: [GpuBlockMaterial {
+            base_color: Color::WHITE.into(),
+            flags: 0,
+            ..Default::default()
+        }; 256],
+        alpha_mode,
+    };
+
+    for (index, material) in block_materials.iter_materials().into_iter().enumerate() {
+        gpu_mats.materials[index].base_color = material.base_color.into();
+        gpu_mats.materials[index].flags = material.flags.bits();
+        gpu_mats.materials[index].emissive = material.emissive.into();
+        gpu_mats.materials[index].perceptual_roughness = material.perceptual_roughness;
+        gpu_mats.materials[index].metallic = material.metallic;
+        gpu_mats.materials[index].reflectance = material.reflectance;
+    }
+
+    gpu_mats
+}
+
 fn update_chunk_material_singleton(
     mut commands: Commands,
     mut materials: ResMut<Assets<GpuTerrainUniforms>>,
     chunk_material: Option<Res<ChunkMaterialSingleton>>,
     block_materials: Res<BlockMaterialRegistry>,
 ) {
-    let mut needs_new_asset = false;
-    let handle = chunk_material.as_ref().map(|cm| cm.0.clone());
-
-    if let Some(ref h) = handle {
-        if !materials.contains(h) {
-            needs_new_asset = true;
-        }
-    } else {
-        needs_new_asset = true;
-    }
-
-    if needs_new_asset {
-        let mut gpu_mats = GpuTerrainUniforms {
-            render_distance: 32,
-            materials: [GpuBlockMaterial {
-                base_color: Color::WHITE.into(),
-                flags: 0,
-                ..Default::default()
-            }; 256],
-        };
-
-        for (index, material) in block_materials.iter_materials().into_iter().enumerate() {
-            gpu_mats.materials[index].base_color = material.base_color.into();
-            gpu_mats.materials[index].flags = material.flags.bits();
-            gpu_mats.materials[index].emissive = material.emissive.into();
-            gpu_mats.materials[index].perceptual_roughness = material.perceptual_roughness;
-            gpu_mats.materials[index].metallic = material.metallic;
-            gpu_mats.materials[index].reflectance = material.reflectance;
-        }
-
-        let new_handle = materials.add(gpu_mats);
-        commands.insert_resource(ChunkMaterialSingleton(new_handle.clone()));
-    } else if let Some(ref h) = handle {
-        if let Some(gpu_mats) = materials.get_mut(h) {
-            *gpu_mats = GpuTerrainUniforms {
-                render_distance: 32,
-                materials: [GpuBlockMaterial {
-                    base_color: Color::WHITE.into(),
-                    flags: 0,
-                    ..Default::default()
-                }; 256],
-            };
-
-            for (index, material) in block_materials.iter_materials().into_iter().enumerate() {
-                gpu_mats.materials[index].base_color = material.base_color.into();
-                gpu_mats.materials[index].flags = material.flags.bits();
-                gpu_mats.materials[index].emissive = material.emissive.into();
-                gpu_mats.materials[index].perceptual_roughness = material.perceptual_roughness;
-                gpu_mats.materials[index].metallic = material.metallic;
-                gpu_mats.materials[index].reflectance = material.reflectance;
+    let opaque = terrain_uniforms(&block_materials, AlphaMode::Opaque);
+    let translucent = terrain_uniforms(&block_materials, AlphaMode::Blend);
+
+    match chunk_material {
+        Some(chunk_material)
+            if materials.contains(&chunk_material.opaque)
+                && materials.contains(&chunk_material.translucent) =>
+        {
+            if let Some(gpu_mats) = materials.get_mut(&chunk_material.opaque) {
+                *gpu_mats = opaque;
             }
+            if let Some(gpu_mats) = materials.get_mut(&chunk_material.translucent) {
+                *gpu_mats = translucent;
+            }
+        }
+        _ => {
+            commands.insert_resource(ChunkMaterialSingleton {
+                opaque: materials.add(opaque),
+                translucent: materials.add(translucent),
+            });
         }
     }
 }
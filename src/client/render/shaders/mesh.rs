@@ -1,80 +1,386 @@
-use bevy::render::mesh::{Indices, Mesh, VertexAttributeValues};
-use block_mesh::{greedy_quads, GreedyQuadsBuffer, RIGHT_HANDED_Y_UP_CONFIG};
-use ndcopy::copy3;
+use bevy::{
+    math::IVec3,
+    render::mesh::{Indices, Mesh, VertexAttributeValues},
+};
+use block_mesh::{
+    greedy_quads, GreedyQuadsBuffer, MergeVoxel, Voxel, VoxelVisibility, RIGHT_HANDED_Y_UP_CONFIG,
+};
 use ndshape::{RuntimeShape, Shape};
 use std::marker::PhantomData;
-use voxel_engine::{BlockBuffer, MaterialBlock};
+use crate::{BlockBuffer, MaterialBlock, NeighbourBorders};
 
-use crate::render::BlockTerrainMesh;
+use crate::client::render::BlockTerrainMesh;
+
+/// Face normals in the order of the faces of [`RIGHT_HANDED_Y_UP_CONFIG`], matching the
+/// `VOXEL_NORMALS` array of `voxel_data.wgsl`.
+const FACE_NORMALS: [[i32; 3]; 6] = [
+    [-1, 0, 0],
+    [0, -1, 0],
+    [0, 0, -1],
+    [1, 0, 0],
+    [0, 1, 0],
+    [0, 0, 1],
+];
+
+/// A block along with the light of its six neighbours and the ambient occlusion of its faces,
+/// faces are only merged together when they are lit and occluded the same way.
+///
+/// The visibility seen by the mesher is set for every meshing pass, see [`mesh_pass`].
+#[derive(Clone, Copy, PartialEq)]
+pub struct LitVoxel<T> {
+    block: T,
+    neighbour_light: u64,
+    occlusion: u64,
+    visibility: VoxelVisibility,
+}
+
+impl<T: Default> Default for LitVoxel<T> {
+    fn default() -> Self {
+        Self {
+            block: T::default(),
+            neighbour_light: 0,
+            occlusion: 0,
+            visibility: VoxelVisibility::Empty,
+        }
+    }
+}
+
+impl<T: Voxel> Voxel for LitVoxel<T> {
+    #[inline]
+    fn get_visibility(&self) -> VoxelVisibility {
+        self.visibility
+    }
+}
+
+impl<T: MergeVoxel> MergeVoxel for LitVoxel<T> {
+    type MergeValue = (T::MergeValue, u64, u64);
+
+    #[inline]
+    fn merge_value(&self) -> Self::MergeValue {
+        (self.block.merge_value(), self.neighbour_light, self.occlusion)
+    }
+}
+
+/// Returns the tangent axes of a face whose normal lies along `axis`.
+#[inline]
+const fn tangent_axes(axis: usize) -> (usize, usize) {
+    match axis {
+        0 => (1, 2),
+        1 => (0, 2),
+        _ => (0, 1),
+    }
+}
+
+/// Classic voxel corner ambient occlusion, from 0 (fully occluded) to 3 (unoccluded).
+#[inline]
+const fn vertex_ao(side1: bool, side2: bool, corner: bool) -> u32 {
+    if side1 && side2 {
+        0
+    } else {
+        3 - (side1 as u32 + side2 as u32 + corner as u32)
+    }
+}
+
+/// Computes the ambient occlusion of the corners of a quad from the voxels in front of its face.
+///
+/// Corners are returned in the order of `quad_positions`.
+fn quad_ao<T: Voxel>(
+    scratch: &[LitVoxel<T>],
+    shape: &RuntimeShape<u32, 3>,
+    face: usize,
+    quad_positions: &[[f32; 3]; 4],
+    scale: f32,
+) -> [u32; 4] {
+    let corners = quad_positions.map(|pos| pos.map(|x| (x / scale).round() as i32));
+    let center = [0, 1, 2].map(|axis| corners.iter().map(|c| c[axis]).sum::<i32>() as f32 / 4.);
+
+    let normal = FACE_NORMALS[face];
+    let axis = face % 3;
+    let (a, b) = tangent_axes(axis);
+    let is_opaque = |pos: [i32; 3]| {
+        scratch[shape.linearize(pos.map(|x| x as u32)) as usize]
+            .block
+            .get_visibility()
+            == VoxelVisibility::Opaque
+    };
+
+    corners.map(|corner| {
+        // Cells touching the corner on each tangent axis, inside and outside of the quad
+        let cells = |i: usize| {
+            if (corner[i] as f32) < center[i] {
+                (corner[i], corner[i] - 1)
+            } else {
+                (corner[i] - 1, corner[i])
+            }
+        };
+        let (inside_a, outside_a) = cells(a);
+        let (inside_b, outside_b) = cells(b);
+
+        let mut front = [0; 3];
+        front[axis] = if normal[axis] > 0 {
+            corner[axis]
+        } else {
+            corner[axis] - 1
+        };
+
+        let cell = |u: i32, v: i32| {
+            let mut pos = front;
+            pos[a] = u;
+            pos[b] = v;
+            is_opaque(pos)
+        };
+
+        vertex_ao(
+            cell(outside_a, inside_b),
+            cell(inside_a, outside_b),
+            cell(outside_a, outside_b),
+        )
+    })
+}
 
 pub struct MeshBuffer<T, S: Shape<3, Coord = u32>>
 where
-    T: Copy + Default + MaterialBlock,
+    T: Copy + Default + PartialEq + MaterialBlock,
 {
-    scratch_buffer: BlockBuffer<T, RuntimeShape<u32, 3>>,
+    scratch_buffer: BlockBuffer<LitVoxel<T>, RuntimeShape<u32, 3>>,
     greedy_buffer: GreedyQuadsBuffer,
     _phantom: PhantomData<S>,
 }
 
 impl<T, S: Shape<3, Coord = u32>> MeshBuffer<T, S>
 where
-    T: Copy + Default + MaterialBlock,
+    T: Copy + Default + PartialEq + MaterialBlock,
 {
     pub fn new(shape: S) -> Self {
         let padded_shape = RuntimeShape::<u32, 3>::new(shape.as_array().map(|x| x + 2));
 
         Self {
             greedy_buffer: GreedyQuadsBuffer::new(padded_shape.size() as usize),
-            scratch_buffer: BlockBuffer::<T, RuntimeShape<u32, 3>>::new_empty(padded_shape),
+            scratch_buffer: BlockBuffer::<LitVoxel<T>, RuntimeShape<u32, 3>>::new_empty(
+                padded_shape,
+            ),
             _phantom: Default::default(),
         }
     }
 }
 
+/// Meshes the opaque and translucent blocks of a chunk buffer into separate meshes.
+///
+/// `light` holds the packed light of the chunk padded by one voxel on every side, laid out like
+/// the scratch buffer of `mesh_buffers`. `neighbours` holds the blocks of the neighbouring chunks
+/// touching this one, faces hidden by them are culled and they shade the border blocks.
 pub fn mesh_buffer<T, S>(
     buffer: &BlockBuffer<T, S>,
+    neighbours: &NeighbourBorders<T>,
+    light: &[u8],
+    mesh_buffers: &mut MeshBuffer<T, S>,
+    opaque_mesh: &mut Mesh,
+    translucent_mesh: &mut Mesh,
+    scale: f32,
+) where
+    T: Copy + Default + PartialEq + MaterialBlock,
+    S: Shape<3, Coord = u32>,
+{
+    let dst_shape = mesh_buffers.scratch_buffer.shape().clone();
+    let light_at = |pos: [u32; 3]| light[dst_shape.linearize(pos) as usize];
+
+    let blocks = buffer.slice();
+    let scratch = mesh_buffers.scratch_buffer.slice_mut();
+    let [size_x, size_y, size_z] = buffer.shape().as_array();
+
+    for x in 0..size_x {
+        for y in 0..size_y {
+            for z in 0..size_z {
+                let padded = [x + 1, y + 1, z + 1];
+                let neighbour_light = FACE_NORMALS
+                    .iter()
+                    .enumerate()
+                    .fold(0u64, |packed, (face, normal)| {
+                        let neighbour = [0, 1, 2].map(|axis| (padded[axis] as i32 + normal[axis]) as u32);
+                        packed | (light_at(neighbour) as u64) << (face * 8)
+                    });
+
+                scratch[dst_shape.linearize(padded) as usize] = LitVoxel {
+                    block: blocks[buffer.shape().linearize([x, y, z]) as usize],
+                    neighbour_light,
+                    ..Default::default()
+                };
+            }
+        }
+    }
+
+    // Fill the padding from the neighbouring chunks, the planes of the faces overlap on the edges
+    // and corners of the padding
+    let dims = [size_x, size_y, size_z];
+    for (face, normal) in FACE_NORMALS.iter().enumerate() {
+        let axis = face % 3;
+        let (a, b) = tangent_axes(axis);
+
+        let mut padded = [0; 3];
+        padded[axis] = if normal[axis] < 0 { 0 } else { dims[axis] + 1 };
+
+        for v in 0..dims[b] + 2 {
+            for u in 0..dims[a] + 2 {
+                padded[a] = u;
+                padded[b] = v;
+
+                let pos = IVec3::from(padded.map(|x| x as i32)) - IVec3::ONE;
+                scratch[dst_shape.linearize(padded) as usize] = LitVoxel {
+                    block: neighbours.block_at(pos).unwrap_or_default(),
+                    ..Default::default()
+                };
+            }
+        }
+    }
+
+    // Faces only get merged when their corners are uniformly occluded, otherwise the occlusion
+    // would be stretched across the merged quad
+    let is_opaque = |scratch: &[LitVoxel<T>], pos: [i32; 3]| {
+        scratch[dst_shape.linearize(pos.map(|x| x as u32)) as usize]
+            .block
+            .get_visibility()
+            == VoxelVisibility::Opaque
+    };
+
+    for x in 1..=size_x as i32 {
+        for y in 1..=size_y as i32 {
+            for z in 1..=size_z as i32 {
+                let index = dst_shape.linearize([x as u32, y as u32, z as u32]) as usize;
+                if scratch[index].block.get_visibility() == VoxelVisibility::Empty {
+                    continue;
+                }
+
+                let mut occlusion = 0u64;
+                let mut uniform = true;
+
+                for (face, normal) in FACE_NORMALS.iter().enumerate() {
+                    let front = [x + normal[0], y + normal[1], z + normal[2]];
+                    if is_opaque(scratch, front) {
+                        continue;
+                    }
+
+                    let axis = face % 3;
+                    let (a, b) = tangent_axes(axis);
+                    let corners = [(-1, -1), (1, -1), (-1, 1), (1, 1)].map(|(sa, sb)| {
+                        let mut side1 = front;
+                        let mut side2 = front;
+                        side1[a] += sa;
+                        side2[b] += sb;
+                        let mut corner = side1;
+                        corner[b] += sb;
+
+                        vertex_ao(
+                            is_opaque(scratch, side1),
+                            is_opaque(scratch, side2),
+                            is_opaque(scratch, corner),
+                        )
+                    });
+
+                    if corners.iter().all(|ao| *ao == corners[0]) {
+                        occlusion |= (corners[0] as u64 + 1) << (face * 3);
+                    } else {
+                        uniform = false;
+                    }
+                }
+
+                // A unique value keeps non-uniformly occluded voxels from merging at all
+                if !uniform {
+                    occlusion |= (index as u64 + 1) << 18;
+                }
+
+                scratch[index].occlusion = occlusion;
+            }
+        }
+    }
+
+    // Opaque blocks are meshed first, then every kind of translucent block on its own so that
+    // faces between identical translucent blocks get culled
+    let mut translucent_blocks: Vec<T> = Vec::new();
+    blocks
+        .iter()
+        .filter(|block| block.get_visibility() == VoxelVisibility::Translucent)
+        .for_each(|block| {
+            if !translucent_blocks.contains(block) {
+                translucent_blocks.push(*block);
+            }
+        });
+
+    let mut opaque = MeshData::default();
+    mesh_pass(mesh_buffers, None, &mut opaque, scale);
+
+    let mut translucent = MeshData::default();
+    for block in translucent_blocks {
+        mesh_pass(mesh_buffers, Some(block), &mut translucent, scale);
+    }
+
+    opaque.insert_into(opaque_mesh);
+    translucent.insert_into(translucent_mesh);
+}
+
+#[derive(Default)]
+struct MeshData {
+    indices: Vec<u32>,
+    positions: Vec<[f32; 3]>,
+    data: Vec<u32>,
+}
+
+impl MeshData {
+    fn insert_into(self, mesh: &mut Mesh) {
+        mesh.insert_attribute(
+            Mesh::ATTRIBUTE_POSITION,
+            VertexAttributeValues::Float32x3(self.positions),
+        );
+
+        mesh.insert_attribute(
+            BlockTerrainMesh::ATTRIBUTE_DATA,
+            VertexAttributeValues::Uint32(self.data),
+        );
+
+        mesh.insert_indices(Indices::U32(self.indices));
+    }
+}
+
+/// Greedy meshes the faces of the blocks of the scratch buffer into `mesh_data`.
+///
+/// Only opaque blocks are meshed when `pass` is `None`, otherwise only the blocks equal to the
+/// translucent block passed are, with their faces culled against opaque blocks and each other.
+fn mesh_pass<T, S>(
     mesh_buffers: &mut MeshBuffer<T, S>,
-    render_mesh: &mut Mesh,
+    pass: Option<T>,
+    mesh_data: &mut MeshData,
     scale: f32,
 ) where
-    T: Copy + Default + MaterialBlock,
+    T: Copy + Default + PartialEq + MaterialBlock,
     S: Shape<3, Coord = u32>,
 {
     mesh_buffers
-        .greedy_buffer
-        .reset(buffer.shape().size() as usize);
+        .scratch_buffer
+        .slice_mut()
+        .iter_mut()
+        .for_each(|voxel| {
+            voxel.visibility = match (voxel.block.get_visibility(), pass) {
+                (VoxelVisibility::Opaque, _) => VoxelVisibility::Opaque,
+                (VoxelVisibility::Translucent, Some(block)) if voxel.block == block => {
+                    VoxelVisibility::Translucent
+                }
+                _ => VoxelVisibility::Empty,
+            };
+        });
 
     let dst_shape = mesh_buffers.scratch_buffer.shape().clone();
+    mesh_buffers.greedy_buffer.reset(dst_shape.size() as usize);
 
-    copy3(
-        buffer.shape().as_array(),
-        buffer.slice(),
-        buffer.shape(),
-        [0; 3],
-        mesh_buffers.scratch_buffer.slice_mut(),
-        &dst_shape,
-        [1; 3],
-    );
+    let scratch = mesh_buffers.scratch_buffer.slice();
 
     greedy_quads(
-        mesh_buffers.scratch_buffer.slice(),
-        mesh_buffers.scratch_buffer.shape(),
+        &scratch,
+        &dst_shape,
         [0; 3],
-        mesh_buffers
-            .scratch_buffer
-            .shape()
-            .as_array()
-            .map(|axis| axis - 1),
+        dst_shape.as_array().map(|axis| axis - 1),
         &RIGHT_HANDED_Y_UP_CONFIG.faces,
         &mut mesh_buffers.greedy_buffer,
     );
 
-    let num_indices = mesh_buffers.greedy_buffer.quads.num_quads() * 6;
-    let num_vertices = mesh_buffers.greedy_buffer.quads.num_quads() * 4;
-    let mut indices: Vec<u32> = Vec::with_capacity(num_indices);
-    let mut positions: Vec<[f32; 3]> = Vec::with_capacity(num_vertices);
-    let mut data: Vec<u32> = Vec::with_capacity(num_vertices);
-
     for (block_face_normal_index, (group, face)) in mesh_buffers
         .greedy_buffer
         .quads
@@ -85,26 +391,39 @@ pub fn mesh_buffer<T, S>(
         .enumerate()
     {
         for quad in group {
-            indices.extend_from_slice(&face.quad_mesh_indices(positions.len() as u32));
-            positions.extend_from_slice(&face.quad_mesh_positions(quad, scale));
-            data.extend_from_slice(
-                &[(block_face_normal_index as u32) << 8u32
-                    | buffer
-                        .block_at(quad.minimum.map(|x| x - 1).into())
-                        .as_mat_id() as u32; 4],
-            );
-        }
-    }
+            let voxel = scratch[dst_shape.linearize(quad.minimum) as usize];
 
-    render_mesh.insert_attribute(
-        Mesh::ATTRIBUTE_POSITION,
-        VertexAttributeValues::Float32x3(positions),
-    );
+            // Opaque faces are also generated by translucent passes to cull against them
+            if pass.is_some_and(|block| voxel.block != block) {
+                continue;
+            }
 
-    render_mesh.insert_attribute(
-        BlockTerrainMesh::ATTRIBUTE_DATA,
-        VertexAttributeValues::Uint32(data),
-    );
+            let face_light = voxel.neighbour_light >> (block_face_normal_index * 8) & 0xFF;
+
+            let quad_positions = face.quad_mesh_positions(quad, scale);
+            let ao = quad_ao(&scratch, &dst_shape, block_face_normal_index, &quad_positions, scale);
 
-    render_mesh.insert_indices(Indices::U32(indices.clone()));
+            let start = mesh_data.positions.len() as u32;
+            let quad_indices = face.quad_mesh_indices(start);
+
+            // Split the quad along the brightest diagonal so occlusion is interpolated evenly
+            if ao[0] + ao[3] > ao[1] + ao[2] {
+                let counter_clockwise = quad_indices[1] == start + 1;
+                mesh_data.indices.extend_from_slice(&if counter_clockwise {
+                    [start, start + 1, start + 3, start, start + 3, start + 2]
+                } else {
+                    [start, start + 3, start + 1, start, start + 2, start + 3]
+                });
+            } else {
+                mesh_data.indices.extend_from_slice(&quad_indices);
+            }
+
+            mesh_data.positions.extend_from_slice(&quad_positions);
+
+            let face_data = (face_light as u32) << 16u32
+                | (block_face_normal_index as u32) << 8u32
+                | voxel.block.as_mat_id() as u32;
+            mesh_data.data.extend(ao.map(|ao| face_data | ao << 11u32));
+        }
+    }
 }
@@ -0,0 +1,251 @@
+use bevy::{
+    input::{
+        keyboard::{Key, KeyboardInput},
+        ButtonState,
+    },
+    prelude::*,
+};
+use std::collections::VecDeque;
+use crate::MAX_CHAT_LENGTH;
+
+use crate::client::systems::{handle_player_input, MovementInput};
+
+/// Lines kept in the chat, older ones scroll away.
+const MAX_CHAT_LINES: usize = 10;
+
+const CHAT_FONT_SIZE: f32 = 16.0;
+const CHAT_INPUT_BACKGROUND: Color = Color::srgba(0.0, 0.0, 0.0, 0.4);
+
+/// Lines shown in the chat, oldest first.
+#[derive(Resource, Default)]
+pub struct ChatLog(VecDeque<String>);
+
+impl ChatLog {
+    pub fn push(&mut self, line: String) {
+        if self.0.len() == MAX_CHAT_LINES {
+            self.0.pop_front();
+        }
+
+        self.0.push_back(line);
+    }
+}
+
+/// Line being typed by the player, `None` while the chat is closed.
+#[derive(Resource, Default)]
+pub struct ChatInput {
+    text: Option<String>,
+    /// Words the server suggested for the last word of the line.
+    suggestions: Vec<String>,
+}
+
+impl ChatInput {
+    pub fn is_open(&self) -> bool {
+        self.text.is_some()
+    }
+
+    /// Completes the last word of the line with the suggestions the server sent for `input`,
+    /// unless the player kept typing since asking.
+    pub fn complete(&mut self, input: &str, suggestions: Vec<String>) {
+        let Some(text) = self.text.as_mut().filter(|text| text.as_str() == input) else {
+            return;
+        };
+
+        let start = text.rfind(' ').map_or(0, |index| index + 1);
+        match suggestions.as_slice() {
+            [] => {}
+            [only] => {
+                text.replace_range(start.., only);
+                text.push(' ');
+            }
+            [first, rest @ ..] => {
+                let common = rest.iter().fold(first.as_str(), |common, suggestion| {
+                    common_prefix(common, suggestion)
+                });
+                text.replace_range(start.., common);
+            }
+        }
+
+        self.suggestions = if suggestions.len() > 1 {
+            suggestions
+        } else {
+            Vec::new()
+        };
+    }
+
+    fn close(&mut self) {
+        self.text = None;
+        self.suggestions.clear();
+    }
+}
+
+fn common_prefix<'a>(a: &'a str, b: &str) -> &'a str {
+    let len = a
+        .char_indices()
+        .zip(b.chars())
+        .take_while(|((_, x), y)| x == y)
+        .last()
+        .map_or(0, |((index, c), _)| index + c.len_utf8());
+
+    &a[..len]
+}
+
+/// Sent when the player submits a line.
+#[derive(Event, Clone)]
+pub struct ChatSubmitted(pub String);
+
+/// Sent when the player presses tab while typing a command.
+#[derive(Event, Clone)]
+pub struct CompletionRequested(pub String);
+
+/// Run condition telling whether the keys and the mouse control the player, rather than the chat.
+pub fn chat_closed(input: Option<Res<ChatInput>>) -> bool {
+    input.is_none_or(|input| !input.is_open())
+}
+
+#[derive(Component)]
+struct ChatLogText;
+
+#[derive(Component)]
+struct ChatInputText;
+
+fn setup_chat(mut commands: Commands) {
+    let font = TextFont {
+        font_size: CHAT_FONT_SIZE,
+        ..Default::default()
+    };
+
+    commands
+        .spawn(Node {
+            position_type: PositionType::Absolute,
+            left: Val::Px(8.0),
+            bottom: Val::Px(8.0),
+            width: Val::Percent(40.0),
+            flex_direction: FlexDirection::Column,
+            row_gap: Val::Px(4.0),
+            ..Default::default()
+        })
+        .with_children(|chat| {
+            chat.spawn((Text::default(), font.clone(), ChatLogText));
+            chat.spawn((
+                Text::default(),
+                font,
+                BackgroundColor(CHAT_INPUT_BACKGROUND),
+                Visibility::Hidden,
+                ChatInputText,
+            ));
+        });
+}
+
+fn push_text(text: &mut String, typed: &str) {
+    for c in typed.chars().filter(|c| !c.is_control()) {
+        if text.chars().count() < MAX_CHAT_LENGTH {
+            text.push(c);
+        }
+    }
+}
+
+pub fn type_in_chat(
+    mut keyboard: EventReader<KeyboardInput>,
+    mut input: ResMut<ChatInput>,
+    mut submitted: EventWriter<ChatSubmitted>,
+    mut completions: EventWriter<CompletionRequested>,
+    mut player: Query<&mut MovementInput>,
+) {
+    for event in keyboard.read() {
+        if event.state != ButtonState::Pressed {
+            continue;
+        }
+
+        let Some(text) = input.text.as_mut() else {
+            let opened = match &event.logical_key {
+                Key::Character(typed) if typed == "/" => Some("/".to_string()),
+                _ if matches!(event.key_code, KeyCode::KeyT | KeyCode::Enter) => {
+                    Some(String::new())
+                }
+                _ => None,
+            };
+
+            if opened.is_some() {
+                // The player would keep walking with the keys held when the chat opened
+                if let Ok(mut movement) = player.single_mut() {
+                    movement.input.direction = Vec3::ZERO;
+                    movement.input.sprint = false;
+                }
+
+                input.text = opened;
+            }
+            continue;
+        };
+
+        match &event.logical_key {
+            Key::Enter => {
+                let line = text.trim();
+                if !line.is_empty() {
+                    submitted.write(ChatSubmitted(line.to_string()));
+                }
+                input.close();
+            }
+            Key::Escape => input.close(),
+            Key::Backspace => {
+                text.pop();
+            }
+            Key::Tab => {
+                if text.starts_with('/') {
+                    completions.write(CompletionRequested(text.clone()));
+                }
+            }
+            Key::Space => push_text(text, " "),
+            Key::Character(typed) => push_text(text, typed),
+            _ => {}
+        }
+    }
+}
+
+fn update_chat_text(
+    log: Res<ChatLog>,
+    input: Res<ChatInput>,
+    mut log_text: Query<&mut Text, (With<ChatLogText>, Without<ChatInputText>)>,
+    mut input_text: Query<(&mut Text, &mut Visibility), With<ChatInputText>>,
+) {
+    if log.is_changed() {
+        if let Ok(mut text) = log_text.single_mut() {
+            text.0 = log.0.iter().cloned().collect::<Vec<_>>().join("\n");
+        }
+    }
+
+    if input.is_changed() {
+        if let Ok((mut text, mut visibility)) = input_text.single_mut() {
+            match &input.text {
+                Some(line) if input.suggestions.is_empty() => text.0 = format!("> {line}_"),
+                Some(line) => text.0 = format!("{}\n> {line}_", input.suggestions.join("  ")),
+                None => text.0.clear(),
+            }
+
+            *visibility = if input.is_open() {
+                Visibility::Inherited
+            } else {
+                Visibility::Hidden
+            };
+        }
+    }
+}
+
+/// Lets the player talk with the others and run commands on the server, T or Enter opens the chat
+/// and / opens it with a command.
+pub struct ChatPlugin;
+
+impl Plugin for ChatPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ChatLog>()
+            .init_resource::<ChatInput>()
+            .add_event::<ChatSubmitted>()
+            .add_event::<CompletionRequested>()
+            .add_systems(Startup, setup_chat)
+            .add_systems(
+                Update,
+                (type_in_chat, update_chat_text)
+                    .chain()
+                    .after(handle_player_input),
+            );
+    }
+}
@@ -0,0 +1,160 @@
+use bevy::prelude::*;
+use crate::{
+    is_breakable, is_replaceable, Block, BlockMaterial, BlockMaterialFlags,
+    BlockMaterialRegistry, Cactus, ChunkMap, ChunkShape, Collider, Dirt, Grass, Gravel, Leaves,
+    RaycastHit, Sand, Snow, Stone, Wood, WorldEdits, PLAYER_REACH,
+};
+
+use crate::client::systems::PlayerController;
+
+const HIGHLIGHT_COLOR: Color = Color::srgb(0.05, 0.05, 0.05);
+
+/// Material placed by the player on right click.
+#[derive(Resource, Deref, DerefMut)]
+pub struct SelectedMaterial(pub Block);
+
+impl Default for SelectedMaterial {
+    fn default() -> Self {
+        Self(Stone::into_block())
+    }
+}
+
+/// The block currently looked at by the player if there's one in reach.
+#[derive(Resource, Default, Deref)]
+pub struct TargetedBlock(pub Option<RaycastHit<Block>>);
+
+/// Sent when the player edits a block, the edit is already applied locally.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct BlockEditRequest {
+    pub pos: IVec3,
+    pub previous: Block,
+    pub block: Block,
+}
+
+pub fn update_targeted_block(
+    player: Query<&Transform, With<PlayerController>>,
+    chunks: Res<ChunkMap<Block, ChunkShape>>,
+    registry: Res<BlockMaterialRegistry>,
+    mut targeted_block: ResMut<TargetedBlock>,
+) {
+    let Ok(transform) = player.single() else {
+        return;
+    };
+
+    // Liquids can be seen through, the player targets whatever lies behind them
+    targeted_block.0 = chunks.raycast_filtered(
+        transform.translation,
+        *transform.forward(),
+        PLAYER_REACH,
+        |block| {
+            !block.is_empty() && !registry.block_flags(block).contains(BlockMaterialFlags::LIQUID)
+        },
+    );
+}
+
+pub fn draw_targeted_block(targeted_block: Res<TargetedBlock>, mut gizmos: Gizmos) {
+    if let Some(hit) = targeted_block.0 {
+        gizmos.cuboid(
+            Transform::from_translation(hit.pos.as_vec3() + Vec3::splat(0.5))
+                .with_scale(Vec3::splat(1.005)),
+            HIGHLIGHT_COLOR,
+        );
+    }
+}
+
+/// Returns a block of material `M` if it's registered.
+fn registered_block<M: BlockMaterial + 'static>(
+    registry: &BlockMaterialRegistry,
+) -> Option<Block> {
+    registry
+        .get_id_for_type::<M>()
+        .and_then(|id| registry.block_for_id(id))
+}
+
+/// Materials selected by the hotbar keys, from the first key to the last.
+const HOTBAR: [fn(&BlockMaterialRegistry) -> Option<Block>; 9] = [
+    registered_block::<Stone>,
+    registered_block::<Dirt>,
+    registered_block::<Grass>,
+    registered_block::<Wood>,
+    registered_block::<Leaves>,
+    registered_block::<Sand>,
+    registered_block::<Gravel>,
+    registered_block::<Snow>,
+    registered_block::<Cactus>,
+];
+
+pub fn select_material(
+    keys: Res<ButtonInput<KeyCode>>,
+    registry: Res<BlockMaterialRegistry>,
+    mut selected: ResMut<SelectedMaterial>,
+) {
+    const HOTBAR_KEYS: [KeyCode; 9] = [
+        KeyCode::Digit1,
+        KeyCode::Digit2,
+        KeyCode::Digit3,
+        KeyCode::Digit4,
+        KeyCode::Digit5,
+        KeyCode::Digit6,
+        KeyCode::Digit7,
+        KeyCode::Digit8,
+        KeyCode::Digit9,
+    ];
+
+    for (key, material) in HOTBAR_KEYS.iter().zip(HOTBAR) {
+        if keys.just_pressed(*key) {
+            if let Some(block) = material(&registry) {
+                selected.0 = block;
+            }
+        }
+    }
+}
+
+pub fn handle_block_interaction(
+    player: Query<(&PlayerController, &Transform, &Collider)>,
+    btns: Res<ButtonInput<MouseButton>>,
+    targeted_block: Res<TargetedBlock>,
+    selected: Res<SelectedMaterial>,
+    registry: Res<BlockMaterialRegistry>,
+    mut edits: WorldEdits,
+    mut edit_requests: EventWriter<BlockEditRequest>,
+) {
+    let Ok((controller, transform, collider)) = player.single() else {
+        return;
+    };
+
+    // The first click only locks the cursor
+    if !controller.cursor_locked {
+        return;
+    }
+
+    let Some(hit) = targeted_block.0 else {
+        return;
+    };
+
+    let edit = if btns.just_pressed(MouseButton::Left) && is_breakable(&registry, hit.block) {
+        Some((hit.pos, Block::EMPTY_BLOCK))
+    } else if btns.just_pressed(MouseButton::Right) {
+        let place_pos = hit.adjacent_pos();
+        let feet = transform.translation - Vec3::Y * collider.eye_height;
+
+        let replaceable = edits
+            .block_at(place_pos)
+            .is_some_and(|block| is_replaceable(&registry, block));
+        let inside_player = collider.overlaps_block(feet, place_pos);
+
+        (replaceable && !inside_player).then_some((place_pos, selected.0))
+    } else {
+        None
+    };
+
+    if let Some((pos, block)) = edit {
+        if let Some(previous) = edits.set_block(pos, block) {
+            edit_requests.write(BlockEditRequest {
+                pos,
+                previous,
+                block,
+            });
+        }
+    }
+}
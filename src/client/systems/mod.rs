@@ -1,7 +1,14 @@
 use bevy::{
-    app::{Plugin, Update},
+    app::{FixedUpdate, Plugin, Update},
     ecs::schedule::IntoScheduleConfigs,
 };
+use crate::{GameMode, PhysicsSet, WorldEditSet};
+
+pub mod chat;
+pub use chat::*;
+
+pub mod interaction;
+pub use interaction::*;
 
 pub mod player;
 pub use player::*;
@@ -11,9 +18,31 @@ pub mod sky;
 pub struct SystemsPlugin;
 impl Plugin for SystemsPlugin {
     fn build(&self, app: &mut bevy::app::App) {
-        app.add_systems(
-            Update,
-            (handle_player_input, handle_player_mouse_move).chain(),
-        );
+        app.init_resource::<SelectedMaterial>()
+            .init_resource::<GameMode>()
+            .add_event::<BlockEditRequest>()
+            .add_event::<PlayerMoved>()
+            .init_resource::<TargetedBlock>()
+            .add_systems(
+                Update,
+                (
+                    handle_player_input.run_if(chat_closed),
+                    handle_player_mouse_move.run_if(chat_closed),
+                )
+                    .chain(),
+            )
+            .add_systems(FixedUpdate, move_player.in_set(PhysicsSet))
+            .add_systems(
+                Update,
+                (
+                    select_material.run_if(chat_closed),
+                    update_targeted_block,
+                    handle_block_interaction.run_if(chat_closed),
+                    draw_targeted_block,
+                )
+                    .chain()
+                    .in_set(WorldEditSet)
+                    .before(handle_player_input),
+            );
     }
 }
@@ -1,5 +1,9 @@
 use std::f32::consts::FRAC_PI_2;
 use bevy::{input::mouse::MouseMotion, prelude::*, window::CursorGrabMode};
+use crate::{
+    solid_blocks, step_player, Block, BlockMaterialRegistry, BodyState, ChunkMap, ChunkShape,
+    Collider, GameMode, IsOnGround, MovementMode, PlayerInput, Velocity, MOVEMENT_STEP,
+};
 
 pub const DEFAULT_CAMERA_SENS: f32 = 0.005;
 
@@ -10,6 +14,20 @@ pub struct PlayerController {
     pub cursor_locked: bool,
 }
 
+/// Input of the player, sampled every frame and applied at the next movement step.
+#[derive(Component, Default)]
+pub struct MovementInput {
+    pub input: PlayerInput,
+    next_sequence: u32,
+}
+
+/// Sent every movement step with the input applied and the resulting state of the player.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct PlayerMoved {
+    pub input: PlayerInput,
+    pub state: BodyState,
+}
+
 pub fn handle_player_mouse_move(
     mut query: Query<(&mut PlayerController, &mut Transform)>,
     mut mouse_motion_event_reader: EventReader<MouseMotion>,
@@ -49,11 +67,12 @@ pub fn handle_player_mouse_move(
 }
 
 pub fn handle_player_input(
-    mut query: Query<(&mut PlayerController, &mut Transform)>,
+    mut query: Query<(&mut PlayerController, &mut MovementInput, &mut MovementMode)>,
     keys: Res<ButtonInput<KeyCode>>,
     btns: Res<ButtonInput<MouseButton>>,
+    game_mode: Res<GameMode>,
 ) {
-    let (mut controller, mut transform) = query.single_mut().unwrap();
+    let (mut controller, mut movement, mut mode) = query.single_mut().unwrap();
 
     if btns.just_pressed(MouseButton::Left) {
         controller.cursor_locked = true;
@@ -63,12 +82,13 @@ pub fn handle_player_input(
         controller.cursor_locked = false;
     }
 
-    let mut direction = Vec3::ZERO;
-
-    let forward = transform.rotation.mul_vec3(Vec3::Z).normalize() * Vec3::new(1.0, 0., 1.0);
-    let right = transform.rotation.mul_vec3(Vec3::X).normalize();
+    if !game_mode.allows_flight() {
+        *mode = MovementMode::Walk;
+    } else if keys.just_pressed(KeyCode::KeyF) {
+        *mode = mode.toggled();
+    }
 
-    let mut acceleration = 1.0f32;
+    let mut direction = Vec3::ZERO;
 
     if keys.pressed(KeyCode::KeyW) {
         direction.z -= 1.0;
@@ -94,16 +114,57 @@ pub fn handle_player_input(
         direction.y -= 1.0;
     }
 
-    if keys.pressed(KeyCode::ControlLeft) {
-        acceleration *= 8.0;
-    }
+    // The input is only applied at the next movement step
+    movement.input = PlayerInput {
+        sequence: movement.input.sequence,
+        direction,
+        yaw: controller.yaw,
+        pitch: controller.pitch,
+        sprint: keys.pressed(KeyCode::ControlLeft),
+        mode: *mode,
+    };
+}
 
-    if direction == Vec3::ZERO {
+/// Moves the player by a step following its latest input.
+pub fn move_player(
+    mut query: Query<(
+        &mut MovementInput,
+        &mut Transform,
+        &mut Velocity,
+        &mut IsOnGround,
+        &Collider,
+    )>,
+    chunks: Res<ChunkMap<Block, ChunkShape>>,
+    registry: Res<BlockMaterialRegistry>,
+    mut moved: EventWriter<PlayerMoved>,
+) {
+    let Ok((mut movement, mut transform, mut velocity, mut on_ground, collider)) =
+        query.single_mut()
+    else {
         return;
-    }
+    };
+
+    let mut input = movement.input;
+    input.sequence = movement.next_sequence;
+    movement.next_sequence = movement.next_sequence.wrapping_add(1);
+
+    let mut state = BodyState {
+        position: transform.translation - Vec3::Y * collider.eye_height,
+        velocity: velocity.0,
+        on_ground: on_ground.0,
+    };
+
+    step_player(
+        solid_blocks(&chunks, &registry),
+        collider,
+        &input,
+        &mut state,
+        MOVEMENT_STEP,
+    );
+
+    transform.translation = state.position + Vec3::Y * collider.eye_height;
+    velocity.0 = state.velocity;
+    on_ground.0 = state.on_ground;
 
-    // hardcoding 0.10 as a factor for now to not go zoomin across the world.
-    transform.translation += direction.x * right * acceleration
-        + direction.z * forward * acceleration
-        + direction.y * Vec3::Y * acceleration;
+    moved.write(PlayerMoved { input, state });
 }
@@ -1,5 +1,12 @@
 use bevy::prelude::*;
-use voxel_engine::Player;
+use bevy_atmosphere::prelude::{AtmosphereMut, Nishita};
+use crate::{Player, TimeOfDay};
+
+/// Illuminance of the sun when it is right overhead, in lux.
+const SUN_ILLUMINANCE: f32 = light_consts::lux::AMBIENT_DAYLIGHT;
+
+/// How far the day goes before the sky is redrawn, redrawing it every frame would be wasteful.
+const SKY_UPDATE_STEP: f32 = 1e-3;
 
 #[derive(Resource, Deref)]
 struct SkyLightEntity(Entity);
@@ -38,11 +45,32 @@ fn update_light_position(
     }
 }
 
+/// Moves the sun across the sky as the day goes by.
+fn follow_time_of_day(
+    time_of_day: Res<TimeOfDay>,
+    sky_light_entity: Res<SkyLightEntity>,
+    mut lights: Query<(&mut Transform, &mut DirectionalLight)>,
+    mut atmosphere: AtmosphereMut<Nishita>,
+    mut drawn_at: Local<Option<f32>>,
+) {
+    let sun = time_of_day.sun_direction();
+
+    if let Ok((mut transform, mut light)) = lights.get_mut(**sky_light_entity) {
+        transform.look_to(-sun, Vec3::Y);
+        light.illuminance = SUN_ILLUMINANCE * sun.y.max(0.);
+    }
+
+    if drawn_at.is_none_or(|drawn_at| (time_of_day.0 - drawn_at).abs() >= SKY_UPDATE_STEP) {
+        atmosphere.sun_position = sun;
+        *drawn_at = Some(time_of_day.0);
+    }
+}
+
 pub struct InteractiveSkyboxPlugin;
 
 impl Plugin for InteractiveSkyboxPlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
         app.add_systems(Startup, setup_sky_lighting)
-            .add_systems(Update, update_light_position);
+            .add_systems(Update, (update_light_position, follow_time_of_day));
     }
 }
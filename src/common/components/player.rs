@@ -1,4 +1,8 @@
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Maximum distance at which a player can break or place blocks.
+pub const PLAYER_REACH: f32 = 8.0;
 
 #[derive(Component, Default)]
 pub struct Player;
@@ -6,6 +10,46 @@ pub struct Player;
 #[derive(Component)]
 pub struct DisplayName(pub String);
 
+/// Where a player is looking, in radians.
+#[derive(Component, Clone, Copy, Default, Debug)]
+pub struct HeadRotation {
+    pub yaw: f32,
+    pub pitch: f32,
+}
+
+/// What a player is allowed to do, set by the server.
+#[derive(Component, Resource, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum GameMode {
+    /// Players can fly around freely.
+    #[default]
+    Creative,
+    /// Players have to walk.
+    Survival,
+}
+
+impl GameMode {
+    pub const NAMES: [&str; 2] = ["creative", "survival"];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "creative" => Some(Self::Creative),
+            "survival" => Some(Self::Survival),
+            _ => None,
+        }
+    }
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Creative => "creative",
+            Self::Survival => "survival",
+        }
+    }
+
+    pub const fn allows_flight(self) -> bool {
+        matches!(self, Self::Creative)
+    }
+}
+
 #[derive(Component)]
 pub struct Health {
     pub current: u8,
@@ -73,6 +73,12 @@ pub enum InvalidData {
     BlockProperties(String),
     #[error("Invalid block texture: {0}")]
     BlockTexture(String),
+    #[error("Invalid chunk data: {0}")]
+    ChunkData(String),
+    #[error("Invalid region file: {0}")]
+    Region(String),
+    #[error("Invalid network message: {0}")]
+    NetworkMessage(String),
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -83,6 +89,8 @@ pub enum ClientError {
     NetworkError(String),
     #[error("Asset error: {0}")]
     AssetError(String),
+    #[error("Incompatible server: {0}")]
+    IncompatibleServer(String),
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -0,0 +1,56 @@
+use bevy::math::{IVec3, Vec2, Vec3Swizzles};
+use crate::{noise, place_decoration_block, Block, BlockMaterial, Cactus, CaveSettings, DensitySettings, LayeredBiomeTerrainGenerator, Sand, WorldSeed};
+
+pub struct DesertBiomeTerrainGenerator;
+
+impl LayeredBiomeTerrainGenerator for DesertBiomeTerrainGenerator {
+    fn fill_strata(&self, _layer: u32) -> Block {
+        Sand::into_block()
+    }
+
+    fn num_layers(&self) -> u32 {
+        6
+    }
+
+    fn density_settings(&self) -> DensitySettings {
+        // Dunes are smooth, and caves rarely reach through the sand
+        DensitySettings {
+            overhang_height: 4.0,
+            caves: CaveSettings {
+                worm_chance: 0.15,
+                ..Default::default()
+            },
+        }
+    }
+
+    fn terrain_height(&self, noise: f32) -> f32 {
+        noise.mul_add(10.0, 124.0)
+    }
+
+    fn place_decoration(
+        &self,
+        seed: WorldSeed,
+        key: IVec3,
+        surface: IVec3,
+        ground: Block,
+        buffer: &mut crate::BlockBuffer<crate::Block, crate::ChunkShape>,
+    ) {
+        // Cacti only grow out of sand
+        if ground != Sand::into_block() {
+            return;
+        }
+
+        let cactus_chance = noise::rand2to1(
+            surface.xz().as_vec2() * 0.1,
+            Vec2::new(27.165, 91.442),
+            *seed.derive("desert.cacti"),
+        );
+
+        if cactus_chance > 0.993 {
+            let cactus_height = 2 + (cactus_chance * 1000.0) as i32 % 3;
+            for y in 1..=cactus_height {
+                place_decoration_block(buffer, key, surface + IVec3::Y * y, Cactus::into_block());
+            }
+        }
+    }
+}
@@ -1,9 +1,7 @@
-use std::ops::Div;
+use bevy::math::IVec3;
+use ilattice::glam::{UVec2, UVec3 as ILUVec3};
 
-use bevy::math::{IVec3, UVec3};
-use ilattice::{extent::Extent, glam::UVec2};
-
-use crate::{BiomeTerrainGenerator, Block, BlockBuffer, BlockMaterial, ChunkShape, Dirt, Grass, HeightMap, CHUNK_SIZE};
+use crate::{BiomeTerrainGenerator, Block, BlockBuffer, BlockMaterial, ChunkShape, DensitySettings, Dirt, Grass, TerrainShape, WorldSeed, CHUNK_HEIGHT, SEA_LEVEL};
 
 pub trait LayeredBiomeTerrainGenerator: BiomeTerrainGenerator {
     fn fill_strata(&self, layer: u32) -> Block {
@@ -17,10 +15,22 @@ pub trait LayeredBiomeTerrainGenerator: BiomeTerrainGenerator {
         8
     }
 
+    fn density_settings(&self) -> DensitySettings {
+        DensitySettings::default()
+    }
+
+    fn terrain_height(&self, noise: f32) -> f32 {
+        noise.mul_add(20.0, 132.0)
+    }
+
+    /// Places the decorations standing on the block of `ground` at `surface`, in world
+    /// coordinates. Only their blocks falling in the chunk at `key` are placed.
     fn place_decoration(
         &self,
+        _seed: WorldSeed,
         _key: IVec3,
-        _pos: UVec3,
+        _surface: IVec3,
+        _ground: Block,
         _buffer: &mut BlockBuffer<Block, ChunkShape>,
     ) {
 
@@ -28,50 +38,49 @@ pub trait LayeredBiomeTerrainGenerator: BiomeTerrainGenerator {
 }
 
 impl<T: LayeredBiomeTerrainGenerator> BiomeTerrainGenerator for T {
-    fn carve_terrain(
+    fn density_settings(&self) -> DensitySettings {
+        LayeredBiomeTerrainGenerator::density_settings(self)
+    }
+
+    fn terrain_height(&self, noise: f32) -> f32 {
+        LayeredBiomeTerrainGenerator::terrain_height(self, noise)
+    }
+
+    fn carve_column(
         &self,
-        chunk_key: IVec3,
-        heightmap: HeightMap<CHUNK_SIZE, CHUNK_SIZE>,
+        _seed: WorldSeed,
+        _chunk_key: IVec3,
+        column: UVec2,
+        shape: &TerrainShape,
         buffer: &mut BlockBuffer<Block, ChunkShape>,
     ) {
-        Extent::from_min_and_shape(UVec2::ZERO, UVec2::splat(CHUNK_SIZE as u32))
-            .iter2()
-            .for_each(|pos| {
-                let height = heightmap.get(pos.into());
+        for y in 0..CHUNK_HEIGHT as u32 {
+            let pos = ILUVec3::new(column.x, y, column.y);
 
-                if height.div(CHUNK_SIZE as u32) == (chunk_key.y as u32).div(CHUNK_SIZE as u32) {
-                    let local_height = height.rem_euclid(CHUNK_SIZE as u32);
+            // Caves were already carved out, their walls are left bare
+            if buffer.block_at(pos).is_empty() {
+                continue;
+            }
 
-                    for h in 0..=self.num_layers() {
-                        let remaining_height = local_height.checked_sub(h);
-
-                        if let Some(uh) = remaining_height {
-                            *buffer.block_at_mut([pos.x, uh, pos.y].into()) = self.fill_strata(h);
-                        }
-                    }
+            match shape.depth(pos) {
+                Some(depth) if depth <= self.num_layers() => {
+                    *buffer.block_at_mut(pos) = self.fill_strata(depth);
                 }
-            });
+                _ => {}
+            }
+        }
     }
 
-    fn decorate_terrain(
+    fn decorate_column(
         &self,
+        seed: WorldSeed,
+        surface: IVec3,
         chunk_key: IVec3,
-        heightmap: HeightMap<CHUNK_SIZE, CHUNK_SIZE>,
         buffer: &mut BlockBuffer<Block, ChunkShape>
     ) {
-        if chunk_key.y <= 96 {
-            return;
+        // Decorations are only placed on dry land, standing on the top layer of the strata
+        if surface.y + 1 >= SEA_LEVEL {
+            self.place_decoration(seed, chunk_key, surface, self.fill_strata(0), buffer);
         }
-
-        Extent::from_min_and_shape(UVec2::ZERO, UVec2::splat(CHUNK_SIZE as u32))
-            .iter2()
-            .for_each(|pos| {
-                let height = heightmap.get(pos.into());
-
-                if height.div(CHUNK_SIZE as u32) == (chunk_key.y as u32).div(CHUNK_SIZE as u32) {
-                    let local_height = height.rem_euclid(CHUNK_SIZE as u32);
-                    self.place_decoration(chunk_key, [pos.x, local_height, pos.y].into(), buffer);
-                }
-            });
     }
 }
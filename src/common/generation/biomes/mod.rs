@@ -1,23 +1,59 @@
-use crate::{Block, BlockBuffer, ChunkShape, HeightMap, CHUNK_SIZE};
+use crate::{Block, BlockBuffer, ChunkShape, DensitySettings, TerrainShape, WorldSeed};
 use bevy::math::IVec3;
+use ilattice::glam::UVec2;
+
+pub mod desert;
+pub use desert::*;
 
 pub mod layered;
 pub use layered::*;
 
+pub mod mountains;
+pub use mountains::*;
+
+pub mod ocean;
+pub use ocean::*;
+
 pub mod plains;
 pub use plains::*;
 
+pub mod snowy_taiga;
+pub use snowy_taiga::*;
+
+pub mod swamp;
+pub use swamp::*;
+
+/// Shapes and decorates the columns of a biome, implementations derive the seeds of their
+/// features from the world seed they are given.
+///
+/// The heights and density settings of the biomes are blended across their borders, columns are
+/// then carved and decorated by a single biome. Decorations can cross chunk borders, every chunk
+/// they reach into places its part of them.
 pub trait BiomeTerrainGenerator: 'static + Sync + Send {
-    fn carve_terrain(
+    /// Settings of the density function and the caves shaping the terrain of the biome.
+    fn density_settings(&self) -> DensitySettings {
+        DensitySettings::default()
+    }
+    /// Turns the height noise of a column, ranging from -1 to 1, into the height of its surface.
+    fn terrain_height(&self, noise: f32) -> f32 {
+        noise.mul_add(20.0, 132.0)
+    }
+    fn carve_column(
         &self,
+        seed: WorldSeed,
         chunk_key: IVec3,
-        heightmap: HeightMap<CHUNK_SIZE, CHUNK_SIZE>,
+        column: UVec2,
+        shape: &TerrainShape,
         buffer: &mut BlockBuffer<Block, ChunkShape>,
     );
-    fn decorate_terrain(
+    /// Places the decorations standing on the surface block at `surface`, in world coordinates.
+    /// The surface can lie in a neighbouring chunk, only the blocks of the decorations falling in
+    /// the chunk at `chunk_key` are placed.
+    fn decorate_column(
         &self,
+        seed: WorldSeed,
+        surface: IVec3,
         chunk_key: IVec3,
-        heightmap: HeightMap<CHUNK_SIZE, CHUNK_SIZE>,
         buffer: &mut BlockBuffer<Block, ChunkShape>
     );
 }
@@ -0,0 +1,47 @@
+use bevy::math::IVec3;
+use crate::{chunk_local, Block, BlockMaterial, DensitySettings, LayeredBiomeTerrainGenerator, Snow, Stone, WorldSeed};
+
+/// World height above which the peaks are capped with snow.
+const SNOW_LINE: i32 = 190;
+
+pub struct MountainsBiomeTerrainGenerator;
+
+impl LayeredBiomeTerrainGenerator for MountainsBiomeTerrainGenerator {
+    fn fill_strata(&self, _layer: u32) -> Block {
+        Stone::into_block()
+    }
+
+    fn num_layers(&self) -> u32 {
+        0
+    }
+
+    fn density_settings(&self) -> DensitySettings {
+        // Tall overhangs carve the slopes into cliffs
+        DensitySettings {
+            overhang_height: 24.0,
+            ..Default::default()
+        }
+    }
+
+    fn terrain_height(&self, noise: f32) -> f32 {
+        noise.mul_add(60.0, 170.0)
+    }
+
+    fn place_decoration(
+        &self,
+        _seed: WorldSeed,
+        key: IVec3,
+        surface: IVec3,
+        _ground: Block,
+        buffer: &mut crate::BlockBuffer<crate::Block, crate::ChunkShape>,
+    ) {
+        if surface.y < SNOW_LINE {
+            return;
+        }
+
+        // The snow caps the peaks rather than growing on top of them
+        if let Some(pos) = chunk_local(key, surface) {
+            buffer.set_block(pos, Snow::into_block());
+        }
+    }
+}
@@ -0,0 +1,31 @@
+use crate::{Block, BlockMaterial, CaveSettings, DensitySettings, Gravel, LayeredBiomeTerrainGenerator, Sand};
+
+pub struct OceanBiomeTerrainGenerator;
+
+impl LayeredBiomeTerrainGenerator for OceanBiomeTerrainGenerator {
+    fn fill_strata(&self, layer: u32) -> Block {
+        match layer {
+            0..3 => Gravel::into_block(),
+            _ => Sand::into_block(),
+        }
+    }
+
+    fn num_layers(&self) -> u32 {
+        5
+    }
+
+    fn density_settings(&self) -> DensitySettings {
+        DensitySettings {
+            overhang_height: 6.0,
+            caves: CaveSettings {
+                cheese_threshold: 0.7,
+                worm_chance: 0.1,
+                ..Default::default()
+            },
+        }
+    }
+
+    fn terrain_height(&self, noise: f32) -> f32 {
+        noise.mul_add(10.0, 72.0)
+    }
+}
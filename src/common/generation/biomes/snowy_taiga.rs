@@ -0,0 +1,36 @@
+use bevy::math::{IVec3, Vec2, Vec3Swizzles};
+use crate::{make_pine_tree, noise, Block, BlockMaterial, Dirt, LayeredBiomeTerrainGenerator, Leaves, Snow, WorldSeed, Wood};
+
+pub struct SnowyTaigaBiomeTerrainGenerator;
+
+impl LayeredBiomeTerrainGenerator for SnowyTaigaBiomeTerrainGenerator {
+    fn fill_strata(&self, layer: u32) -> Block {
+        match layer {
+            0..1 => Snow::into_block(),
+            _ => Dirt::into_block(),
+        }
+    }
+
+    fn terrain_height(&self, noise: f32) -> f32 {
+        noise.mul_add(24.0, 136.0)
+    }
+
+    fn place_decoration(
+        &self,
+        seed: WorldSeed,
+        key: IVec3,
+        surface: IVec3,
+        _ground: Block,
+        buffer: &mut crate::BlockBuffer<crate::Block, crate::ChunkShape>,
+    ) {
+        let spawn_chance = noise::rand2to1(
+            surface.xz().as_vec2() * 0.01,
+            Vec2::new(55.193, 17.608),
+            *seed.derive("snowy_taiga.trees"),
+        );
+
+        if spawn_chance > 0.96 {
+            make_pine_tree::<Wood, Leaves>(buffer, key, surface);
+        }
+    }
+}
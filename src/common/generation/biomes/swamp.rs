@@ -0,0 +1,42 @@
+use bevy::math::{IVec3, Vec2, Vec3Swizzles};
+use crate::{make_tree, noise, Block, CaveSettings, DensitySettings, LayeredBiomeTerrainGenerator, Leaves, WorldSeed, Wood, SEA_LEVEL};
+
+pub struct SwampBiomeTerrainGenerator;
+
+impl LayeredBiomeTerrainGenerator for SwampBiomeTerrainGenerator {
+    fn density_settings(&self) -> DensitySettings {
+        // The ground is soaked, caves would flood
+        DensitySettings {
+            overhang_height: 2.0,
+            caves: CaveSettings {
+                cheese_threshold: 1.0,
+                worm_chance: 0.05,
+                ..Default::default()
+            },
+        }
+    }
+
+    fn terrain_height(&self, noise: f32) -> f32 {
+        // Hugging the sea level floods the lowest half of the swamp into shallow pools
+        noise.mul_add(4.0, SEA_LEVEL as f32)
+    }
+
+    fn place_decoration(
+        &self,
+        seed: WorldSeed,
+        key: IVec3,
+        surface: IVec3,
+        _ground: Block,
+        buffer: &mut crate::BlockBuffer<crate::Block, crate::ChunkShape>,
+    ) {
+        let tree_chance = noise::rand2to1(
+            surface.xz().as_vec2() * 0.01,
+            Vec2::new(33.871, 62.514),
+            *seed.derive("swamp.trees"),
+        );
+
+        if tree_chance > 0.99 {
+            make_tree::<Wood, Leaves>(buffer, key, surface);
+        }
+    }
+}
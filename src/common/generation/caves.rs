@@ -0,0 +1,135 @@
+use bevy::math::{IVec2, IVec3, Vec3, Vec3Swizzles};
+use ilattice::{extent::Extent, glam::UVec3};
+use rand_chacha::{
+    rand_core::{RngCore, SeedableRng},
+    ChaCha8Rng,
+};
+use std::f32::consts::{PI, TAU};
+
+use crate::{
+    chunk_extent, Block, BlockBuffer, CaveSettings, ChunkShape, TerrainShape, WorldSeed, CAVE_MIN_Y,
+    CHUNK_DIMS, CHUNK_SIZE,
+};
+
+/// Steps a worm cave takes from where it starts, one block per step.
+const WORM_LENGTH: u32 = 80;
+
+/// Highest world height worm caves start at.
+const WORM_MAX_START_Y: i32 = 120;
+
+/// Solid blocks kept between the worm caves and the surface, so that they don't open up the ground
+/// the decorations stand on.
+pub const WORM_MIN_DEPTH: u32 = 4;
+
+/// Worm caves never get wider than this, so that they can't reach chunks further than
+/// `WORM_REACH` chunks away from where they start.
+const MAX_WORM_RADIUS: f32 = 8.0;
+
+/// Chunk columns around a chunk whose worm caves can cross it.
+const WORM_REACH: i32 =
+    (WORM_LENGTH as i32 + MAX_WORM_RADIUS as i32 + CHUNK_SIZE as i32 - 1) / CHUNK_SIZE as i32;
+
+fn roll(rng: &mut ChaCha8Rng) -> f32 {
+    rng.next_u32() as f32 / u32::MAX as f32
+}
+
+/// Hollows out a block of the terrain, water is left in place so that caves don't drain the seas.
+fn hollow(buffer: &mut BlockBuffer<Block, ChunkShape>, pos: UVec3) {
+    let block = buffer.block_at_mut(pos);
+    if block.is_opaque() {
+        *block = Block::EMPTY_BLOCK;
+    }
+}
+
+/// Hollows out the blocks of a chunk the cheese caves of its shape go through.
+pub fn carve_cheese_caves(buffer: &mut BlockBuffer<Block, ChunkShape>, shape: &TerrainShape) {
+    chunk_extent()
+        .iter3()
+        .filter(|pos| shape.is_cave(*pos))
+        .for_each(|pos| hollow(buffer, pos));
+}
+
+/// Carves the worm caves crossing a chunk, including the ones starting in the chunk columns around
+/// it. `caves_at` returns the cave settings of the column a worm starts in, the worms only hollow
+/// out the blocks of `shape` lying at least `WORM_MIN_DEPTH` blocks below its surface.
+///
+/// Every worm is walked from its start with its own random generator, so each chunk carves its
+/// part of the worm without having to know about the chunks around it.
+pub fn carve_worm_caves(
+    seed: WorldSeed,
+    key: IVec3,
+    shape: &TerrainShape,
+    buffer: &mut BlockBuffer<Block, ChunkShape>,
+    caves_at: impl Fn(IVec3) -> CaveSettings,
+) {
+    let seed = seed.derive("caves.worms");
+    let column = key.xz().div_euclid(IVec2::splat(CHUNK_SIZE as i32));
+
+    for dz in -WORM_REACH..=WORM_REACH {
+        for dx in -WORM_REACH..=WORM_REACH {
+            let origin = (column + IVec2::new(dx, dz)).extend(0).xzy() * CHUNK_SIZE as i32;
+            let settings = caves_at(origin);
+
+            let mut rng = ChaCha8Rng::seed_from_u64(*seed.at(origin));
+            if roll(&mut rng) < settings.worm_chance {
+                carve_worm(&mut rng, origin, settings.worm_radius, key, shape, buffer);
+            }
+        }
+    }
+}
+
+fn carve_worm(
+    rng: &mut ChaCha8Rng,
+    origin: IVec3,
+    radius: f32,
+    key: IVec3,
+    shape: &TerrainShape,
+    buffer: &mut BlockBuffer<Block, ChunkShape>,
+) {
+    let mut pos = origin.as_vec3()
+        + Vec3::new(
+            roll(rng) * CHUNK_SIZE as f32,
+            CAVE_MIN_Y as f32 + roll(rng) * (WORM_MAX_START_Y - CAVE_MIN_Y) as f32,
+            roll(rng) * CHUNK_SIZE as f32,
+        );
+    let mut yaw = roll(rng) * TAU;
+    let mut pitch = (roll(rng) - 0.5) * 0.5;
+
+    for step in 0..WORM_LENGTH {
+        pos += Vec3::new(yaw.cos() * pitch.cos(), pitch.sin(), yaw.sin() * pitch.cos());
+        yaw += (roll(rng) - 0.5) * 0.5;
+        pitch = pitch * 0.7 + (roll(rng) - 0.5) * 0.4;
+
+        // Worms swell in their middle and narrow down at their ends
+        let swell = 0.5 + (PI * step as f32 / WORM_LENGTH as f32).sin();
+        carve_sphere(buffer, key, shape, pos, (radius * swell).min(MAX_WORM_RADIUS));
+    }
+}
+
+fn carve_sphere(
+    buffer: &mut BlockBuffer<Block, ChunkShape>,
+    key: IVec3,
+    shape: &TerrainShape,
+    center: Vec3,
+    radius: f32,
+) {
+    let local = center - key.as_vec3();
+    let min = (local - radius).floor().max(Vec3::ZERO);
+    let max = (local + radius).ceil().min((CHUNK_DIMS - IVec3::ONE).as_vec3());
+    if min.cmpgt(max).any() {
+        return;
+    }
+
+    let min_y = (CAVE_MIN_Y - key.y).max(0) as u32;
+    Extent::from_min_and_max(
+        UVec3::from_array(min.as_uvec3().to_array()),
+        UVec3::from_array(max.as_uvec3().to_array()),
+    )
+    .iter3()
+    .filter(|pos| pos.y >= min_y)
+    .filter(|pos| {
+        shape.is_solid(*pos) && shape.depth(*pos).is_none_or(|depth| depth >= WORM_MIN_DEPTH)
+    })
+    .filter(|pos| (Vec3::from_array(pos.as_vec3().to_array()) + 0.5 - local).length() < radius)
+    .for_each(|pos| hollow(buffer, pos));
+}
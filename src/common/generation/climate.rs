@@ -0,0 +1,87 @@
+use ::noise::{Fbm, MultiFractal, NoiseFn, SuperSimplex};
+use bevy::math::{IVec2, Vec3};
+use std::ops::Range;
+
+use crate::{fold_seed, WorldSeed};
+
+/// Climate of a column of the world, every parameter ranges from -1 to 1.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Climate {
+    pub temperature: f32,
+    pub humidity: f32,
+    /// How far inland the column is, low values are out at sea.
+    pub continentalness: f32,
+}
+
+/// Climates a biome is found in.
+#[derive(Clone, Debug)]
+pub struct ClimateRange {
+    pub temperature: Range<f32>,
+    pub humidity: Range<f32>,
+    pub continentalness: Range<f32>,
+}
+
+impl ClimateRange {
+    /// Every climate, biomes only declare the parameters they care about on top of it.
+    pub const ANY: Self = Self {
+        temperature: -1.0..1.0,
+        humidity: -1.0..1.0,
+        continentalness: -1.0..1.0,
+    };
+
+    /// Returns how far a climate is from the range, 0 when the range contains it.
+    pub fn distance(&self, climate: Climate) -> f32 {
+        let outside = |range: &Range<f32>, value: f32| {
+            (range.start - value).max(value - range.end).max(0.0)
+        };
+
+        Vec3::new(
+            outside(&self.temperature, climate.temperature),
+            outside(&self.humidity, climate.humidity),
+            outside(&self.continentalness, climate.continentalness),
+        )
+        .length()
+    }
+}
+
+impl Default for ClimateRange {
+    fn default() -> Self {
+        Self::ANY
+    }
+}
+
+/// Noise maps the climate of the world is sampled from.
+pub struct ClimateNoise {
+    temperature: Fbm<SuperSimplex>,
+    humidity: Fbm<SuperSimplex>,
+    continentalness: Fbm<SuperSimplex>,
+}
+
+impl ClimateNoise {
+    pub fn new(seed: WorldSeed) -> Self {
+        let noise = |feature: &str, frequency: f64| {
+            Fbm::<SuperSimplex>::new(fold_seed(*seed.derive(feature)))
+                .set_octaves(3)
+                .set_frequency(frequency)
+                .set_persistence(0.5)
+        };
+
+        Self {
+            temperature: noise("climate.temperature", 0.0012),
+            humidity: noise("climate.humidity", 0.0012),
+            continentalness: noise("climate.continentalness", 0.0008),
+        }
+    }
+
+    /// Samples the climate of the column at the world coordinates `column`.
+    pub fn at(&self, column: IVec2) -> Climate {
+        let point = column.as_dvec2().to_array();
+        let sample = |noise: &Fbm<SuperSimplex>| (noise.get(point) as f32).clamp(-1.0, 1.0);
+
+        Climate {
+            temperature: sample(&self.temperature),
+            humidity: sample(&self.humidity),
+            continentalness: sample(&self.continentalness),
+        }
+    }
+}
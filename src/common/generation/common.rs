@@ -1,6 +1,6 @@
 use bevy::math::{IVec3, Vec3};
-use ilattice::{extent::Extent, glam::{UVec2, UVec3}};
-use crate::{sdf, Bedrock, Block, BlockBuffer, BlockMaterial, ChunkShape, HeightMap, Stone, Water, CHUNK_SIZE};
+use ilattice::{extent::Extent, glam::UVec3};
+use crate::{sdf, Bedrock, Block, BlockBuffer, BlockMaterial, ChunkShape, Stone, TerrainShape, Water, CHUNK_DIMS, CHUNK_SIZE};
 
 pub fn terrain_generate_world_bottom_border(
     buffer: &mut BlockBuffer<Block, ChunkShape>
@@ -11,120 +11,125 @@ pub fn terrain_generate_world_bottom_border(
     );
 }
 
-pub fn carve_terrain_heightmap(
+/// Returns the blocks of a chunk, from its minimum.
+pub fn chunk_extent() -> Extent<UVec3> {
+    Extent::from_min_and_shape(UVec3::ZERO, UVec3::from_array(CHUNK_DIMS.as_uvec3().to_array()))
+}
+
+/// World height the oceans, lakes and pools are filled up to.
+pub const SEA_LEVEL: i32 = 110;
+
+/// Fills the solid blocks of the shape of a chunk with stone, and the others below the sea level
+/// with water.
+pub fn carve_terrain_density(
     buffer: &mut BlockBuffer<Block, ChunkShape>,
     key: IVec3,
-    heightmap: &HeightMap<CHUNK_SIZE, CHUNK_SIZE>
+    shape: &TerrainShape,
 ) {
-    if key.y <= 0 {
-        buffer.fill_extent(
-            Extent::from_min_and_shape(UVec3::ZERO, UVec3::splat(CHUNK_SIZE as u32)),
-            Water::into_block(),
-        );
+    chunk_extent().iter3().for_each(|pos| {
+        if shape.is_solid(pos) {
+            *buffer.block_at_mut(pos) = Stone::into_block();
+        } else if key.y + (pos.y as i32) < SEA_LEVEL {
+            *buffer.block_at_mut(pos) = Water::into_block();
+        }
+    });
+}
+
+/// Returns the position of a block of the world in the chunk at `key`, if it lies in it.
+pub fn chunk_local(key: IVec3, pos: IVec3) -> Option<UVec3> {
+    let local = pos - key;
+    (local.cmpge(IVec3::ZERO).all() && local.cmplt(CHUNK_DIMS).all())
+        .then(|| UVec3::from_array(local.as_uvec3().to_array()))
+}
+
+/// Places a block of a decoration at the world position `pos`, if it lies in the chunk at `key`.
+///
+/// Decorations only grow into empty blocks and leave the terrain they stand on alone, so every
+/// chunk a decoration crosses places its own part of it the same way.
+pub fn place_decoration_block(
+    buffer: &mut BlockBuffer<Block, ChunkShape>,
+    key: IVec3,
+    pos: IVec3,
+    block: Block,
+) {
+    if let Some(local) = chunk_local(key, pos) {
+        if buffer.block_at(local).is_empty() {
+            buffer.set_block(local, block);
+        }
     }
+}
 
-    Extent::from_min_and_shape(
-        UVec2::ZERO,
-        UVec2::new(CHUNK_SIZE as u32, CHUNK_SIZE as u32))
-        .iter2()
-        .for_each(|pos| {
-            let local_height = heightmap
-                .get(pos.into())
-                .checked_sub(key.y as u32)
-                .unwrap_or_default()
-                .min(CHUNK_SIZE as u32);
+/// Places the blocks of a decoration between `min` and `max` around `origin`, `block_at` returns
+/// the block of the decoration at an offset from its origin.
+fn place_decoration_shape(
+    buffer: &mut BlockBuffer<Block, ChunkShape>,
+    key: IVec3,
+    origin: IVec3,
+    (min, max): (IVec3, IVec3),
+    block_at: impl Fn(Vec3) -> Option<Block>,
+) {
+    let min = (origin + min).max(key);
+    let max = (origin + max).min(key + CHUNK_DIMS - IVec3::ONE);
+    if min.cmpgt(max).any() {
+        return;
+    }
 
-            for h in 0..local_height {
-                *buffer.block_at_mut([pos.x, h, pos.y].into()) = Stone::into_block();
+    for z in min.z..=max.z {
+        for y in min.y..=max.y {
+            for x in min.x..=max.x {
+                let pos = IVec3::new(x, y, z);
+                if let Some(block) = block_at((pos - origin).as_vec3()) {
+                    place_decoration_block(buffer, key, pos, block);
+                }
             }
-        });
+        }
+    }
 }
 
+/// Makes a pine tree standing on the block at `origin`, in world coordinates.
 pub fn make_pine_tree<T: BlockMaterial, L: BlockMaterial>(
     buffer: &mut BlockBuffer<Block, ChunkShape>,
-    origin: UVec3,
+    key: IVec3,
+    origin: IVec3,
 ) {
-    let origin = Vec3::from(origin.as_vec3().to_array());
-    Extent::from_min_and_shape(UVec3::ZERO, UVec3::splat(CHUNK_SIZE as u32)) //may want to calculate an extent encompassing the tree instead of iterating over the complete 32^3 volume
-        .iter3()
-        .map(|x| Vec3::from_array(x.as_vec3().to_array()))
-        .map(|position| {
-            let trunk_distance =
-                sdf::sdf_capped_cylinder(position - (origin + 2.0 * Vec3::Y), 1.5, 8.0) < 0.;
-            let leaves_distance =
-                sdf::sdf_v_cone(position - (origin + 6.0 * Vec3::Y), 7.0, 17.0) < 0.;
-            (trunk_distance, leaves_distance, position)
-        })
-        .map(|(trunk_distance, leaves_distance, position)| {
-            (
-                trunk_distance,
-                leaves_distance,
-                UVec3::from(position.as_uvec3().to_array()),
-            )
-        })
-        .for_each(|(trunk_distance, leaves_distance, position)| {
-            if trunk_distance {
-                *buffer.block_at_mut(position) = T::into_block();
-            }
-
-            if leaves_distance {
-                *buffer.block_at_mut(position) = L::into_block();
-            }
-        });
+    let bounds = (IVec3::new(-7, 1, -7), IVec3::new(7, 23, 7));
+    place_decoration_shape(buffer, key, origin, bounds, |offset| {
+        if sdf::sdf_v_cone(offset - 6.0 * Vec3::Y, 7.0, 17.0) < 0. {
+            Some(L::into_block())
+        } else if sdf::sdf_capped_cylinder(offset - 2.0 * Vec3::Y, 1.5, 8.0) < 0. {
+            Some(T::into_block())
+        } else {
+            None
+        }
+    });
 }
 
-/// Make a tree using SDF functions
+/// Make a tree using SDF functions, standing on the block at `origin` in world coordinates
 pub fn make_tree<T: BlockMaterial, L: BlockMaterial>(
     buffer: &mut BlockBuffer<Block, ChunkShape>,
-    origin: UVec3,
+    key: IVec3,
+    origin: IVec3,
 ) {
-    let origin = Vec3::from(origin.as_vec3().to_array());
-    Extent::from_min_and_shape(UVec3::ZERO, UVec3::splat(CHUNK_SIZE as u32)) //may want to calculate an extent encompassing the tree instead of iterating over the complete 32^3 volume
-        .iter3()
-        .map(|x| Vec3::from_array(x.as_vec3().to_array()))
-        .map(|position| {
-            let trunk_distance =
-                sdf::sdf_capped_cylinder(position - (origin + 2.0 * Vec3::Y), 1.5, 8.0) < 0.;
-            let leaves_distance = sdf::sdf_sphere(position - (origin + 14.0 * Vec3::Y), 6.0) < 0.;
-            (trunk_distance, leaves_distance, position)
-        })
-        .map(|(trunk_distance, leaves_distance, position)| {
-            (
-                trunk_distance,
-                leaves_distance,
-                UVec3::from(position.as_uvec3().to_array()),
-            )
-        })
-        .for_each(|(trunk_distance, leaves_distance, position)| {
-            if trunk_distance {
-                *buffer.block_at_mut(position) = T::into_block();
-            }
-
-            if leaves_distance {
-                *buffer.block_at_mut(position) = L::into_block();
-            }
-        });
+    let bounds = (IVec3::new(-6, 1, -6), IVec3::new(6, 20, 6));
+    place_decoration_shape(buffer, key, origin, bounds, |offset| {
+        if sdf::sdf_sphere(offset - 14.0 * Vec3::Y, 6.0) < 0. {
+            Some(L::into_block())
+        } else if sdf::sdf_capped_cylinder(offset - 2.0 * Vec3::Y, 1.5, 8.0) < 0. {
+            Some(T::into_block())
+        } else {
+            None
+        }
+    });
 }
 
 pub fn make_rock<V: BlockMaterial>(
     buffer: &mut BlockBuffer<Block, ChunkShape>,
-    origin: UVec3,
+    key: IVec3,
+    origin: IVec3,
     size: f32,
 ) {
-    let origin = Vec3::from(origin.as_vec3().to_array());
-    Extent::from_min_and_shape(UVec3::ZERO, UVec3::splat(CHUNK_SIZE as u32)) //may want to calculate an extent encompassing the tree instead of iterating over the complete 32^3 volume
-        .iter3()
-        .map(|x| Vec3::from_array(x.as_vec3().to_array()))
-        .map(|position| {
-            let trunk_distance = sdf::sdf_sphere(position - origin, size) < 0.;
-            (trunk_distance, position)
-        })
-        .map(|(rock_distance, position)| {
-            (rock_distance, UVec3::from(position.as_uvec3().to_array()))
-        })
-        .for_each(|(rock, position)| {
-            if rock {
-                *buffer.block_at_mut(position) = V::into_block();
-            }
-        });
+    let reach = IVec3::splat(size.ceil() as i32);
+    place_decoration_shape(buffer, key, origin, (-reach, reach), |offset| {
+        (sdf::sdf_sphere(offset, size) < 0.).then(V::into_block)
+    });
 }
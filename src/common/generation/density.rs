@@ -0,0 +1,280 @@
+use ::noise::{Fbm, MultiFractal, NoiseFn, SuperSimplex};
+use bevy::math::IVec3;
+use ilattice::glam::UVec3;
+use std::ops::Range;
+
+use crate::{fold_seed, WorldSeed, CHUNK_HEIGHT};
+
+/// Blocks between two samples of the 3D noise, the blocks in between are interpolated.
+const SAMPLE_SPACING: usize = 4;
+
+/// Blocks above a chunk sampled along with it, to tell how deep its blocks are below the surface.
+pub const SURFACE_MARGIN: usize = 12;
+
+const FIELD_HEIGHT: usize = CHUNK_HEIGHT + SURFACE_MARGIN;
+
+/// Depth of the blocks which aren't solid or whose depth can't be told.
+const UNKNOWN_DEPTH: u16 = u16::MAX;
+
+/// Blocks of rock kept between the cheese caves and the surface.
+pub const CHEESE_MIN_DEPTH: f32 = 12.0;
+
+/// Lowest world height caves are carved at, keeps them off the bedrock.
+pub const CAVE_MIN_Y: i32 = 8;
+
+/// How a biome shapes its terrain out of the density function.
+#[derive(Clone, Copy, Debug)]
+pub struct DensitySettings {
+    /// Height over which the 3D noise fades into the heightmap, taller values give bigger cliffs
+    /// and overhangs.
+    pub overhang_height: f32,
+    pub caves: CaveSettings,
+}
+
+impl Default for DensitySettings {
+    fn default() -> Self {
+        Self {
+            overhang_height: 12.0,
+            caves: CaveSettings::default(),
+        }
+    }
+}
+
+impl DensitySettings {
+    /// Averages the settings of the biomes blended in a column, weighted by how much each biome
+    /// weighs in it.
+    pub fn blend(settings: impl IntoIterator<Item = (f32, DensitySettings)>) -> Self {
+        let mut total = 0.0;
+        let mut blended = Self {
+            overhang_height: 0.0,
+            caves: CaveSettings {
+                cheese_threshold: 0.0,
+                worm_chance: 0.0,
+                worm_radius: 0.0,
+            },
+        };
+
+        for (weight, settings) in settings {
+            total += weight;
+            blended.overhang_height += settings.overhang_height * weight;
+            blended.caves.cheese_threshold += settings.caves.cheese_threshold * weight;
+            blended.caves.worm_chance += settings.caves.worm_chance * weight;
+            blended.caves.worm_radius += settings.caves.worm_radius * weight;
+        }
+
+        if total <= 0.0 {
+            return Self::default();
+        }
+
+        blended.overhang_height /= total;
+        blended.caves.cheese_threshold /= total;
+        blended.caves.worm_chance /= total;
+        blended.caves.worm_radius /= total;
+        blended
+    }
+}
+
+/// Caves carved out of the terrain of a biome.
+#[derive(Clone, Copy, Debug)]
+pub struct CaveSettings {
+    /// Noise value over which the large cheese caves open, 1 disables them.
+    pub cheese_threshold: f32,
+    /// Chance of a chunk column to start a worm cave.
+    pub worm_chance: f32,
+    /// Radius of the worm caves, in blocks.
+    pub worm_radius: f32,
+}
+
+impl Default for CaveSettings {
+    fn default() -> Self {
+        Self {
+            cheese_threshold: 0.55,
+            worm_chance: 0.25,
+            worm_radius: 2.5,
+        }
+    }
+}
+
+/// Terrain of a column of a chunk, blended from the biomes weighing in it.
+#[derive(Clone, Copy, Debug)]
+pub struct ColumnTerrain {
+    /// Height the surface of the column would be at without the 3D noise.
+    pub height: f32,
+    pub density: DensitySettings,
+}
+
+impl ColumnTerrain {
+    /// Returns the world heights the surface of the column lies between, the 3D noise moves it by
+    /// up to `overhang_height` blocks from `height`.
+    pub fn surface_heights(&self) -> Range<f32> {
+        let reach = self.density.overhang_height + 1.0;
+        self.height - reach..self.height + reach
+    }
+}
+
+/// Samples of a noise function every `SAMPLE_SPACING` blocks over an area of columns and the
+/// margin above it.
+struct NoiseLattice {
+    samples: Vec<f32>,
+    /// Samples along the X and Z axes.
+    size: usize,
+}
+
+impl NoiseLattice {
+    const HEIGHT: usize = FIELD_HEIGHT / SAMPLE_SPACING + 1;
+
+    /// Samples the noise over `size` by `size` columns from `origin`, both aligned to
+    /// `SAMPLE_SPACING`.
+    fn sample(noise: &impl NoiseFn<f64, 3>, origin: IVec3, size: usize) -> Self {
+        const SPACING: i32 = SAMPLE_SPACING as i32;
+
+        let size = size / SAMPLE_SPACING + 1;
+        let mut samples = Vec::with_capacity(size * Self::HEIGHT * size);
+
+        for z in 0..size {
+            for y in 0..Self::HEIGHT {
+                for x in 0..size {
+                    // World coordinates keep the samples of neighboring chunks lined up
+                    let pos = origin + IVec3::new(x as i32, y as i32, z as i32) * SPACING;
+                    samples.push(noise.get(pos.as_dvec3().to_array()) as f32);
+                }
+            }
+        }
+
+        Self { samples, size }
+    }
+
+    fn at(&self, x: usize, y: usize, z: usize) -> f32 {
+        self.samples[(z * Self::HEIGHT + y) * self.size + x]
+    }
+
+    /// Trilinearly interpolates the samples around a block of the area.
+    fn get(&self, pos: UVec3) -> f32 {
+        let [x, y, z] = pos.to_array().map(|c| c as usize / SAMPLE_SPACING);
+        let [tx, ty, tz] = pos
+            .to_array()
+            .map(|c| (c as usize % SAMPLE_SPACING) as f32 / SAMPLE_SPACING as f32);
+
+        let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+        let row = |y, z| lerp(self.at(x, y, z), self.at(x + 1, y, z), tx);
+        let plane = |z| lerp(row(y, z), row(y + 1, z), ty);
+
+        lerp(plane(z), plane(z + 1), tz)
+    }
+}
+
+/// Shape of the terrain of a chunk, sampled from a density function combining the heightmap with
+/// 3D noise so that it can form cliffs and overhangs.
+///
+/// Shapes can span more columns than a chunk, to find the surfaces around it. They are always as
+/// tall as a chunk, the blocks are indexed from the minimum the shape was sampled at.
+pub struct TerrainShape {
+    /// Solidity of the blocks of the area and of the `SURFACE_MARGIN` blocks above it.
+    solid: Vec<bool>,
+    /// Solid blocks between each solid block and the air above it, laid out like `solid`.
+    /// `UNKNOWN_DEPTH` when the block isn't solid or no air lies above it in the shape.
+    depths: Vec<u16>,
+    /// Blocks hollowed out by cheese caves, laid out like `solid`.
+    caves: Vec<bool>,
+    /// Columns along the X and Z axes.
+    size: usize,
+}
+
+impl TerrainShape {
+    /// Samples the shape of `size` by `size` columns from `min`, `columns` holds their terrain row
+    /// by row along the X axis. Chunks are sampled from their key with a size of `CHUNK_SIZE`,
+    /// larger areas have to stay aligned to the noise samples of the chunks.
+    pub fn sample(seed: WorldSeed, min: IVec3, size: usize, columns: &[ColumnTerrain]) -> Self {
+        let terrain = Fbm::<SuperSimplex>::new(fold_seed(*seed.derive("density")))
+            .set_octaves(3)
+            .set_frequency(0.02)
+            .set_persistence(0.5);
+        let cheese = Fbm::<SuperSimplex>::new(fold_seed(*seed.derive("caves.cheese")))
+            .set_octaves(2)
+            .set_frequency(0.015);
+
+        let terrain = NoiseLattice::sample(&terrain, min, size);
+        let cheese = NoiseLattice::sample(&cheese, min, size);
+
+        let mut shape = Self {
+            solid: vec![false; size * FIELD_HEIGHT * size],
+            depths: vec![UNKNOWN_DEPTH; size * FIELD_HEIGHT * size],
+            caves: vec![false; size * FIELD_HEIGHT * size],
+            size,
+        };
+
+        for z in 0..size as u32 {
+            for x in 0..size as u32 {
+                let column = columns[z as usize * size + x as usize];
+                let overhang_height = column.density.overhang_height.max(f32::EPSILON);
+                let cheese_threshold = column.density.caves.cheese_threshold;
+
+                for y in 0..FIELD_HEIGHT as u32 {
+                    let pos = UVec3::new(x, y, z);
+                    let world_y = (min.y + y as i32) as f32;
+                    let index = shape.index(pos);
+
+                    // The heightmap dominates far from the surface, the noise close to it
+                    let density = (column.height - world_y) / overhang_height + terrain.get(pos);
+                    shape.solid[index] = density > 0.0;
+
+                    if y < CHUNK_HEIGHT as u32
+                        && world_y >= CAVE_MIN_Y as f32
+                        && world_y < column.height - CHEESE_MIN_DEPTH
+                    {
+                        shape.caves[index] = cheese.get(pos) > cheese_threshold;
+                    }
+                }
+
+                // Depths are counted down from the air above, the blocks under the top of the
+                // shape can't tell how deep they are until some air is found
+                let mut below = None;
+                for y in (0..FIELD_HEIGHT as u32).rev() {
+                    let index = shape.index(UVec3::new(x, y, z));
+                    if shape.solid[index] {
+                        shape.depths[index] = below.unwrap_or(UNKNOWN_DEPTH);
+                        below = below.map(|depth| depth + 1);
+                    } else {
+                        below = Some(0);
+                    }
+                }
+            }
+        }
+
+        shape
+    }
+
+    #[inline]
+    fn index(&self, pos: UVec3) -> usize {
+        (pos.z as usize * FIELD_HEIGHT + pos.y as usize) * self.size + pos.x as usize
+    }
+
+    /// Returns whether the terrain is solid at a block of the shape, `pos.y` can reach up to
+    /// `SURFACE_MARGIN` blocks above it.
+    #[inline]
+    pub fn is_solid(&self, pos: UVec3) -> bool {
+        self.solid[self.index(pos)]
+    }
+
+    /// Returns whether a cheese cave hollows out a block of the shape.
+    #[inline]
+    pub fn is_cave(&self, pos: UVec3) -> bool {
+        self.caves[self.index(pos)]
+    }
+
+    /// Returns how many solid blocks lie between a solid block and the air above it, `None` when
+    /// the block is not solid or no air lies above it up to `SURFACE_MARGIN` blocks above the
+    /// shape.
+    #[inline]
+    pub fn depth(&self, pos: UVec3) -> Option<u32> {
+        let depth = self.depths[self.index(pos)];
+        (depth != UNKNOWN_DEPTH).then_some(depth as u32)
+    }
+
+    /// Returns the highest block of a column of the shape with air right above it.
+    pub fn surface(&self, x: u32, z: u32) -> Option<u32> {
+        (0..CHUNK_HEIGHT as u32)
+            .rev()
+            .find(|y| self.depth(UVec3::new(x, *y, z)) == Some(0))
+    }
+}
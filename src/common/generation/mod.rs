@@ -1,59 +1,295 @@
-use crate::{Block, BlockBuffer, ChunkShape, CHUNK_SIZE};
+use crate::{Block, BlockBuffer, ChunkShape, RegionStorage, ResultExt, CHUNK_HEIGHT, CHUNK_SIZE};
 use bevy::{
     app::Plugin,
-    math::{FloatOrd, IVec3, Vec3Swizzles},
+    math::{IVec2, IVec3, Vec2, Vec3Swizzles},
+    prelude::{Deref, Resource},
 };
+use bevy_renet::netcode::generate_random_bytes;
+use ilattice::glam::UVec2;
 use once_cell::sync::Lazy;
-use std::{collections::BTreeMap, sync::RwLock};
+use std::{any::TypeId, sync::RwLock};
+use tracing::{info, warn};
 
 pub mod biomes;
 pub use biomes::*;
 
+pub mod caves;
+pub use caves::*;
+
+pub mod climate;
+pub use climate::*;
+
 pub mod common;
 pub use common::*;
 
+pub mod density;
+pub use density::*;
+
 pub mod noise;
 pub use noise::*;
 
+pub mod ores;
+pub use ores::*;
+
+/// Seed the world is generated from, clients get the one of the server when joining it.
+#[derive(Resource, Clone, Copy, Default, PartialEq, Eq, Debug, Deref)]
+pub struct WorldSeed(pub u64);
+
+impl WorldSeed {
+    /// Returns the seed the saved world was generated from, new worlds use `requested` or a
+    /// random seed which is then saved along with the world.
+    pub fn load_or_create(storage: Option<&RegionStorage>, requested: Option<u64>) -> Self {
+        let saved = storage.and_then(|storage| {
+            storage
+                .load_seed()
+                .log_err_with("Failed to read the world seed")
+                .flatten()
+        });
+
+        if let Some(saved) = saved {
+            // Generating the rest of a saved world from another seed would leave seams
+            if requested.is_some_and(|requested| requested != saved) {
+                warn!("The world was generated from seed {saved}, ignoring the requested seed");
+            }
+            return Self(saved);
+        }
+
+        let seed = requested.unwrap_or_else(|| u64::from_le_bytes(generate_random_bytes()));
+        info!("Generating a new world from seed {seed}");
+
+        if let Some(storage) = storage {
+            storage
+                .save_seed(seed)
+                .log_err_with("Failed to save the world seed");
+        }
+
+        Self(seed)
+    }
+
+    /// Derives the seed of a generation feature, so that features sampling the same noise
+    /// functions don't line up with each other.
+    pub fn derive(self, feature: &str) -> Self {
+        // FNV-1a, unlike the std hashers it is guaranteed to stay the same across builds
+        let hash = feature.bytes().fold(0xcbf2_9ce4_8422_2325, |hash: u64, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+        });
+
+        Self(splitmix64(self.0 ^ hash))
+    }
+
+    /// Derives the seed of a cell of the world, for features scattered cell by cell.
+    pub fn at(self, cell: IVec3) -> Self {
+        Self(
+            cell.to_array()
+                .into_iter()
+                .fold(self.0, |seed, c| splitmix64(seed ^ c as u32 as u64)),
+        )
+    }
+}
+
+/// Scrambles the bits of a value so that close inputs give unrelated outputs.
+const fn splitmix64(value: u64) -> u64 {
+    let mut z = value.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
 pub static TERRAIN_GENERATOR: Lazy<RwLock<TerrainGenerator>> = Lazy::new(Default::default);
 
+/// Climate difference over which the terrain of neighboring biomes blends together.
+const BIOME_BLEND_DISTANCE: f32 = 0.08;
+
+/// Blocks decorations reach sideways from the surface block they stand on, the decorations of the
+/// columns up to this far around a chunk can cross into it. Kept a multiple of the spacing of the
+/// density samples, so that the shapes of the areas around chunks line up with theirs.
+const DECORATION_REACH: usize = 8;
+
+/// Columns along the X and Z axes of the area whose decorations can reach into a chunk.
+const DECORATION_AREA: usize = CHUNK_SIZE + 2 * DECORATION_REACH;
+
+struct RegisteredBiome {
+    climate: ClimateRange,
+    generator: Box<dyn BiomeTerrainGenerator>,
+    /// Type of the generator, a biome is registered once per type.
+    kind: TypeId,
+}
+
 #[derive(Default)]
 pub struct TerrainGenerator {
-    biomes_map: BTreeMap<FloatOrd, Box<dyn BiomeTerrainGenerator>>,
+    biomes: Vec<RegisteredBiome>,
+    ores: Vec<OreDefinition>,
+}
+
+/// Columns of a `size` by `size` area, row by row along the X axis.
+fn area_columns(size: usize) -> impl Iterator<Item = UVec2> {
+    (0..size as u32).flat_map(move |z| (0..size as u32).map(move |x| UVec2::new(x, z)))
 }
 
 impl TerrainGenerator {
-    pub fn register_biome_generator(
+    /// Adds a biome found in the climates of `climate`, biomes are blended together where their
+    /// climates meet. Registering a biome again replaces it.
+    pub fn register_biome<B: BiomeTerrainGenerator>(
         &mut self,
-        chance: f32,
-        biome: Box<dyn BiomeTerrainGenerator>,
+        climate: ClimateRange,
+        biome: Box<B>,
     ) -> &mut Self {
-        self.biomes_map.insert(FloatOrd(chance), biome);
+        let kind = TypeId::of::<B>();
+        self.biomes.retain(|registered| registered.kind != kind);
+        self.biomes.push(RegisteredBiome {
+            climate,
+            generator: biome,
+            kind,
+        });
+        self
+    }
+
+    /// Adds an ore to the ones placed underground, registering an ore again replaces it.
+    pub fn register_ore(&mut self, ore: OreDefinition) -> &mut Self {
+        self.ores.retain(|registered| registered.name != ore.name);
+        self.ores.push(ore);
         self
     }
 
-    fn biome_at(&self, chunk_key: IVec3) -> &Box<dyn BiomeTerrainGenerator> {
-        const BIOME_INVERSE_SCALE: f32 = 0.001;
+    /// Returns the biome whose climates are the closest to `climate`.
+    fn biome_at(&self, climate: Climate) -> &dyn BiomeTerrainGenerator {
+        self.biomes
+            .iter()
+            .min_by(|a, b| {
+                a.climate
+                    .distance(climate)
+                    .total_cmp(&b.climate.distance(climate))
+            })
+            .map(|biome| biome.generator.as_ref())
+            .expect("No biome registered")
+    }
 
-        let coords = noise::voronoi(chunk_key.xzy().truncate().as_vec2() * BIOME_INVERSE_SCALE);
-        let p = FloatOrd(noise::rand2to1i(coords));
+    /// Returns how much every biome weighs in a climate, the closest biome weighs 1 and the others
+    /// fade out over `BIOME_BLEND_DISTANCE`.
+    fn biome_weights(&self, climate: Climate) -> Vec<(f32, &dyn BiomeTerrainGenerator)> {
+        let closest = self
+            .biomes
+            .iter()
+            .map(|biome| biome.climate.distance(climate))
+            .fold(f32::INFINITY, f32::min);
 
-        self.biomes_map
-            .range(..=p)
-            .last()
-            .map_or(self.biomes_map.first_key_value().unwrap().1, |x| x.1)
+        self.biomes
+            .iter()
+            .filter_map(|biome| {
+                let fade = 1.0 - (biome.climate.distance(climate) - closest) / BIOME_BLEND_DISTANCE;
+                (fade > 0.0).then_some((fade * fade, biome.generator.as_ref()))
+            })
+            .collect()
     }
 
-    pub fn generate(&self, chunk_key: IVec3, buffer: &mut BlockBuffer<Block, ChunkShape>) {
-        let biome = self.biome_at(chunk_key);
-        let noise = generate_height_map(chunk_key, CHUNK_SIZE);
+    /// Blends the terrain of the biomes weighing in every column of a `size` by `size` area from
+    /// `min`, and picks the biome carving each column among them.
+    fn blend_columns(
+        &self,
+        seed: WorldSeed,
+        climate: &ClimateNoise,
+        min: IVec3,
+        size: usize,
+    ) -> (Vec<&dyn BiomeTerrainGenerator>, Vec<ColumnTerrain>) {
+        let height_noise = generate_height_noise(min, size, *seed.derive("height"));
+        let dither = *seed.derive("biomes.dither");
 
-        let noise_map = HeightMap::<CHUNK_SIZE, CHUNK_SIZE>::from_slice(&noise);
+        area_columns(size)
+            .zip(height_noise)
+            .map(|(column, noise)| {
+                let world = min.xz() + IVec2::from_array(column.as_ivec2().to_array());
+                let weights = self.biome_weights(climate.at(world));
+                let total: f32 = weights.iter().map(|(weight, _)| weight).sum();
 
-        common::carve_terrain_heightmap(buffer, chunk_key, &noise_map);
+                let terrain = ColumnTerrain {
+                    height: weights
+                        .iter()
+                        .map(|(weight, biome)| weight * biome.terrain_height(noise))
+                        .sum::<f32>()
+                        / total,
+                    density: DensitySettings::blend(
+                        weights
+                            .iter()
+                            .map(|(weight, biome)| (*weight, biome.density_settings())),
+                    ),
+                };
+
+                // Picking the biome at random among the blended ones dithers the border between
+                // them, rather than leaving a sharp line through their strata
+                let mut roll =
+                    noise::rand2to1(world.as_vec2(), Vec2::new(63.726, 10.873), dither).abs()
+                        * total;
+                let (_, biome) = weights
+                    .iter()
+                    .find(|(weight, _)| {
+                        roll -= weight;
+                        roll < 0.0
+                    })
+                    .unwrap_or(&weights[0]);
+
+                (*biome, terrain)
+            })
+            .unzip()
+    }
 
-        biome.carve_terrain(chunk_key, noise_map, buffer);
-        biome.decorate_terrain(chunk_key, noise_map, buffer);
+    /// Generates the chunk at `chunk_key`, the same seed always gives the same blocks.
+    pub fn generate(
+        &self,
+        seed: WorldSeed,
+        chunk_key: IVec3,
+        buffer: &mut BlockBuffer<Block, ChunkShape>,
+    ) {
+        let climate = ClimateNoise::new(seed);
+
+        // The columns around the chunk are blended along with it, for their decorations
+        let area_min = chunk_key - IVec3::new(DECORATION_REACH as i32, 0, DECORATION_REACH as i32);
+        let (area_biomes, area_terrain) =
+            self.blend_columns(seed, &climate, area_min, DECORATION_AREA);
+
+        let chunk_area = DECORATION_REACH as u32..(DECORATION_REACH + CHUNK_SIZE) as u32;
+        let (biomes, columns): (Vec<_>, Vec<_>) = area_columns(DECORATION_AREA)
+            .zip(area_biomes.iter().zip(&area_terrain))
+            .filter(|(column, _)| chunk_area.contains(&column.x) && chunk_area.contains(&column.y))
+            .map(|(_, (biome, terrain))| (*biome, *terrain))
+            .unzip();
+        let shape = TerrainShape::sample(seed, chunk_key, CHUNK_SIZE, &columns);
+
+        common::carve_terrain_density(buffer, chunk_key, &shape);
+
+        // Worms are carved with the settings of the biome they start in, so that the caves
+        // crossing biome borders line up
+        carve_cheese_caves(buffer, &shape);
+        carve_worm_caves(seed, chunk_key, &shape, buffer, |column| {
+            self.biome_at(climate.at(column.xz())).density_settings().caves
+        });
+
+        for (column, biome) in area_columns(CHUNK_SIZE).zip(&biomes) {
+            biome.carve_column(seed, chunk_key, column, &shape, buffer);
+        }
+
+        place_ores(seed, chunk_key, &self.ores, buffer);
+
+        // Decorations are less than a chunk tall, the ones standing on the surfaces of the chunks
+        // right above and below reach into it as well
+        for level in -1..=1 {
+            let min = area_min + IVec3::Y * level * CHUNK_HEIGHT as i32;
+            let (bottom, top) = (min.y as f32, (min.y + CHUNK_HEIGHT as i32) as f32);
+            let has_surface = area_terrain.iter().any(|terrain| {
+                let heights = terrain.surface_heights();
+                heights.start < top && heights.end > bottom
+            });
+            if !has_surface {
+                continue;
+            }
+
+            let level_shape = TerrainShape::sample(seed, min, DECORATION_AREA, &area_terrain);
+            for (column, biome) in area_columns(DECORATION_AREA).zip(&area_biomes) {
+                if let Some(y) = level_shape.surface(column.x, column.y) {
+                    let surface = min + IVec3::new(column.x as i32, y as i32, column.y as i32);
+                    biome.decorate_column(seed, surface, chunk_key, buffer);
+                }
+            }
+        }
 
         if chunk_key.y == 0 {
             terrain_generate_world_bottom_border(buffer);
@@ -64,9 +300,59 @@ impl TerrainGenerator {
 pub struct TerrainGeneratorPlugin;
 impl Plugin for TerrainGeneratorPlugin {
     fn build(&self, _app: &mut bevy::app::App) {
-        TERRAIN_GENERATOR.write().unwrap().register_biome_generator(
-            0.0f32,
-            biomes::PlainsBiomeTerrainGenerator.into_boxed_generator(),
-        );
+        // Land biomes lie between the oceans and the mountains
+        const LAND: std::ops::Range<f32> = -0.25..0.35;
+
+        // Every app of the process shares the generator, building the plugin again replaces the
+        // biomes registered by the previous ones
+        TERRAIN_GENERATOR
+            .write()
+            .unwrap()
+            .register_biome(
+                ClimateRange {
+                    continentalness: -1.0..-0.25,
+                    ..ClimateRange::ANY
+                },
+                biomes::OceanBiomeTerrainGenerator.into_boxed_generator(),
+            )
+            .register_biome(
+                ClimateRange {
+                    continentalness: 0.35..1.0,
+                    ..ClimateRange::ANY
+                },
+                biomes::MountainsBiomeTerrainGenerator.into_boxed_generator(),
+            )
+            .register_biome(
+                ClimateRange {
+                    temperature: -0.25..0.25,
+                    humidity: -1.0..0.3,
+                    continentalness: LAND,
+                },
+                biomes::PlainsBiomeTerrainGenerator.into_boxed_generator(),
+            )
+            .register_biome(
+                ClimateRange {
+                    temperature: 0.25..1.0,
+                    humidity: -1.0..0.0,
+                    continentalness: LAND,
+                },
+                biomes::DesertBiomeTerrainGenerator.into_boxed_generator(),
+            )
+            .register_biome(
+                ClimateRange {
+                    temperature: -1.0..-0.25,
+                    humidity: -1.0..1.0,
+                    continentalness: LAND,
+                },
+                biomes::SnowyTaigaBiomeTerrainGenerator.into_boxed_generator(),
+            )
+            .register_biome(
+                ClimateRange {
+                    temperature: -0.25..1.0,
+                    humidity: 0.3..1.0,
+                    continentalness: LAND,
+                },
+                biomes::SwampBiomeTerrainGenerator.into_boxed_generator(),
+            );
     }
 }
@@ -4,28 +4,40 @@ use noise::{
     Fbm, MultiFractal, SuperSimplex,
 };
 
-pub fn rand2to1(p: Vec2, dot: Vec2) -> f32 {
-    let sp: Vec2 = p.to_array().map(f32::sin).into();
-    let random = sp.dot(dot);
-    (random.sin() * 143_758.55).fract()
+/// Largest offset a seed applies to the inputs of the hash functions, small enough for the
+/// inputs to keep their precision.
+const SEED_OFFSET_RANGE: f32 = 1024.0;
+
+/// Offset applied to the inputs of the hash functions so that every seed gets its own values.
+#[inline]
+fn seed_offset(seed: u64) -> Vec2 {
+    Vec2::new(seed as u32 as f32, (seed >> 32) as u32 as f32) / u32::MAX as f32 * SEED_OFFSET_RANGE
+}
+
+pub fn rand2to1(p: Vec2, dot: Vec2, seed: u64) -> f32 {
+    let offset = seed_offset(seed);
+    let sp: Vec2 = (p + offset).to_array().map(f32::sin).into();
+    let random = sp.dot(dot + offset);
+    // Scaled up in single precision, the fraction would be left with a handful of bits
+    (f64::from(random).sin() * 143_758.55).fract() as f32
 }
 
-pub fn rand2to1i(vec: Vec2) -> f32 {
-    let mut p3 = (vec.xyx() * 0.39).fract();
+pub fn rand2to1i(vec: Vec2, seed: u64) -> f32 {
+    let mut p3 = ((vec + seed_offset(seed)).xyx() * 0.39).fract();
     p3 += p3.dot(p3.yzx());
     (p3.x + p3.y) * p3.z.fract()
 }
 
 
 #[inline(always)]
-pub fn rand2to2(p: Vec2) -> Vec2 {
+pub fn rand2to2(p: Vec2, seed: u64) -> Vec2 {
     Vec2::new(
-        rand2to1(p, Vec2::new(12.989, 78.233)),
-        rand2to1(p, Vec2::new(39.346, 11.135)),
+        rand2to1(p, Vec2::new(12.989, 78.233), seed),
+        rand2to1(p, Vec2::new(39.346, 11.135), seed),
     )
 }
 
-pub fn voronoi(p: Vec2) -> Vec2 {
+pub fn voronoi(p: Vec2, seed: u64) -> Vec2 {
     const NEIGHBOR_RANGE: i32 = 2;
 
     let base_cel = p.floor();
@@ -35,7 +47,7 @@ pub fn voronoi(p: Vec2) -> Vec2 {
     for x in -NEIGHBOR_RANGE..=NEIGHBOR_RANGE {
         for y in -NEIGHBOR_RANGE..=NEIGHBOR_RANGE {
             let cell = base_cel + Vec2::new(x as f32, y as f32);
-            let cell_pos = cell + rand2to2(cell);
+            let cell_pos = cell + rand2to2(cell, seed);
             let distance = (cell_pos - p).length_squared();
 
             if distance < min_distance {
@@ -48,8 +60,16 @@ pub fn voronoi(p: Vec2) -> Vec2 {
     closest_point
 }
 
-pub fn generate_height_map(key: IVec3, chunk_len: usize) -> Vec<f32> {
-    let noise = Fbm::<SuperSimplex>::new(0)
+/// Folds a seed into the 32 bits the noise functions of the `noise` crate take.
+#[inline]
+pub const fn fold_seed(seed: u64) -> u32 {
+    (seed ^ (seed >> 32)) as u32
+}
+
+/// Generates the noise the surface height of every column of a chunk is shaped from, ranging from
+/// -1 to 1 row by row along the X axis. Chunks of the same column share it.
+pub fn generate_height_noise(key: IVec3, chunk_len: usize, seed: u64) -> Vec<f32> {
+    let noise = Fbm::<SuperSimplex>::new(fold_seed(seed))
         .set_octaves(4)
         .set_frequency(0.005)
         .set_persistence(0.5)
@@ -58,26 +78,9 @@ pub fn generate_height_map(key: IVec3, chunk_len: usize) -> Vec<f32> {
     PlaneMapBuilder::new(noise)
         .set_size(chunk_len, chunk_len)
         .set_x_bounds(key.x as f64, (key.x + chunk_len as i32) as f64)
-        .set_y_bounds(key.y as f64, (key.y + chunk_len as i32) as f64)
+        .set_y_bounds(key.z as f64, (key.z + chunk_len as i32) as f64)
         .build()
         .into_iter()
-        .map(|x| x.mul_add(20f64, 132f64) as f32)
+        .map(|x| x as f32)
         .collect()
 }
-
-#[derive(Clone, Copy)]
-pub struct HeightMap<'a, const W: usize, const H: usize> {
-    slice: &'a [f32],
-}
-
-impl<'a, const W: usize, const H: usize> HeightMap<'a, W, H> {
-    #[inline]
-    pub fn get(&self, pos: [u32; 2]) -> u32 {
-        self.slice[pos[1] as usize * W + pos[0] as usize].round() as u32
-    }
-
-    #[inline]
-    pub const fn from_slice(slice: &'a [f32]) -> Self {
-        Self { slice }
-    }
-}
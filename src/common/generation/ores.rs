@@ -0,0 +1,122 @@
+use bevy::math::IVec3;
+use rand_chacha::{
+    rand_core::{RngCore, SeedableRng},
+    ChaCha8Rng,
+};
+use std::ops::Range;
+
+use crate::{chunk_local, Block, BlockBuffer, BlockMaterial, ChunkShape, Stone, WorldSeed, CHUNK_DIMS};
+
+/// Veins of an ore scattered through the underground.
+#[derive(Clone, Debug)]
+pub struct OreDefinition {
+    /// Id string of the ore material, also seeds the placement of its veins.
+    pub name: String,
+    pub block: Block,
+    /// World heights the veins are placed between, veins are cut off at its bounds.
+    pub heights: Range<i32>,
+    /// Blocks of the vein, fewer end up placed when it runs into blocks it can't replace.
+    pub vein_size: u32,
+    /// Veins tried in every chunk, the ones starting outside of `heights` are dropped.
+    pub veins_per_chunk: u32,
+    /// Blocks the veins are allowed to replace.
+    pub replaceable: Vec<Block>,
+}
+
+impl OreDefinition {
+    /// Ore of material `M` replacing stone.
+    pub fn new<M: BlockMaterial>(
+        heights: Range<i32>,
+        vein_size: u32,
+        veins_per_chunk: u32,
+    ) -> Self {
+        Self {
+            name: M::id_string(),
+            block: M::into_block(),
+            heights,
+            vein_size,
+            veins_per_chunk,
+            replaceable: vec![Stone::into_block()],
+        }
+    }
+
+    /// Lets the veins replace blocks of material `M` as well.
+    pub fn replacing<M: BlockMaterial>(mut self) -> Self {
+        self.replaceable.push(M::into_block());
+        self
+    }
+}
+
+/// Places the veins of the ores crossing a chunk, including the ones starting in the chunks around
+/// it. The veins only depend on the seed and the key of the chunk they start in.
+///
+/// Every vein is walked from its start with the random generator of its chunk, so each chunk
+/// places its part of the vein without having to know about the chunks around it.
+pub fn place_ores(
+    seed: WorldSeed,
+    key: IVec3,
+    ores: &[OreDefinition],
+    buffer: &mut BlockBuffer<Block, ChunkShape>,
+) {
+    let seed = seed.derive("ores");
+
+    for ore in ores {
+        let ore_seed = seed.derive(&ore.name);
+
+        // Veins are walked one block per step, they can't reach further than their size
+        let reach = CHUNK_DIMS.map(|dim| (ore.vein_size as i32 + dim - 1) / dim);
+
+        for dz in -reach.z..=reach.z {
+            for dy in -reach.y..=reach.y {
+                for dx in -reach.x..=reach.x {
+                    let origin = key + IVec3::new(dx, dy, dz) * CHUNK_DIMS;
+                    let mut rng = ChaCha8Rng::seed_from_u64(*ore_seed.at(origin));
+                    place_chunk_veins(ore, &mut rng, origin, key, buffer);
+                }
+            }
+        }
+    }
+}
+
+/// Places the parts of the veins starting in the chunk at `origin` which fall in the chunk at
+/// `key`.
+fn place_chunk_veins(
+    ore: &OreDefinition,
+    rng: &mut ChaCha8Rng,
+    origin: IVec3,
+    key: IVec3,
+    buffer: &mut BlockBuffer<Block, ChunkShape>,
+) {
+    for _ in 0..ore.veins_per_chunk {
+        let offset = CHUNK_DIMS.to_array().map(|dim| (rng.next_u32() % dim as u32) as i32);
+        let start = origin + IVec3::from_array(offset);
+
+        if ore.heights.contains(&start.y) {
+            place_vein(ore, rng, key, start, buffer);
+        }
+    }
+}
+
+/// Grows a vein by walking from `start` one block at a time along random axes, only the blocks
+/// of the vein falling in the chunk at `key` are placed.
+fn place_vein(
+    ore: &OreDefinition,
+    rng: &mut ChaCha8Rng,
+    key: IVec3,
+    start: IVec3,
+    buffer: &mut BlockBuffer<Block, ChunkShape>,
+) {
+    let mut pos = start;
+
+    for _ in 0..ore.vein_size {
+        if let Some(local) = chunk_local(key, pos).filter(|_| ore.heights.contains(&pos.y)) {
+            if ore.replaceable.contains(&buffer.block_at(local)) {
+                buffer.set_block(local, ore.block);
+            }
+        }
+
+        let step = rng.next_u32();
+        let direction = if step >> 31 == 0 { -1 } else { 1 };
+        pos[(step % 3) as usize] += direction;
+    }
+}
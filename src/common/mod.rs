@@ -19,5 +19,11 @@ pub use error::*;
 pub mod logging;
 pub use logging::*;
 
+pub mod movement;
+pub use movement::*;
+
+pub mod physics;
+pub use physics::*;
+
 pub mod sdf;
 pub use sdf::*;
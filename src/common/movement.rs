@@ -0,0 +1,85 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{step_body, BodyState, Collider, MovementMode};
+
+/// Rate at which players sample and apply their inputs, in steps per second.
+pub const MOVEMENT_RATE: f64 = 60.0;
+
+/// Duration of a single movement step, in seconds.
+pub const MOVEMENT_STEP: f32 = (1. / MOVEMENT_RATE) as f32;
+
+/// Horizontal speed of the player in walk mode, in blocks per second.
+pub const WALK_SPEED: f32 = 4.3;
+
+/// Speed multiplier applied when sprinting in walk mode.
+pub const SPRINT_MULTIPLIER: f32 = 1.3;
+
+/// Vertical speed given to the player when jumping, enough to clear a single block.
+pub const JUMP_VELOCITY: f32 = 8.5;
+
+/// Speed of the player in fly mode, in blocks per second.
+pub const FLY_SPEED: f32 = 60.0;
+
+/// Speed multiplier applied when sprinting in fly mode.
+pub const FLY_BOOST_MULTIPLIER: f32 = 8.0;
+
+/// What a player asked for during a single movement step.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct PlayerInput {
+    /// Increases by one every step, the server acknowledges inputs with it.
+    pub sequence: u32,
+    /// Wished movement relative to the yaw of the player, +X goes right, +Y up and -Z forward.
+    pub direction: Vec3,
+    pub yaw: f32,
+    pub pitch: f32,
+    pub sprint: bool,
+    pub mode: MovementMode,
+}
+
+/// Advances a player by a step of `dt` seconds following its input.
+///
+/// The client predicting its own movement and the server both run this, so they only disagree
+/// when they don't see the same blocks.
+pub fn step_player(
+    is_solid: impl Fn(IVec3) -> bool,
+    collider: &Collider,
+    input: &PlayerInput,
+    state: &mut BodyState,
+    dt: f32,
+) {
+    // Inputs come from the network, they must not move players faster than the keyboard does
+    let direction = input.direction.clamp(Vec3::NEG_ONE, Vec3::ONE);
+    let wished = Quat::from_rotation_y(input.yaw) * direction.with_y(0.);
+
+    match input.mode {
+        MovementMode::Walk => {
+            let speed = if input.sprint {
+                WALK_SPEED * SPRINT_MULTIPLIER
+            } else {
+                WALK_SPEED
+            };
+            let horizontal = wished.normalize_or_zero() * speed;
+
+            state.velocity.x = horizontal.x;
+            state.velocity.z = horizontal.z;
+
+            if direction.y > 0. && state.on_ground {
+                state.velocity.y = JUMP_VELOCITY;
+            }
+
+            step_body(is_solid, collider, state, dt);
+        }
+        MovementMode::Fly => {
+            let speed = if input.sprint {
+                FLY_SPEED * FLY_BOOST_MULTIPLIER
+            } else {
+                FLY_SPEED
+            };
+
+            state.position += (wished + Vec3::Y * direction.y) * speed * dt;
+            state.velocity = Vec3::ZERO;
+            state.on_ground = false;
+        }
+    }
+}
@@ -0,0 +1,106 @@
+use bevy_renet::netcode::{
+    generate_random_bytes, ConnectToken, NETCODE_KEY_BYTES, NETCODE_USER_DATA_BYTES,
+};
+use std::{fs::File, net::SocketAddr, path::Path, time::SystemTime};
+
+use crate::{common::PROTOCOL_ID, ClientError, GameError, ServerError};
+
+/// Key shared by a server in secure mode and the issuer of its connect tokens.
+pub type PrivateKey = [u8; NETCODE_KEY_BYTES];
+
+/// How long a connect token can be used to join the server after being issued, in seconds.
+pub const TOKEN_EXPIRE_SECONDS: u64 = 300;
+
+/// How long the connection lasts without hearing from the other side, in seconds.
+pub const CONNECTION_TIMEOUT_SECONDS: i32 = 15;
+
+/// Longest username carried by a connect token, in bytes.
+pub const MAX_USERNAME_BYTES: usize = 32;
+
+pub fn generate_private_key() -> PrivateKey {
+    generate_random_bytes()
+}
+
+/// Returns a client id unlikely to collide with the ones of other clients.
+pub fn generate_client_id() -> u64 {
+    u64::from_le_bytes(generate_random_bytes())
+}
+
+pub fn encode_private_key(key: &PrivateKey) -> String {
+    key.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// Parses a private key written as hexadecimal by [`encode_private_key`].
+pub fn parse_private_key(hex: &str) -> Result<PrivateKey, GameError> {
+    let hex = hex.trim();
+    if hex.len() != NETCODE_KEY_BYTES * 2 || !hex.bytes().all(|digit| digit.is_ascii_hexdigit()) {
+        return Err(GameError::Parser(format!(
+            "private key must be {} hexadecimal digits",
+            NETCODE_KEY_BYTES * 2
+        )));
+    }
+
+    let mut key = [0; NETCODE_KEY_BYTES];
+    for (byte, digits) in key.iter_mut().zip(hex.as_bytes().chunks(2)) {
+        // Hexadecimal digits are ASCII so every pair of them is a valid string
+        let digits = std::str::from_utf8(digits).unwrap_or_default();
+        *byte = u8::from_str_radix(digits, 16).map_err(|e| GameError::Parser(e.to_string()))?;
+    }
+
+    Ok(key)
+}
+
+/// Packs a username into the user data of a connect token, prefixed with its length.
+pub fn encode_username(username: &str) -> Result<[u8; NETCODE_USER_DATA_BYTES], GameError> {
+    if username.is_empty() || username.len() > MAX_USERNAME_BYTES {
+        return Err(GameError::Parser(format!(
+            "username must be between 1 and {MAX_USERNAME_BYTES} bytes long"
+        )));
+    }
+
+    let mut user_data = [0; NETCODE_USER_DATA_BYTES];
+    user_data[0] = username.len() as u8;
+    user_data[1..=username.len()].copy_from_slice(username.as_bytes());
+
+    Ok(user_data)
+}
+
+/// Reads back the username packed by [`encode_username`].
+pub fn decode_username(user_data: &[u8; NETCODE_USER_DATA_BYTES]) -> Option<String> {
+    let len = user_data[0] as usize;
+    if len == 0 || len > MAX_USERNAME_BYTES {
+        return None;
+    }
+
+    String::from_utf8(user_data[1..=len].to_vec()).ok()
+}
+
+/// Mints a token letting a player join the servers at `server_addresses` under `username`.
+pub fn issue_connect_token(
+    private_key: &PrivateKey,
+    username: &str,
+    server_addresses: Vec<SocketAddr>,
+) -> Result<ConnectToken, GameError> {
+    let current_time = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_err(|e| ServerError::AuthenticationError(e.to_string()))?;
+    let user_data = encode_username(username)?;
+
+    ConnectToken::generate(
+        current_time,
+        PROTOCOL_ID,
+        TOKEN_EXPIRE_SECONDS,
+        generate_client_id(),
+        CONNECTION_TIMEOUT_SECONDS,
+        server_addresses,
+        Some(&user_data),
+        private_key,
+    )
+    .map_err(|e| ServerError::AuthenticationError(e.to_string()).into())
+}
+
+/// Reads a connect token written by the token issuer.
+pub fn read_connect_token(path: &Path) -> Result<ConnectToken, GameError> {
+    ConnectToken::read(&mut File::open(path)?)
+        .map_err(|e| ClientError::NetworkError(e.to_string()).into())
+}
@@ -0,0 +1,403 @@
+use bevy::{prelude::*, time::TimeUpdateStrategy};
+use bevy_renet::{
+    renet::{ClientId, RenetClient, RenetServer},
+    RenetClientPlugin, RenetReceive, RenetSend, RenetServerPlugin,
+};
+use rand_chacha::{
+    rand_core::{RngCore, SeedableRng},
+    ChaCha8Rng,
+};
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use crate::{LocalClientId, NetworkConfig};
+
+/// How packets behave on the links of a [`LoopbackNetwork`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LinkConditions {
+    /// Delay of every packet, each way.
+    pub latency: Duration,
+    /// Upper bound of the random delay added to every packet, which can reorder them.
+    pub jitter: Duration,
+    /// Chance for a packet to be dropped, between 0 and 1.
+    pub packet_loss: f32,
+}
+
+struct InFlightPacket {
+    deliver_at: Duration,
+    payload: Vec<u8>,
+}
+
+/// Packets travelling one way, in the order they were sent.
+#[derive(Default)]
+struct PacketQueue(Vec<InFlightPacket>);
+
+impl PacketQueue {
+    /// Removes the packets which reached the other side by `now`, earliest first.
+    fn take_delivered(&mut self, now: Duration) -> Vec<Vec<u8>> {
+        let (mut delivered, in_flight): (Vec<_>, Vec<_>) =
+            self.0.drain(..).partition(|packet| packet.deliver_at <= now);
+        self.0 = in_flight;
+
+        delivered.sort_by_key(|packet| packet.deliver_at);
+        delivered.into_iter().map(|packet| packet.payload).collect()
+    }
+}
+
+struct Link {
+    client_id: ClientId,
+    to_server: PacketQueue,
+    to_client: PacketQueue,
+    /// The server accepted the connection.
+    connected: bool,
+    /// Either side dropped the connection.
+    closed: bool,
+}
+
+struct NetworkState {
+    now: Duration,
+    conditions: LinkConditions,
+    rng: ChaCha8Rng,
+    links: Vec<Link>,
+}
+
+impl NetworkState {
+    /// Returns when a packet sent now arrives, or `None` if it gets lost.
+    fn schedule(&mut self) -> Option<Duration> {
+        let roll = |rng: &mut ChaCha8Rng| rng.next_u32() as f32 / u32::MAX as f32;
+
+        if roll(&mut self.rng) < self.conditions.packet_loss {
+            return None;
+        }
+
+        let jitter = self.conditions.jitter.mul_f32(roll(&mut self.rng));
+        Some(self.now + self.conditions.latency + jitter)
+    }
+
+    fn send(&mut self, client_id: ClientId, to_server: bool, payload: Vec<u8>) {
+        let Some(deliver_at) = self.schedule() else {
+            return;
+        };
+
+        if let Some(link) = self.links.iter_mut().find(|link| link.client_id == client_id) {
+            let queue = if to_server {
+                &mut link.to_server
+            } else {
+                &mut link.to_client
+            };
+            queue.0.push(InFlightPacket {
+                deliver_at,
+                payload,
+            });
+        }
+    }
+
+    fn link_mut(&mut self, client_id: ClientId) -> Option<&mut Link> {
+        self.links.iter_mut().find(|link| link.client_id == client_id)
+    }
+}
+
+/// An in-memory network carrying the packets of a server and its clients within one process.
+///
+/// Time only moves forward through [`LoopbackNetwork::advance`], and packet loss and jitter are
+/// drawn from a seeded generator, so a run can be reproduced exactly.
+#[derive(Clone)]
+pub struct LoopbackNetwork(Arc<Mutex<NetworkState>>);
+
+impl LoopbackNetwork {
+    pub fn new(conditions: LinkConditions, seed: u64) -> Self {
+        Self(Arc::new(Mutex::new(NetworkState {
+            now: Duration::ZERO,
+            conditions,
+            rng: ChaCha8Rng::seed_from_u64(seed),
+            links: Vec::new(),
+        })))
+    }
+
+    pub fn advance(&self, delta: Duration) {
+        self.0.lock().unwrap().now += delta;
+    }
+
+    pub fn set_conditions(&self, conditions: LinkConditions) {
+        self.0.lock().unwrap().conditions = conditions;
+    }
+
+    pub fn server_transport(&self) -> LoopbackServerTransport {
+        LoopbackServerTransport {
+            network: self.clone(),
+        }
+    }
+
+    /// Opens a link for a new client, the server accepts it on its next update.
+    pub fn connect_client(&self, client_id: ClientId) -> LoopbackClientTransport {
+        self.0.lock().unwrap().links.push(Link {
+            client_id,
+            to_server: PacketQueue::default(),
+            to_client: PacketQueue::default(),
+            connected: false,
+            closed: false,
+        });
+
+        LoopbackClientTransport {
+            network: self.clone(),
+            client_id,
+        }
+    }
+}
+
+/// Server side of a [`LoopbackNetwork`].
+#[derive(Resource)]
+pub struct LoopbackServerTransport {
+    network: LoopbackNetwork,
+}
+
+/// Client side of a [`LoopbackNetwork`].
+#[derive(Resource)]
+pub struct LoopbackClientTransport {
+    network: LoopbackNetwork,
+    client_id: ClientId,
+}
+
+impl LoopbackClientTransport {
+    #[inline]
+    pub fn client_id(&self) -> ClientId {
+        self.client_id
+    }
+}
+
+pub fn receive_server_packets(
+    transport: Res<LoopbackServerTransport>,
+    mut server: ResMut<RenetServer>,
+) {
+    let mut network = transport.network.0.lock().unwrap();
+    let now = network.now;
+
+    for link in network.links.iter_mut() {
+        if link.closed {
+            if link.connected {
+                link.connected = false;
+                server.remove_connection(link.client_id);
+            }
+            continue;
+        }
+
+        if !link.connected {
+            link.connected = true;
+            server.add_connection(link.client_id);
+        }
+
+        for payload in link.to_server.take_delivered(now) {
+            // Packets of a connection the server just dropped are expected
+            let _ = server.process_packet_from(&payload, link.client_id);
+        }
+    }
+}
+
+pub fn send_server_packets(
+    transport: Res<LoopbackServerTransport>,
+    mut server: ResMut<RenetServer>,
+) {
+    let mut network = transport.network.0.lock().unwrap();
+
+    for client_id in server.disconnections_id() {
+        if let Some(link) = network.link_mut(client_id) {
+            link.closed = true;
+            link.connected = false;
+        }
+        server.remove_connection(client_id);
+    }
+
+    let connected: Vec<ClientId> = network
+        .links
+        .iter()
+        .filter(|link| link.connected)
+        .map(|link| link.client_id)
+        .collect();
+
+    for client_id in connected {
+        if let Ok(packets) = server.get_packets_to_send(client_id) {
+            for payload in packets {
+                network.send(client_id, false, payload);
+            }
+        }
+    }
+}
+
+pub fn receive_client_packets(
+    transport: Res<LoopbackClientTransport>,
+    mut client: ResMut<RenetClient>,
+) {
+    let mut network = transport.network.0.lock().unwrap();
+    let now = network.now;
+    let Some(link) = network.link_mut(transport.client_id) else {
+        return;
+    };
+
+    if link.closed {
+        if !client.is_disconnected() {
+            client.disconnect_due_to_transport();
+        }
+        return;
+    }
+
+    if link.connected && client.is_connecting() {
+        client.set_connected();
+    }
+
+    for payload in link.to_client.take_delivered(now) {
+        client.process_packet(&payload);
+    }
+}
+
+pub fn send_client_packets(
+    transport: Res<LoopbackClientTransport>,
+    mut client: ResMut<RenetClient>,
+) {
+    let mut network = transport.network.0.lock().unwrap();
+
+    if client.is_disconnected() {
+        if let Some(link) = network.link_mut(transport.client_id) {
+            link.closed = true;
+        }
+        return;
+    }
+
+    for payload in client.get_packets_to_send() {
+        network.send(transport.client_id, true, payload);
+    }
+}
+
+/// Carries the packets of the [`RenetServer`] through its [`LoopbackServerTransport`].
+pub struct LoopbackServerPlugin;
+
+impl Plugin for LoopbackServerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            PreUpdate,
+            receive_server_packets
+                .in_set(RenetReceive)
+                .run_if(resource_exists::<LoopbackServerTransport>),
+        )
+        .add_systems(
+            PostUpdate,
+            send_server_packets
+                .in_set(RenetSend)
+                .run_if(resource_exists::<LoopbackServerTransport>),
+        );
+    }
+}
+
+/// Carries the packets of the [`RenetClient`] through its [`LoopbackClientTransport`].
+pub struct LoopbackClientPlugin;
+
+impl Plugin for LoopbackClientPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            PreUpdate,
+            receive_client_packets
+                .in_set(RenetReceive)
+                .run_if(resource_exists::<LoopbackClientTransport>),
+        )
+        .add_systems(
+            PostUpdate,
+            send_client_packets
+                .in_set(RenetSend)
+                .run_if(resource_exists::<LoopbackClientTransport>),
+        );
+    }
+}
+
+/// Runs a server app and client apps in the same process, linked by a [`LoopbackNetwork`] and
+/// updated in lockstep with a fixed time step.
+pub struct LoopbackHarness {
+    pub server: App,
+    pub clients: Vec<App>,
+    pub network: LoopbackNetwork,
+    step: Duration,
+}
+
+impl LoopbackHarness {
+    /// Wires the apps to a new network, client ids are their index plus one.
+    ///
+    /// Apps get the renet resources and plugins they lack, with the channels of the default
+    /// [`NetworkConfig`].
+    pub fn new(
+        mut server: App,
+        clients: impl IntoIterator<Item = App>,
+        conditions: LinkConditions,
+        seed: u64,
+        step: Duration,
+    ) -> Self {
+        let network = LoopbackNetwork::new(conditions, seed);
+        let connection_config = NetworkConfig::default().connection_config();
+
+        if !server.world().contains_resource::<RenetServer>() {
+            server.insert_resource(RenetServer::new(connection_config.clone()));
+        }
+        if !server.is_plugin_added::<RenetServerPlugin>() {
+            server.add_plugins(RenetServerPlugin);
+        }
+        server
+            .insert_resource(TimeUpdateStrategy::ManualDuration(step))
+            .insert_resource(network.server_transport())
+            .add_plugins(LoopbackServerPlugin);
+        server.finish();
+        server.cleanup();
+
+        let clients = clients
+            .into_iter()
+            .enumerate()
+            .map(|(index, mut client)| {
+                let transport = network.connect_client(index as ClientId + 1);
+
+                if !client.world().contains_resource::<RenetClient>() {
+                    client.insert_resource(RenetClient::new(connection_config.clone()));
+                }
+                if !client.is_plugin_added::<RenetClientPlugin>() {
+                    client.add_plugins(RenetClientPlugin);
+                }
+                client
+                    .insert_resource(TimeUpdateStrategy::ManualDuration(step))
+                    .insert_resource(LocalClientId(transport.client_id()))
+                    .insert_resource(transport)
+                    .add_plugins(LoopbackClientPlugin);
+                client.finish();
+                client.cleanup();
+
+                client
+            })
+            .collect();
+
+        Self {
+            server,
+            clients,
+            network,
+            step,
+        }
+    }
+
+    /// Advances the network and every app by one time step, the server first.
+    pub fn step(&mut self) {
+        self.network.advance(self.step);
+        self.server.update();
+        self.clients.iter_mut().for_each(App::update);
+    }
+
+    /// Steps until `done` returns true, returns false if it still doesn't after `max_steps`.
+    pub fn step_until(
+        &mut self,
+        max_steps: usize,
+        mut done: impl FnMut(&mut Self) -> bool,
+    ) -> bool {
+        for _ in 0..max_steps {
+            if done(self) {
+                return true;
+            }
+            self.step();
+        }
+
+        done(self)
+    }
+}
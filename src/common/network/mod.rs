@@ -3,3 +3,12 @@ pub use protocol::*;
 
 pub mod server;
 pub use server::*;
+
+pub mod loopback;
+pub use loopback::*;
+
+pub mod auth;
+pub use auth::*;
+
+pub mod settings;
+pub use settings::*;
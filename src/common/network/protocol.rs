@@ -4,21 +4,51 @@ use bevy::{
         entity::Entity,
         resource::Resource
     },
-    math::Vec3,
+    math::{IVec3, Vec3},
     platform::collections::HashMap,
 };
-use bevy_renet::renet::{ChannelConfig, ClientId, SendType};
+use bevy_renet::renet::{ChannelConfig, ClientId, ConnectionConfig, SendType};
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
 
+use crate::{
+    Block, BodyState, GameError, GameMode, InvalidData, MaterialDefinition, PlayerInput,
+};
+
 pub const PROTOCOL_ID: u64 = 1234;
 
+/// Version of the messages exchanged by the client and the server, bump it whenever
+/// [`NetworkMessage`] changes.
+pub const PROTOCOL_VERSION: u32 = 2;
+
+/// Rate at which the server simulates the world and sends snapshots, in ticks per second.
+pub const TICK_RATE: f64 = 20.0;
+
+/// Port the dedicated server listens on by default.
+pub const DEFAULT_SERVER_PORT: u16 = 5000;
+
+/// Channel carrying messages which must arrive in order, like chunk data.
+pub const RELIABLE_CHANNEL: u8 = 0;
+
+/// Channel carrying frequent messages which can be dropped, like player positions.
+pub const UNRELIABLE_CHANNEL: u8 = 1;
+
+/// Longest line players can send in the chat, in characters.
+pub const MAX_CHAT_LENGTH: usize = 256;
+
+/// Upper bound on the size of a decoded message, guards against corrupt length prefixes.
+const MESSAGE_SIZE_LIMIT: usize = 8 * 1024 * 1024;
+
+/// Id the server knows the local client by, inserted along with its transport.
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct LocalClientId(pub ClientId);
+
 #[derive(Debug, Default, Resource)]
 pub struct Lobby {
     pub players: HashMap<ClientId, Entity>,
 }
 
-#[derive(Component)]
+#[derive(Component, Resource)]
 pub struct NetworkConfig {
     pub server_config: Vec<ChannelConfig>,
     pub client_config: Vec<ChannelConfig>,
@@ -42,22 +72,116 @@ impl Default for NetworkConfig {
                     send_type: SendType::Unreliable,
                 },
             ],
-            client_config: vec![ChannelConfig {
-                channel_id: 0,
-                max_memory_usage_bytes: 1024 * 1024 * 5,
-                send_type: SendType::ReliableOrdered {
-                    resend_time: Duration::from_millis(300),
+            client_config: vec![
+                ChannelConfig {
+                    channel_id: 0,
+                    max_memory_usage_bytes: 1024 * 1024 * 5,
+                    send_type: SendType::ReliableOrdered {
+                        resend_time: Duration::from_millis(300),
+                    },
+                },
+                ChannelConfig {
+                    channel_id: 1,
+                    max_memory_usage_bytes: 1024 * 1024 * 2,
+                    send_type: SendType::Unreliable,
                 },
-            }],
+            ],
             bytes_per_tick: 1024 * 1024 * 7,
         }
     }
 }
 
+impl NetworkConfig {
+    /// Returns the renet connection config matching these channels.
+    pub fn connection_config(&self) -> ConnectionConfig {
+        ConnectionConfig {
+            available_bytes_per_tick: self.bytes_per_tick,
+            server_channels_config: self.server_config.clone(),
+            client_channels_config: self.client_config.clone(),
+        }
+    }
+}
+
+/// State of a player at a given server tick.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct PlayerSnapshot {
+    pub id: ClientId,
+    /// Eye position of the player.
+    pub position: Vec3,
+    pub yaw: f32,
+    pub pitch: f32,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub enum NetworkMessage {
+    // The handshake messages come first and keep their fields so that they are understood by
+    // clients and servers running other protocol versions.
+    /// First message of a client, the server answers with [`NetworkMessage::LoginAccepted`] or
+    /// [`NetworkMessage::Disconnect`].
+    Login { protocol_version: u32 },
+    /// Tells the client why it is about to be disconnected.
+    Disconnect { reason: String },
+    /// Lets the client in, along with what it needs to see the world like the server does.
+    LoginAccepted {
+        protocol_version: u32,
+        seed: u64,
+        materials: Vec<MaterialDefinition>,
+    },
     PlayerConnected { id: ClientId },
     PlayerDisconnected { id: ClientId },
-    ChunkData(Vec<u8>),
-    PlayerPosition(Vec3),
+    /// A chunk encoded with [`crate::encode_chunk`], sent by the server.
+    ChunkData { key: IVec3, data: Vec<u8> },
+    /// Tells the client to drop a chunk it was sent earlier.
+    UnloadChunk { key: IVec3 },
+    /// Latest movement inputs of the client, oldest first.
+    ///
+    /// Inputs travel on the unreliable channel so every message repeats the last few of them,
+    /// the server skips the ones it already applied.
+    PlayerInputs(Vec<PlayerInput>),
+    /// Authoritative state of the player of the client after applying the input `sequence`.
+    PlayerState { sequence: u32, state: BodyState },
+    /// State of every connected player, sent by the server every tick.
+    PlayerSnapshots { tick: u32, players: Vec<PlayerSnapshot> },
+    /// Asks the server to break a block, answered with [`NetworkMessage::EditResult`].
+    BreakBlock { request: u32, pos: IVec3 },
+    /// Asks the server to place a block, answered with [`NetworkMessage::EditResult`].
+    PlaceBlock { request: u32, pos: IVec3, block: Block },
+    /// Tells the client whether one of its edit requests was applied.
+    EditResult { request: u32, accepted: bool },
+    /// Blocks changed since the last tick in the chunks the client has loaded.
+    BlockChanges(Vec<(IVec3, Block)>),
+    /// A line typed by the player, lines starting with a `/` are commands.
+    Chat { text: String },
+    /// A line to show in the chat, `sender` is `None` for the messages of the server itself.
+    ChatMessage { sender: Option<String>, text: String },
+    /// Asks the server how the last word of a command line can be completed, answered with
+    /// [`NetworkMessage::Completions`].
+    CompleteCommand { input: String },
+    /// Words which can complete the last word of `input`.
+    Completions { input: String, suggestions: Vec<String> },
+    /// Changes what the player of the client is allowed to do.
+    GameModeChanged { mode: GameMode },
+    /// Puts a block in the hand of the player of the client.
+    HeldBlock { block: Block },
+    /// Time of the day in the world, sent when the client joins and whenever it is changed.
+    SetTimeOfDay { time: f32 },
+}
+
+impl NetworkMessage {
+    pub fn to_bytes(&self) -> Result<Vec<u8>, GameError> {
+        bincode::serde::encode_to_vec(
+            self,
+            bincode::config::standard().with_limit::<MESSAGE_SIZE_LIMIT>(),
+        )
+        .map_err(|e| GameError::InvalidData(InvalidData::NetworkMessage(e.to_string())))
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, GameError> {
+        bincode::serde::decode_from_slice(
+            bytes,
+            bincode::config::standard().with_limit::<MESSAGE_SIZE_LIMIT>(),
+        )
+        .map(|(message, _)| message)
+        .map_err(|e| GameError::InvalidData(InvalidData::NetworkMessage(e.to_string())))
+    }
 }
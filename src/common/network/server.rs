@@ -1,22 +1,26 @@
 use bevy::prelude::*;
 use std::{net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket}, time::SystemTime};
 use bevy_renet::{
-    netcode::{ClientAuthentication, NetcodeClientTransport, NetcodeServerTransport, ServerAuthentication, ServerConfig},
+    netcode::{ClientAuthentication, ConnectToken, NetcodeClientTransport, NetcodeServerTransport, ServerAuthentication, ServerConfig},
     renet::{ConnectionConfig, RenetClient, RenetServer}
 };
 
-use crate::common::PROTOCOL_ID;
+use crate::common::{generate_client_id, PrivateKey, PROTOCOL_ID};
 
-pub fn create_dedicated_server(port: u16, config: ConnectionConfig) -> (RenetServer, NetcodeServerTransport) {
-    let server_addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), port);
-    let socket = UdpSocket::bind(server_addr).unwrap();
+/// Creates the server side of the connection over `socket`, clients need a connect token for
+/// `public_addr` signed with `private_key` to join when there's one.
+pub fn create_dedicated_server(socket: UdpSocket, public_addr: SocketAddr, max_clients: usize, private_key: Option<PrivateKey>, config: ConnectionConfig) -> (RenetServer, NetcodeServerTransport) {
     let current_time = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap();
+    let authentication = match private_key {
+        Some(private_key) => ServerAuthentication::Secure { private_key },
+        None => ServerAuthentication::Unsecure,
+    };
     let server_config = ServerConfig {
         current_time,
-        max_clients: 64,
+        max_clients,
         protocol_id: PROTOCOL_ID,
-        public_addresses: vec![server_addr],
-        authentication: ServerAuthentication::Unsecure,
+        public_addresses: vec![public_addr],
+        authentication,
     };
 
     let transport = NetcodeServerTransport::new(server_config, socket).unwrap();
@@ -25,16 +29,19 @@ pub fn create_dedicated_server(port: u16, config: ConnectionConfig) -> (RenetSer
     (server, transport)
 }
 
-pub fn create_client_connection(port: u16, config: ConnectionConfig) -> (RenetClient, NetcodeClientTransport) {
-    let server_addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), port);
-    let socket = UdpSocket::bind(server_addr).unwrap();
+/// Creates the client side of the connection, servers in secure mode only accept clients with a
+/// `connect_token`.
+pub fn create_client_connection(server_addr: SocketAddr, connect_token: Option<ConnectToken>, config: ConnectionConfig) -> (RenetClient, NetcodeClientTransport) {
+    let socket = UdpSocket::bind(SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0)).unwrap();
     let current_time = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap();
-    let client_id = current_time.as_millis() as u64;
-    let authentication = ClientAuthentication::Unsecure {
-        client_id,
-        protocol_id: PROTOCOL_ID,
-        server_addr,
-        user_data: None,
+    let authentication = match connect_token {
+        Some(connect_token) => ClientAuthentication::Secure { connect_token },
+        None => ClientAuthentication::Unsecure {
+            client_id: generate_client_id(),
+            protocol_id: PROTOCOL_ID,
+            server_addr,
+            user_data: None,
+        },
     };
 
     let transport = NetcodeClientTransport::new(current_time, authentication, socket).unwrap();
@@ -0,0 +1,64 @@
+use bevy::ecs::resource::Resource;
+use serde::Deserialize;
+use std::net::{Ipv4Addr, SocketAddr};
+
+use crate::{parse_private_key, GameError, PrivateKey, DEFAULT_SERVER_PORT};
+
+/// File the server settings are read from, any extension supported by `config` works.
+pub const SERVER_SETTINGS_FILE: &str = "server";
+
+/// Prefix of the environment variables overriding the server settings, like `SERVER_PORT`.
+const SERVER_SETTINGS_ENV_PREFIX: &str = "SERVER";
+
+/// Settings of the dedicated server.
+#[derive(Resource, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct ServerSettings {
+    pub port: u16,
+    /// Address the clients reach the server at, connect tokens are only accepted when issued for
+    /// it. Defaults to the local machine on `port`.
+    pub public_addr: Option<SocketAddr>,
+    pub max_clients: usize,
+    /// Hexadecimal key shared with the token issuer, when set the server is in secure mode and
+    /// only lets in clients presenting a connect token signed with it.
+    pub private_key: Option<String>,
+    /// Names of the players allowed to run the commands changing the game, only honoured in
+    /// secure mode where usernames come from connect tokens.
+    pub operators: Vec<String>,
+    /// Seed new worlds are generated from, saved worlds keep the seed they were created with.
+    pub seed: Option<u64>,
+}
+
+impl Default for ServerSettings {
+    fn default() -> Self {
+        Self {
+            port: DEFAULT_SERVER_PORT,
+            public_addr: None,
+            max_clients: 64,
+            private_key: None,
+            operators: Vec::new(),
+            seed: None,
+        }
+    }
+}
+
+impl ServerSettings {
+    /// Reads the settings file if there's one, then applies the environment overrides.
+    pub fn load() -> Result<Self, GameError> {
+        config::Config::builder()
+            .add_source(config::File::with_name(SERVER_SETTINGS_FILE).required(false))
+            .add_source(config::Environment::with_prefix(SERVER_SETTINGS_ENV_PREFIX))
+            .build()
+            .and_then(|settings| settings.try_deserialize())
+            .map_err(|e| GameError::Parser(e.to_string()))
+    }
+
+    pub fn public_addr(&self) -> SocketAddr {
+        self.public_addr
+            .unwrap_or_else(|| SocketAddr::from((Ipv4Addr::LOCALHOST, self.port)))
+    }
+
+    pub fn private_key(&self) -> Result<Option<PrivateKey>, GameError> {
+        self.private_key.as_deref().map(parse_private_key).transpose()
+    }
+}
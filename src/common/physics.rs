@@ -0,0 +1,277 @@
+use crate::{
+    Block, BlockMaterialFlags, BlockMaterialRegistry, ChunkMap, ChunkShape, MaterialBlock, Player,
+};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Downward acceleration applied to walking bodies, in blocks per second squared.
+pub const GRAVITY: f32 = 28.0;
+
+/// Maximum falling speed, in blocks per second.
+pub const TERMINAL_VELOCITY: f32 = 60.0;
+
+/// Height of the ledges a walking body climbs without jumping.
+pub const STEP_HEIGHT: f32 = 1.0;
+
+/// Gap kept between a body and the blocks it collides with.
+const SKIN: f32 = 1e-3;
+
+#[derive(Component)]
+pub struct IsOnGround(pub bool);
+
+#[derive(Component, Default)]
+pub struct Velocity(pub Vec3);
+
+/// How a body moves through the world.
+#[derive(Component, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum MovementMode {
+    /// Free flight ignoring gravity and collisions.
+    #[default]
+    Fly,
+    /// Gravity-bound movement colliding against solid blocks.
+    Walk,
+}
+
+impl MovementMode {
+    pub fn toggled(self) -> Self {
+        match self {
+            Self::Fly => Self::Walk,
+            Self::Walk => Self::Fly,
+        }
+    }
+}
+
+/// Axis-aligned box of a body, its origin lies at the center of its feet.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Collider {
+    pub half_width: f32,
+    pub height: f32,
+    /// Height of the camera above the feet of the body.
+    pub eye_height: f32,
+}
+
+impl Default for Collider {
+    fn default() -> Self {
+        Self {
+            half_width: 0.3,
+            height: 1.8,
+            eye_height: 1.62,
+        }
+    }
+}
+
+impl Collider {
+    #[inline]
+    fn bounds(&self, feet: Vec3) -> (Vec3, Vec3) {
+        (
+            feet - Vec3::new(self.half_width, 0., self.half_width),
+            feet + Vec3::new(self.half_width, self.height, self.half_width),
+        )
+    }
+
+    /// Returns whether the box of a body with its feet at `feet` overlaps the block at `pos`.
+    pub fn overlaps_block(&self, feet: Vec3, pos: IVec3) -> bool {
+        let (min, max) = self.bounds(feet);
+        let block = pos.as_vec3();
+
+        min.cmplt(block + Vec3::ONE).all() && max.cmpgt(block).all()
+    }
+}
+
+/// State of a body simulated by [`step_body`].
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct BodyState {
+    /// Position of the feet of the body.
+    pub position: Vec3,
+    pub velocity: Vec3,
+    pub on_ground: bool,
+}
+
+/// Returns true if the block collides with walking bodies.
+pub fn is_block_solid(registry: &BlockMaterialRegistry, block: Block) -> bool {
+    !block.is_empty()
+        && registry
+            .get_by_id(block.as_mat_id())
+            .is_none_or(|material| !material.flags.contains(BlockMaterialFlags::LIQUID))
+}
+
+/// Returns a predicate telling which blocks of the world walking bodies collide with.
+///
+/// Unloaded chunks are considered solid so bodies don't fall through the world while it loads.
+pub fn solid_blocks<'a>(
+    chunks: &'a ChunkMap<Block, ChunkShape>,
+    registry: &'a BlockMaterialRegistry,
+) -> impl Fn(IVec3) -> bool + 'a {
+    move |pos| {
+        chunks
+            .block_at(pos)
+            .is_none_or(|block| is_block_solid(registry, block))
+    }
+}
+
+/// Advances a walking body by `dt` seconds, applying gravity and resolving collisions against the
+/// blocks for which `is_solid` returns true.
+///
+/// This only depends on its inputs so the client and the server can run it and get the same
+/// results.
+pub fn step_body(
+    is_solid: impl Fn(IVec3) -> bool,
+    collider: &Collider,
+    state: &mut BodyState,
+    dt: f32,
+) {
+    state.velocity.y = (state.velocity.y - GRAVITY * dt).max(-TERMINAL_VELOCITY);
+
+    let motion = state.velocity * dt;
+    let mut position = state.position;
+
+    // Vertical movement first so that step-up and ground detection use the settled height
+    let moved_y = sweep_axis(&is_solid, collider, position, 1, motion.y);
+    position.y += moved_y;
+
+    let hit_ground = motion.y < 0. && moved_y > motion.y;
+    if moved_y != motion.y {
+        state.velocity.y = 0.;
+    }
+
+    let horizontal = Vec3::new(motion.x, 0., motion.z);
+    let mut flat = move_horizontal(&is_solid, collider, position, horizontal);
+
+    // Climb one-block ledges when walking into them
+    let blocked = (flat - position).length_squared() + 1e-6 < horizontal.length_squared();
+    if (state.on_ground || hit_ground) && blocked {
+        let raised_by = sweep_axis(&is_solid, collider, position, 1, STEP_HEIGHT);
+        let mut stepped = position + Vec3::Y * raised_by;
+        stepped = move_horizontal(&is_solid, collider, stepped, horizontal);
+        stepped.y += sweep_axis(&is_solid, collider, stepped, 1, -raised_by);
+
+        if (stepped - position).xz().length_squared() > (flat - position).xz().length_squared() {
+            flat = stepped;
+        }
+    }
+
+    if (flat.x - position.x - horizontal.x).abs() > SKIN {
+        state.velocity.x = 0.;
+    }
+    if (flat.z - position.z - horizontal.z).abs() > SKIN {
+        state.velocity.z = 0.;
+    }
+
+    state.position = flat;
+    state.on_ground =
+        hit_ground || sweep_axis(&is_solid, collider, state.position, 1, -2. * SKIN) > -2. * SKIN;
+}
+
+fn move_horizontal(
+    is_solid: &impl Fn(IVec3) -> bool,
+    collider: &Collider,
+    mut position: Vec3,
+    motion: Vec3,
+) -> Vec3 {
+    position.x += sweep_axis(is_solid, collider, position, 0, motion.x);
+    position.z += sweep_axis(is_solid, collider, position, 2, motion.z);
+    position
+}
+
+/// Moves the box of the body along one axis and returns how far it can go before touching a
+/// solid block.
+fn sweep_axis(
+    is_solid: &impl Fn(IVec3) -> bool,
+    collider: &Collider,
+    position: Vec3,
+    axis: usize,
+    delta: f32,
+) -> f32 {
+    if delta == 0. {
+        return 0.;
+    }
+
+    let (min, max) = collider.bounds(position);
+    let (a, b) = match axis {
+        0 => (1, 2),
+        1 => (0, 2),
+        _ => (0, 1),
+    };
+
+    let cross_range = |i: usize| (min[i] + SKIN).floor() as i32..=(max[i] - SKIN).floor() as i32;
+    let slab_blocked = |layer: i32| {
+        cross_range(a).any(|u| {
+            cross_range(b).any(|v| {
+                let mut pos = IVec3::ZERO;
+                pos[axis] = layer;
+                pos[a] = u;
+                pos[b] = v;
+                is_solid(pos)
+            })
+        })
+    };
+
+    if delta > 0. {
+        let first = (max[axis] - SKIN).floor() as i32 + 1;
+        let last = (max[axis] + delta).floor() as i32;
+
+        (first..=last)
+            .find(|layer| slab_blocked(*layer))
+            .map_or(delta, |layer| (layer as f32 - max[axis] - SKIN).clamp(0., delta))
+    } else {
+        let first = (min[axis] + SKIN).floor() as i32 - 1;
+        let last = (min[axis] + delta).floor() as i32;
+
+        (last..=first)
+            .rev()
+            .find(|layer| slab_blocked(*layer))
+            .map_or(delta, |layer| (layer as f32 + 1. - min[axis] + SKIN).clamp(delta, 0.))
+    }
+}
+
+/// Simulates the bodies which aren't players, players are moved by their inputs through
+/// [`crate::step_player`].
+pub fn apply_body_physics(
+    mut bodies: Query<
+        (
+            &mut Transform,
+            &mut Velocity,
+            &mut IsOnGround,
+            &Collider,
+            &MovementMode,
+        ),
+        Without<Player>,
+    >,
+    chunks: Res<ChunkMap<Block, ChunkShape>>,
+    registry: Res<BlockMaterialRegistry>,
+    time: Res<Time>,
+) {
+    let is_solid = solid_blocks(&chunks, &registry);
+
+    for (mut transform, mut velocity, mut on_ground, collider, mode) in bodies.iter_mut() {
+        if *mode != MovementMode::Walk {
+            on_ground.0 = false;
+            continue;
+        }
+
+        let mut state = BodyState {
+            position: transform.translation - Vec3::Y * collider.eye_height,
+            velocity: velocity.0,
+            on_ground: on_ground.0,
+        };
+
+        step_body(&is_solid, collider, &mut state, time.delta_secs());
+
+        transform.translation = state.position + Vec3::Y * collider.eye_height;
+        velocity.0 = state.velocity;
+        on_ground.0 = state.on_ground;
+    }
+}
+
+/// Label for the systems simulating bodies in the fixed timestep.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug, Hash, SystemSet)]
+pub struct PhysicsSet;
+
+pub struct PhysicsPlugin;
+
+impl Plugin for PhysicsPlugin {
+    fn build(&self, app: &mut App) {
+        app.configure_sets(FixedUpdate, PhysicsSet)
+            .add_systems(FixedUpdate, apply_body_physics.in_set(PhysicsSet));
+    }
+}
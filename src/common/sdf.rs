@@ -11,7 +11,7 @@ pub fn sdf_torus(p: Vec3, t: Vec2) -> f32 {
 
 pub fn sdf_capped_cylinder(p: Vec3, h: f32, radius: f32) -> f32 {
     let d = vec2(p.xz().length(), p.y).abs() - vec2(h, radius);
-    d.x.max(d.y).min(0.) - d.max(Vec2::ZERO).length()
+    d.x.max(d.y).min(0.) + d.max(Vec2::ZERO).length()
 }
 
 pub fn sdf_box(p: Vec3, b: Vec3) -> f32 {
@@ -1,67 +1,290 @@
 use ilattice::{extent::Extent, glam::UVec3};
 use ndshape::Shape;
+use std::borrow::Cow;
 
+/// Bit-packed palette storage, every voxel stores an index into a local palette of values using
+/// the smallest amount of bits able to address the whole palette.
+///
+/// Indices never straddle two words so a word holds `64 / bits` of them.
+#[derive(Clone)]
+struct PaletteStorage<V> {
+    palette: Vec<V>,
+    bits: u32,
+    words: Box<[u64]>,
+    len: usize,
+}
+
+impl<V> PaletteStorage<V>
+where
+    V: Copy + Clone + Default + PartialEq,
+{
+    fn new(len: usize, initial_val: V) -> Self {
+        Self {
+            palette: vec![initial_val],
+            bits: 0,
+            words: Box::default(),
+            len,
+        }
+    }
+
+    fn from_dense(data: &[V]) -> Self {
+        let mut palette = Vec::new();
+        let mut last = None;
+        let indices: Vec<u32> = data
+            .iter()
+            .map(|val| match last {
+                Some((last_val, index)) if last_val == *val => index,
+                _ => {
+                    let index = palette
+                        .iter()
+                        .position(|entry| entry == val)
+                        .unwrap_or_else(|| {
+                            palette.push(*val);
+                            palette.len() - 1
+                        }) as u32;
+                    last = Some((*val, index));
+                    index
+                }
+            })
+            .collect();
+
+        let mut storage = Self {
+            bits: bits_for(palette.len()),
+            palette,
+            words: Box::default(),
+            len: data.len(),
+        };
+        storage.words = vec![0; words_for(storage.len, storage.bits)].into_boxed_slice();
+        indices
+            .into_iter()
+            .enumerate()
+            .for_each(|(i, index)| storage.set_index(i, index));
+
+        storage
+    }
+
+    #[inline]
+    fn index(&self, i: usize) -> u32 {
+        if self.bits == 0 {
+            return 0;
+        }
+
+        let per_word = 64 / self.bits as usize;
+        let shift = (i % per_word) as u32 * self.bits;
+        ((self.words[i / per_word] >> shift) & ((1u64 << self.bits) - 1)) as u32
+    }
+
+    #[inline]
+    fn set_index(&mut self, i: usize, index: u32) {
+        if self.bits == 0 {
+            return;
+        }
+
+        let per_word = 64 / self.bits as usize;
+        let shift = (i % per_word) as u32 * self.bits;
+        let mask = ((1u64 << self.bits) - 1) << shift;
+        let word = &mut self.words[i / per_word];
+        *word = (*word & !mask) | ((index as u64) << shift) & mask;
+    }
+
+    #[inline]
+    fn get(&self, i: usize) -> V {
+        self.palette[self.index(i) as usize]
+    }
+
+    fn set(&mut self, i: usize, val: V) {
+        let index = match self.palette.iter().position(|entry| *entry == val) {
+            Some(index) => index,
+            None => {
+                // Drop the values overwritten since rather than widening the indices for them
+                if self.palette.len() > 1 && bits_for(self.palette.len() + 1) != self.bits {
+                    *self = Self::from_dense(&self.to_dense());
+                }
+
+                self.palette.push(val);
+                let bits = bits_for(self.palette.len());
+                if bits != self.bits {
+                    self.repack(bits);
+                }
+                self.palette.len() - 1
+            }
+        };
+
+        self.set_index(i, index as u32);
+    }
+
+    /// Re-encodes the indices with a new amount of bits per voxel.
+    fn repack(&mut self, bits: u32) {
+        let mut repacked = Self {
+            palette: Vec::new(),
+            bits,
+            words: vec![0; words_for(self.len, bits)].into_boxed_slice(),
+            len: self.len,
+        };
+
+        (0..self.len).for_each(|i| repacked.set_index(i, self.index(i)));
+
+        self.bits = bits;
+        self.words = repacked.words;
+    }
+
+    fn to_dense(&self) -> Box<[V]> {
+        (0..self.len).map(|i| self.get(i)).collect()
+    }
+
+    /// Size in bytes of the packed representation, palette included.
+    fn packed_size(&self) -> usize {
+        self.words.len() * size_of::<u64>() + self.palette.len() * size_of::<V>()
+    }
+}
+
+#[inline]
+fn bits_for(palette_len: usize) -> u32 {
+    usize::BITS - palette_len.saturating_sub(1).leading_zeros()
+}
+
+#[inline]
+fn words_for(len: usize, bits: u32) -> usize {
+    if bits == 0 {
+        0
+    } else {
+        len.div_ceil(64 / bits as usize)
+    }
+}
+
+#[derive(Clone)]
+enum BlockStorage<V> {
+    Dense(Box<[V]>),
+    Palette(PaletteStorage<V>),
+}
+
+/// A buffer of blocks laid out according to a [`Shape`].
+///
+/// Buffers start out palette-compressed, mutable slice or reference access unpacks them into a
+/// dense array, [`BlockBuffer::compact`] packs them back once done writing.
 #[derive(Clone)]
 pub struct BlockBuffer<V, S:Shape<3, Coord = u32>>
 where
-    V: Copy + Clone + Default,
+    V: Copy + Clone + Default + PartialEq,
 {
-    data: Box<[V]>,
+    data: BlockStorage<V>,
     shape: S,
 }
 
 impl<V, S: Shape<3, Coord = u32>> BlockBuffer<V, S>
 where
-    V: Copy + Clone + Default,
+    V: Copy + Clone + Default + PartialEq,
 {
     #[inline]
     pub fn new(shape: S, initial_val: V) -> Self {
         Self {
-            data: vec![initial_val; shape.size() as usize].into_boxed_slice(),
+            data: BlockStorage::Palette(PaletteStorage::new(shape.size() as usize, initial_val)),
             shape,
         }
     }
 
     #[inline]
     pub fn new_empty(shape: S) -> Self {
-        Self {
-            data: vec![Default::default(); shape.size() as usize].into_boxed_slice(),
-            shape,
-        }
+        Self::new(shape, Default::default())
     }
 
     /// Returns the block at the queried position in local space.
     #[inline]
     pub fn block_at(&self, pos: UVec3) -> V {
-        self.data[self.shape.linearize(pos.to_array()) as usize]
+        let index = self.shape.linearize(pos.to_array()) as usize;
+
+        match &self.data {
+            BlockStorage::Dense(data) => data[index],
+            BlockStorage::Palette(palette) => palette.get(index),
+        }
     }
 
     /// Returns a mutable reference to the block at the queried position in local space.
+    ///
+    /// This unpacks palette-compressed buffers until the next [`BlockBuffer::compact`], prefer
+    /// [`BlockBuffer::set_block`] for sparse edits.
     #[inline]
     pub fn block_at_mut(&mut self, pos: UVec3) -> &mut V {
-        &mut self.data[self.shape.linearize(pos.to_array()) as usize]
+        let index = self.shape.linearize(pos.to_array()) as usize;
+        &mut self.dense_mut()[index]
     }
 
-    /// Fill an extent of this buffer with a specified value. 
+    /// Sets the block at the queried position in local space without unpacking the buffer, the
+    /// palette only keeps the blocks still in use when it has to grow.
+    #[inline]
+    pub fn set_block(&mut self, pos: UVec3, val: V) {
+        let index = self.shape.linearize(pos.to_array()) as usize;
+
+        match &mut self.data {
+            BlockStorage::Dense(data) => data[index] = val,
+            BlockStorage::Palette(palette) => palette.set(index, val),
+        }
+    }
+
+    /// Fill an extent of this buffer with a specified value.
     #[inline]
     pub fn fill_extent(&mut self, extent: Extent<UVec3>, val: V) {
-        ndcopy::fill3(
-            extent.shape.to_array(),
-            val,
-            &mut self.data,
-            &self.shape,
-            extent.minimum.to_array(),
-        );
+        match &mut self.data {
+            BlockStorage::Dense(data) => ndcopy::fill3(
+                extent.shape.to_array(),
+                val,
+                data,
+                &self.shape,
+                extent.minimum.to_array(),
+            ),
+            BlockStorage::Palette(palette) => extent.iter3().for_each(|pos| {
+                palette.set(self.shape.linearize(pos.to_array()) as usize, val)
+            }),
+        }
+    }
+
+    /// Packs the buffer into a palette if that takes less memory than the dense representation,
+    /// dropping any unused palette entries.
+    pub fn compact(&mut self) {
+        let packed = match &self.data {
+            BlockStorage::Dense(data) => PaletteStorage::from_dense(data),
+            BlockStorage::Palette(palette) => PaletteStorage::from_dense(&palette.to_dense()),
+        };
+
+        self.data = if packed.packed_size() < packed.len * size_of::<V>() {
+            BlockStorage::Palette(packed)
+        } else {
+            BlockStorage::Dense(packed.to_dense())
+        };
+    }
+
+    /// Returns true if any block of the buffer may match the predicate.
+    ///
+    /// Only the palette is checked for compact buffers, so this can report blocks that were
+    /// overwritten since the last [`BlockBuffer::compact`].
+    pub fn any(&self, mut f: impl FnMut(V) -> bool) -> bool {
+        match &self.data {
+            BlockStorage::Dense(data) => data.iter().any(|val| f(*val)),
+            BlockStorage::Palette(palette) => palette.palette.iter().any(|val| f(*val)),
+        }
+    }
+
+    /// Returns true if the buffer is currently palette-compressed.
+    #[inline]
+    pub const fn is_compact(&self) -> bool {
+        matches!(self.data, BlockStorage::Palette(_))
     }
 
+    /// Returns the blocks of this buffer in linear order, unpacking a copy if the buffer is
+    /// palette-compressed.
     #[inline]
-    pub const fn slice(&self) -> &[V] {
-        &self.data
+    pub fn slice(&self) -> Cow<'_, [V]> {
+        match &self.data {
+            BlockStorage::Dense(data) => Cow::Borrowed(data),
+            BlockStorage::Palette(palette) => Cow::Owned(palette.to_dense().into_vec()),
+        }
     }
 
+    /// Returns the blocks of this buffer in linear order for writing, which unpacks it until the
+    /// next [`BlockBuffer::compact`].
     #[inline]
-    pub const fn slice_mut(&mut self) -> &mut [V] {
-        &mut self.data
+    pub fn slice_mut(&mut self) -> &mut [V] {
+        self.dense_mut()
     }
 
     #[inline]
@@ -72,5 +295,15 @@ where
     pub const fn shape_mut(&mut self) -> &mut S {
         &mut self.shape
     }
-}
 
+    fn dense_mut(&mut self) -> &mut [V] {
+        if let BlockStorage::Palette(palette) = &self.data {
+            self.data = BlockStorage::Dense(palette.to_dense());
+        }
+
+        match &mut self.data {
+            BlockStorage::Dense(data) => data,
+            BlockStorage::Palette(_) => unreachable!(),
+        }
+    }
+}
@@ -1,5 +1,4 @@
 use super::buffer::BlockBuffer;
-use crate::CHUNK_SIZE;
 use bevy::{math::IVec3, prelude::Resource};
 use ilattice::{morton::Morton3i32, vector::Map as VecMap};
 use ndshape::Shape;
@@ -32,23 +31,35 @@ where
     }
 
     pub fn block_at(&self, pos: IVec3) -> Option<V> {
-        let chunk_minimum = pos & self.shape_mask;
-        let local_minimum = ilattice::glam::IVec3::from(pos.to_array())
-            .map(|x| x.rem_euclid(CHUNK_SIZE as i32))
-            .as_uvec3();
+        let chunk_minimum = self.chunk_key(pos);
+        let local_minimum = Self::local_pos(pos, chunk_minimum);
 
         self.buffer_at(chunk_minimum)
             .map(|buffer| buffer.block_at(local_minimum))
     }
 
-    pub fn block_at_mut(&mut self, pos: IVec3) -> Option<&mut V> {
-        let chunk_minimum = pos & self.shape_mask;
-        let local_minimum = ilattice::glam::IVec3::from(pos.to_array())
-            .map(|x| x.rem_euclid(CHUNK_SIZE as i32))
-            .as_uvec3();
+    /// Sets the block at the specified world position and returns the previous one, or `None` if
+    /// the chunk holding it isn't loaded.
+    pub fn set_block(&mut self, pos: IVec3, val: V) -> Option<V> {
+        let chunk_minimum = self.chunk_key(pos);
+        let local_minimum = Self::local_pos(pos, chunk_minimum);
 
-        self.buffer_at_mut(chunk_minimum)
-            .map(|buffer| buffer.block_at_mut(local_minimum))
+        self.buffer_at_mut(chunk_minimum).map(|buffer| {
+            let previous = buffer.block_at(local_minimum);
+            buffer.set_block(local_minimum, val);
+            previous
+        })
+    }
+
+    /// Returns the minimum of the chunk containing the specified world position.
+    #[inline]
+    pub fn chunk_key(&self, pos: IVec3) -> IVec3 {
+        pos & self.shape_mask
+    }
+
+    #[inline]
+    fn local_pos(pos: IVec3, chunk_minimum: IVec3) -> ilattice::glam::UVec3 {
+        ilattice::glam::UVec3::from((pos - chunk_minimum).as_uvec3().to_array())
     }
 
     /// Check whether there's a buffer at the specified minimum.
@@ -105,8 +116,108 @@ where
         self.chunks.remove(&pos.into())
     }
 
+    /// Returns an iterator over the minimums and buffers of every chunk in the map.
+    pub fn iter(&self) -> impl Iterator<Item = (IVec3, &BlockBuffer<V, S>)> {
+        self.chunks.iter().map(|(key, buffer)| {
+            (
+                IVec3::from(ilattice::glam::IVec3::from(*key).to_array()),
+                buffer,
+            )
+        })
+    }
+
+    /// Returns the blocks of the neighbours of the chunk at the specified minimum that touch its
+    /// faces, edges and corners.
+    pub fn neighbour_borders(&self, minimum: IVec3) -> NeighbourBorders<V> {
+        let dims = self.chunk_dims();
+
+        let regions = (0..27)
+            .map(|index| {
+                let offset = region_offset(index);
+                if offset == IVec3::ZERO {
+                    return None;
+                }
+
+                let buffer = self.buffer_at(minimum + offset * dims)?;
+                let size = border_size(offset, dims);
+                let start =
+                    IVec3::select(offset.cmplt(IVec3::ZERO), dims - IVec3::ONE, IVec3::ZERO);
+
+                let mut region = Vec::with_capacity(size.element_product() as usize);
+                for z in 0..size.z {
+                    for y in 0..size.y {
+                        for x in 0..size.x {
+                            let pos = start + IVec3::new(x, y, z);
+                            region.push(buffer.block_at(Self::local_pos(pos, IVec3::ZERO)));
+                        }
+                    }
+                }
+
+                Some(region)
+            })
+            .collect();
+
+        NeighbourBorders { dims, regions }
+    }
+
     #[inline]
     pub const fn shape_mask(&self) -> IVec3 {
         self.shape_mask
     }
+
+    /// Returns the size of the chunks along every axis.
+    #[inline]
+    pub fn chunk_dims(&self) -> IVec3 {
+        IVec3::from(self.shape.as_array().map(|x| x as i32))
+    }
+}
+
+/// Blocks of the neighbours of a chunk touching it, as returned by
+/// [`ChunkMap::neighbour_borders`].
+pub struct NeighbourBorders<V> {
+    dims: IVec3,
+    /// Blocks of each of the 26 neighbours touching the chunk, `None` for the chunk itself and
+    /// the neighbours which aren't loaded. Neighbours are ordered by their offset with X varying
+    /// fastest, and so are their blocks.
+    regions: Vec<Option<Vec<V>>>,
+}
+
+impl<V: Copy> NeighbourBorders<V> {
+    /// Returns the block at the specified position relative to the chunk minimum, or `None` if
+    /// the position is inside the chunk or more than a block away from it, or if its chunk isn't
+    /// loaded.
+    pub fn block_at(&self, pos: IVec3) -> Option<V> {
+        if pos.cmplt(IVec3::NEG_ONE).any() || pos.cmpgt(self.dims).any() {
+            return None;
+        }
+
+        let offset = IVec3::select(
+            pos.cmplt(IVec3::ZERO),
+            IVec3::NEG_ONE,
+            IVec3::select(pos.cmpge(self.dims), IVec3::ONE, IVec3::ZERO),
+        );
+        let index = offset + IVec3::ONE;
+        let region = self.regions[(index.x + index.y * 3 + index.z * 9) as usize].as_ref()?;
+
+        let size = border_size(offset, self.dims);
+        let local = IVec3::select(offset.cmpeq(IVec3::ZERO), pos, IVec3::ZERO);
+        Some(region[(local.x + (local.y + local.z * size.y) * size.x) as usize])
+    }
+}
+
+/// Returns the offsets of the 26 neighbours touching a chunk by a face, an edge or a corner, in
+/// the order of the regions of [`NeighbourBorders`].
+pub fn neighbour_offsets() -> impl Iterator<Item = IVec3> {
+    (0..27).map(region_offset).filter(|offset| *offset != IVec3::ZERO)
+}
+
+/// Returns the offset of the region at `index` among the 27 around and including a chunk.
+fn region_offset(index: i32) -> IVec3 {
+    IVec3::new(index % 3, index / 3 % 3, index / 9) - IVec3::ONE
+}
+
+/// Returns the size of the blocks touching a chunk in its neighbour at `offset`, a layer for
+/// faces, a line for edges and a single block for corners.
+fn border_size(offset: IVec3, dims: IVec3) -> IVec3 {
+    IVec3::select(offset.cmpeq(IVec3::ZERO), dims, IVec3::ONE)
 }
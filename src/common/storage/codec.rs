@@ -0,0 +1,223 @@
+use super::buffer::BlockBuffer;
+use crate::{Block, BlockMaterialRegistry, GameError, InvalidData, MaterialBlock};
+use bevy::platform::collections::HashMap;
+use bincode::{
+    config::{Configuration, Limit, LittleEndian, Varint},
+    Decode, Encode,
+};
+use ndshape::Shape;
+
+/// Current version of the chunk binary format, bump it whenever the layout changes.
+pub const CHUNK_CODEC_VERSION: u16 = 1;
+
+const CHUNK_CODEC_MAGIC: [u8; 4] = *b"RCCK";
+
+/// Upper bound on the size of a decoded chunk, guards against corrupt length prefixes.
+const CHUNK_CODEC_LIMIT: usize = 32 * 1024 * 1024;
+
+type CodecConfig = Configuration<LittleEndian, Varint, Limit<CHUNK_CODEC_LIMIT>>;
+
+const fn codec_config() -> CodecConfig {
+    bincode::config::standard().with_limit::<CHUNK_CODEC_LIMIT>()
+}
+
+/// Mapping between numeric material ids and the id strings of the [`BlockMaterialRegistry`].
+///
+/// Encoded chunks reference materials by id string so they stay valid when numeric ids change.
+#[derive(Clone, Default)]
+pub struct MaterialIdTable {
+    names: Vec<Option<String>>,
+    ids: HashMap<String, u64>,
+}
+
+impl MaterialIdTable {
+    /// Returns the id string of the material with the specified numeric id.
+    pub fn name(&self, id: u64) -> Option<&str> {
+        self.names.get(id as usize).and_then(|name| name.as_deref())
+    }
+
+    /// Returns the numeric id of the material with the specified id string.
+    pub fn id(&self, name: &str) -> Option<u64> {
+        self.ids.get(name).copied()
+    }
+}
+
+impl From<&BlockMaterialRegistry> for MaterialIdTable {
+    fn from(registry: &BlockMaterialRegistry) -> Self {
+        let mut table = Self::default();
+
+        for (id, material) in registry.iter_materials().into_iter().enumerate() {
+            if material.id.is_empty() {
+                table.names.push(None);
+                continue;
+            }
+
+            table.names.push(Some(material.id.to_string()));
+            table.ids.insert(material.id.to_string(), id as u64);
+        }
+
+        table
+    }
+}
+
+#[derive(Encode, Decode)]
+struct ChunkHeader {
+    magic: [u8; 4],
+    version: u16,
+    shape: [u32; 3],
+}
+
+#[derive(Encode, Decode)]
+struct PaletteEntry {
+    material: String,
+    transparent: bool,
+}
+
+#[derive(Encode, Decode)]
+enum ChunkBody {
+    /// Runs of `(length, palette index)` over the voxels in linear order.
+    RunLength(Vec<(u32, u32)>),
+}
+
+#[derive(Encode, Decode)]
+struct ChunkPayload {
+    palette: Vec<PaletteEntry>,
+    body: ChunkBody,
+}
+
+/// Encodes a chunk buffer into the versioned chunk binary format.
+///
+/// The payload is made of a header holding the format version and the buffer shape, followed by a
+/// palette of material id strings and a run-length encoded body indexing into it.
+pub fn encode_chunk<S: Shape<3, Coord = u32>>(
+    buffer: &BlockBuffer<Block, S>,
+    materials: &MaterialIdTable,
+) -> Result<Vec<u8>, GameError> {
+    let header = ChunkHeader {
+        magic: CHUNK_CODEC_MAGIC,
+        version: CHUNK_CODEC_VERSION,
+        shape: buffer.shape().as_array(),
+    };
+
+    let mut palette_blocks: Vec<Block> = Vec::new();
+    let mut palette = Vec::new();
+    let mut runs: Vec<(u32, u32)> = Vec::new();
+
+    for block in buffer.slice().iter() {
+        let index = match palette_blocks.iter().position(|entry| entry == block) {
+            Some(index) => index,
+            None => {
+                let material = materials.name(block.as_mat_id()).ok_or_else(|| {
+                    GameError::NotFound(format!("material with id {}", block.as_mat_id()))
+                })?;
+
+                palette_blocks.push(*block);
+                palette.push(PaletteEntry {
+                    material: material.to_string(),
+                    transparent: block.is_transparent(),
+                });
+                palette_blocks.len() - 1
+            }
+        } as u32;
+
+        match runs.last_mut() {
+            Some((len, last_index)) if *last_index == index => *len += 1,
+            _ => runs.push((1, index)),
+        }
+    }
+
+    let mut data = bincode::encode_to_vec(header, codec_config()).map_err(codec_error)?;
+    bincode::encode_into_std_write(
+        ChunkPayload {
+            palette,
+            body: ChunkBody::RunLength(runs),
+        },
+        &mut data,
+        codec_config(),
+    )
+    .map_err(codec_error)?;
+
+    Ok(data)
+}
+
+/// Decodes a chunk buffer of the specified shape from the versioned chunk binary format.
+pub fn decode_chunk<S: Shape<3, Coord = u32>>(
+    data: &[u8],
+    shape: S,
+    materials: &MaterialIdTable,
+) -> Result<BlockBuffer<Block, S>, GameError> {
+    let (header, header_len): (ChunkHeader, usize) =
+        bincode::decode_from_slice(data, codec_config()).map_err(codec_error)?;
+
+    if header.magic != CHUNK_CODEC_MAGIC {
+        return Err(chunk_error("bad magic".to_string()));
+    }
+
+    if header.version != CHUNK_CODEC_VERSION {
+        return Err(chunk_error(format!(
+            "unsupported format version {} (expected {})",
+            header.version, CHUNK_CODEC_VERSION
+        )));
+    }
+
+    if header.shape != shape.as_array() {
+        return Err(chunk_error(format!(
+            "shape {:?} does not match {:?}",
+            header.shape,
+            shape.as_array()
+        )));
+    }
+
+    let (payload, _): (ChunkPayload, usize) =
+        bincode::decode_from_slice(&data[header_len..], codec_config()).map_err(codec_error)?;
+
+    let palette = payload
+        .palette
+        .iter()
+        .map(|entry| {
+            let id = materials.id(&entry.material).ok_or_else(|| {
+                GameError::InvalidData(InvalidData::BlockIdentifier(entry.material.clone()))
+            })?;
+
+            Ok(match entry.transparent && id != 0 {
+                true => Block(id | Block::TRANSPARENT_FLAG),
+                false => Block(id),
+            })
+        })
+        .collect::<Result<Vec<_>, GameError>>()?;
+
+    let mut buffer = BlockBuffer::new_empty(shape);
+    let voxels = buffer.slice_mut();
+    let volume = voxels.len();
+    let mut cursor = 0usize;
+
+    let ChunkBody::RunLength(runs) = payload.body;
+    for (len, index) in runs {
+        let block = *palette.get(index as usize).ok_or_else(|| {
+            chunk_error(format!("palette index {} out of bounds", index))
+        })?;
+
+        let len = len as usize;
+        if cursor + len > volume {
+            return Err(chunk_error("runs overflow the chunk shape".to_string()));
+        }
+
+        voxels[cursor..cursor + len].fill(block);
+        cursor += len;
+    }
+
+    if cursor != volume {
+        return Err(chunk_error("runs do not cover the chunk shape".to_string()));
+    }
+
+    buffer.compact();
+    Ok(buffer)
+}
+
+fn codec_error(err: impl std::fmt::Display) -> GameError {
+    chunk_error(err.to_string())
+}
+
+fn chunk_error(reason: String) -> GameError {
+    GameError::InvalidData(InvalidData::ChunkData(reason))
+}
@@ -1,5 +1,14 @@
 pub mod buffer;
 pub use buffer::*;
 
+pub mod codec;
+pub use codec::*;
+
 pub mod chunk_map;
 pub use chunk_map::*;
+
+pub mod raycast;
+pub use raycast::*;
+
+pub mod region;
+pub use region::*;
@@ -0,0 +1,104 @@
+use super::chunk_map::ChunkMap;
+use bevy::math::{IVec3, Vec3};
+use ndshape::Shape;
+use std::hash::Hash;
+
+/// Result of a successful raycast against a [`ChunkMap`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RaycastHit<V> {
+    /// World position of the block that was hit.
+    pub pos: IVec3,
+    /// Normal of the face the ray entered the block through, zero if the ray started inside of it.
+    pub normal: IVec3,
+    /// Distance travelled along the ray before hitting the block.
+    pub distance: f32,
+    /// The block that was hit.
+    pub block: V,
+}
+
+impl<V> RaycastHit<V> {
+    /// Returns the position of the block adjacent to the hit face, where a new block would be
+    /// placed.
+    #[inline]
+    pub fn adjacent_pos(&self) -> IVec3 {
+        self.pos + self.normal
+    }
+}
+
+impl<V, S> ChunkMap<V, S>
+where
+    V: Clone + Copy + Default + PartialEq + Eq + Hash,
+    S: Shape<3, Coord = u32> + Clone,
+{
+    /// Casts a ray through the map and returns the first non-empty block it hits within
+    /// `max_dist`.
+    pub fn raycast(&self, origin: Vec3, dir: Vec3, max_dist: f32) -> Option<RaycastHit<V>> {
+        self.raycast_filtered(origin, dir, max_dist, |block| block != V::default())
+    }
+
+    /// Casts a ray through the map and returns the first block accepted by `filter` it hits
+    /// within `max_dist`, unloaded chunks are treated as empty.
+    ///
+    /// This walks the voxel grid using the Amanatides & Woo DDA algorithm.
+    pub fn raycast_filtered(
+        &self,
+        origin: Vec3,
+        dir: Vec3,
+        max_dist: f32,
+        mut filter: impl FnMut(V) -> bool,
+    ) -> Option<RaycastHit<V>> {
+        let dir = dir.normalize_or_zero();
+        if dir == Vec3::ZERO {
+            return None;
+        }
+
+        let mut pos = origin.floor().as_ivec3();
+        let step = IVec3::from(dir.to_array().map(|d| {
+            if d > 0. {
+                1
+            } else if d < 0. {
+                -1
+            } else {
+                0
+            }
+        }));
+        let t_delta = Vec3::from(dir.to_array().map(|d| (1. / d).abs()));
+        let mut t_max = Vec3::from(std::array::from_fn::<f32, 3, _>(|axis| match step[axis] {
+            1 => (pos[axis] as f32 + 1. - origin[axis]) / dir[axis],
+            -1 => (origin[axis] - pos[axis] as f32) / -dir[axis],
+            _ => f32::INFINITY,
+        }));
+
+        let mut distance = 0.;
+        let mut normal = IVec3::ZERO;
+
+        loop {
+            if let Some(block) = self.block_at(pos).filter(|block| filter(*block)) {
+                return Some(RaycastHit {
+                    pos,
+                    normal,
+                    distance,
+                    block,
+                });
+            }
+
+            let axis = if t_max.x < t_max.y {
+                if t_max.x < t_max.z { 0 } else { 2 }
+            } else if t_max.y < t_max.z {
+                1
+            } else {
+                2
+            };
+
+            if t_max[axis] > max_dist {
+                return None;
+            }
+
+            distance = t_max[axis];
+            pos[axis] += step[axis];
+            t_max[axis] += t_delta[axis];
+            normal = IVec3::ZERO;
+            normal[axis] = -step[axis];
+        }
+    }
+}
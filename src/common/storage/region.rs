@@ -0,0 +1,262 @@
+use super::{
+    buffer::BlockBuffer,
+    codec::{decode_chunk, encode_chunk, MaterialIdTable},
+};
+use crate::{Block, ChunkShape, GameError, InvalidData, CHUNK_HEIGHT, CHUNK_SIZE};
+use bevy::{math::IVec3, platform::collections::HashMap, prelude::Resource};
+use std::{
+    fs::{File, OpenOptions},
+    io::{Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, RwLock},
+};
+
+/// Default directory where the world regions are saved.
+pub const DEFAULT_WORLD_DIR: &str = "saves/world";
+
+/// File next to the regions holding the seed the world was generated from.
+const SEED_FILE: &str = "seed";
+
+/// Number of chunks stored along the X and Z axes of a single region file.
+pub const REGION_SIZE: i32 = 16;
+
+const REGION_MAGIC: [u8; 4] = *b"RCRG";
+const REGION_VERSION: u32 = 2;
+const REGION_CHUNK_COUNT: usize = (REGION_SIZE * REGION_SIZE) as usize;
+const REGION_ENTRY_LEN: u64 = 8;
+const REGION_HEADER_LEN: u64 = 8 + REGION_CHUNK_COUNT as u64 * REGION_ENTRY_LEN;
+
+/// Location of a chunk payload inside of a region file, an offset of 0 means the chunk has never
+/// been saved.
+#[derive(Clone, Copy, Default)]
+struct RegionEntry {
+    offset: u32,
+    length: u32,
+}
+
+/// A region file groups `REGION_SIZE * REGION_SIZE` chunks sharing the same vertical position.
+///
+/// The file starts with a magic and a version followed by an offset table, chunk payloads are
+/// appended after it and rewritten in place when the new payload fits in the old one.
+struct RegionFile {
+    file: File,
+    entries: Box<[RegionEntry]>,
+}
+
+impl RegionFile {
+    fn open(path: &Path) -> Result<Self, GameError> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+        let mut entries = vec![RegionEntry::default(); REGION_CHUNK_COUNT].into_boxed_slice();
+
+        if file.metadata()?.len() == 0 {
+            let mut header = Vec::with_capacity(REGION_HEADER_LEN as usize);
+            header.extend_from_slice(&REGION_MAGIC);
+            header.extend_from_slice(&REGION_VERSION.to_le_bytes());
+            header.resize(REGION_HEADER_LEN as usize, 0);
+            file.write_all(&header)?;
+
+            return Ok(Self { file, entries });
+        }
+
+        let mut header = vec![0u8; REGION_HEADER_LEN as usize];
+        file.read_exact(&mut header)
+            .map_err(|_| region_error(path, "truncated header"))?;
+
+        if header[0..4] != REGION_MAGIC {
+            return Err(region_error(path, "bad magic"));
+        }
+
+        let version = read_u32(&header, 4);
+        if version != REGION_VERSION {
+            return Err(region_error(path, &format!("unsupported version {}", version)));
+        }
+
+        for (index, entry) in entries.iter_mut().enumerate() {
+            let base = 8 + index * REGION_ENTRY_LEN as usize;
+            entry.offset = read_u32(&header, base);
+            entry.length = read_u32(&header, base + 4);
+        }
+
+        Ok(Self { file, entries })
+    }
+
+    fn read(&mut self, index: usize) -> Result<Option<Vec<u8>>, GameError> {
+        let entry = self.entries[index];
+        if entry.offset == 0 {
+            return Ok(None);
+        }
+
+        let mut data = vec![0u8; entry.length as usize];
+        self.file.seek(SeekFrom::Start(entry.offset as u64))?;
+        self.file.read_exact(&mut data)?;
+
+        Ok(Some(data))
+    }
+
+    fn write(&mut self, index: usize, data: &[u8]) -> Result<(), GameError> {
+        let entry = self.entries[index];
+        let offset = if entry.offset != 0 && data.len() <= entry.length as usize {
+            entry.offset as u64
+        } else {
+            self.file.seek(SeekFrom::End(0))?
+        };
+
+        let offset = u32::try_from(offset).map_err(|_| {
+            GameError::Unsupported("region file exceeds 4 GiB".to_string())
+        })?;
+        let length = data.len() as u32;
+
+        self.file.seek(SeekFrom::Start(offset as u64))?;
+        self.file.write_all(data)?;
+
+        let mut raw_entry = [0u8; REGION_ENTRY_LEN as usize];
+        raw_entry[0..4].copy_from_slice(&offset.to_le_bytes());
+        raw_entry[4..8].copy_from_slice(&length.to_le_bytes());
+        self.file
+            .seek(SeekFrom::Start(8 + index as u64 * REGION_ENTRY_LEN))?;
+        self.file.write_all(&raw_entry)?;
+
+        self.entries[index] = RegionEntry { offset, length };
+        Ok(())
+    }
+}
+
+/// On-disk storage of chunk buffers split into region files.
+///
+/// This is cheap to clone and can be moved into async tasks.
+#[derive(Resource, Clone)]
+pub struct RegionStorage {
+    root: Arc<PathBuf>,
+    regions: Arc<Mutex<HashMap<IVec3, RegionFile>>>,
+    materials: Arc<RwLock<MaterialIdTable>>,
+}
+
+impl RegionStorage {
+    /// Opens the region storage at the specified directory, creating it if needed.
+    pub fn open(root: impl Into<PathBuf>) -> Result<Self, GameError> {
+        let root = root.into();
+        std::fs::create_dir_all(&root)?;
+
+        Ok(Self {
+            root: Arc::new(root),
+            regions: Default::default(),
+            materials: Default::default(),
+        })
+    }
+
+    /// Replaces the material table used to encode and decode the saved chunks.
+    pub fn set_materials(&self, materials: MaterialIdTable) {
+        *self.materials.write().unwrap() = materials;
+    }
+
+    /// Returns the saved buffer of the chunk at the specified minimum if there's one.
+    pub fn load_chunk(
+        &self,
+        key: IVec3,
+    ) -> Result<Option<BlockBuffer<Block, ChunkShape>>, GameError> {
+        let (region_key, index) = region_location(key);
+        let mut regions = self.regions.lock().unwrap();
+
+        if !regions.contains_key(&region_key) {
+            let path = self.region_path(region_key);
+            if !path.exists() {
+                return Ok(None);
+            }
+            regions.insert(region_key, RegionFile::open(&path)?);
+        }
+
+        regions
+            .get_mut(&region_key)
+            .unwrap()
+            .read(index)?
+            .map(|data| decode_chunk(&data, ChunkShape {}, &self.materials.read().unwrap()))
+            .transpose()
+    }
+
+    /// Writes the buffer of the chunk at the specified minimum to its region file.
+    pub fn save_chunk(
+        &self,
+        key: IVec3,
+        buffer: &BlockBuffer<Block, ChunkShape>,
+    ) -> Result<(), GameError> {
+        let (region_key, index) = region_location(key);
+        let data = encode_chunk(buffer, &self.materials.read().unwrap())?;
+        let mut regions = self.regions.lock().unwrap();
+
+        if !regions.contains_key(&region_key) {
+            let region = RegionFile::open(&self.region_path(region_key))?;
+            regions.insert(region_key, region);
+        }
+
+        regions
+            .get_mut(&region_key)
+            .unwrap()
+            .write(index, &data)
+    }
+
+    /// Returns the seed the world was generated from, if it was saved.
+    pub fn load_seed(&self) -> Result<Option<u64>, GameError> {
+        let path = self.root.join(SEED_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        std::fs::read_to_string(path)?
+            .trim()
+            .parse()
+            .map(Some)
+            .map_err(|e| GameError::InvalidData(InvalidData::Region(format!("invalid seed: {e}"))))
+    }
+
+    pub fn save_seed(&self, seed: u64) -> Result<(), GameError> {
+        std::fs::write(self.root.join(SEED_FILE), seed.to_string())?;
+        Ok(())
+    }
+
+    /// Flushes every open region file to disk.
+    pub fn flush(&self) -> Result<(), GameError> {
+        for region in self.regions.lock().unwrap().values_mut() {
+            region.file.sync_data()?;
+        }
+        Ok(())
+    }
+
+    fn region_path(&self, region_key: IVec3) -> PathBuf {
+        self.root.join(format!(
+            "r.{}.{}.{}.region",
+            region_key.x, region_key.y, region_key.z
+        ))
+    }
+}
+
+/// Returns the region key and the index in the region offset table of the chunk at the specified
+/// minimum.
+pub fn region_location(key: IVec3) -> (IVec3, usize) {
+    let chunk = IVec3::new(
+        key.x.div_euclid(CHUNK_SIZE as i32),
+        key.y.div_euclid(CHUNK_HEIGHT as i32),
+        key.z.div_euclid(CHUNK_SIZE as i32),
+    );
+    let region_key = IVec3::new(
+        chunk.x.div_euclid(REGION_SIZE),
+        chunk.y,
+        chunk.z.div_euclid(REGION_SIZE),
+    );
+    let index = chunk.z.rem_euclid(REGION_SIZE) * REGION_SIZE + chunk.x.rem_euclid(REGION_SIZE);
+
+    (region_key, index as usize)
+}
+
+#[inline]
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+fn region_error(path: &Path, reason: &str) -> GameError {
+    GameError::InvalidData(InvalidData::Region(format!("{}: {}", path.display(), reason)))
+}
@@ -1,6 +1,7 @@
 use block_mesh::{MergeVoxel, Voxel};
+use serde::{Deserialize, Serialize};
 
-#[derive(Copy, Clone, Hash, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Hash, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Block(pub u64);
 
 impl Block {
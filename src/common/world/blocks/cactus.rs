@@ -0,0 +1,14 @@
+use bevy::color::Color;
+
+use crate::{BlockMaterial, BlockMaterialFlags};
+
+pub struct Cactus;
+
+impl BlockMaterial for Cactus {
+    const ID: u64 = 13;
+
+    fn block_name() -> &'static str { "cactus" }
+    fn base_color() -> bevy::color::Color { Color::srgb_u8(88, 140, 62) }
+    fn flags() -> crate::BlockMaterialFlags { BlockMaterialFlags::SOLID }
+    fn perceptual_roughness() -> f32 { 0.8 }
+}
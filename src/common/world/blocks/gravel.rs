@@ -0,0 +1,14 @@
+use bevy::color::Color;
+
+use crate::{BlockMaterial, BlockMaterialFlags};
+
+pub struct Gravel;
+
+impl BlockMaterial for Gravel {
+    const ID: u64 = 15;
+
+    fn block_name() -> &'static str { "gravel" }
+    fn base_color() -> bevy::color::Color { Color::srgb_u8(136, 126, 126) }
+    fn flags() -> crate::BlockMaterialFlags { BlockMaterialFlags::SOLID }
+    fn perceptual_roughness() -> f32 { 0.9 }
+}
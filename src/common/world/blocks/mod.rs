@@ -1,15 +1,30 @@
 pub mod bedrock;
 pub use bedrock::*;
 
+pub mod cactus;
+pub use cactus::*;
+
 pub mod dirt;
 pub use dirt::*;
 
 pub mod grass;
 pub use grass::*;
 
+pub mod gravel;
+pub use gravel::*;
+
 pub mod leaves;
 pub use leaves::*;
 
+pub mod ore;
+pub use ore::*;
+
+pub mod sand;
+pub use sand::*;
+
+pub mod snow;
+pub use snow::*;
+
 pub mod stone;
 pub use stone::*;
 
@@ -21,6 +36,7 @@ pub use wood::*;
 
 use bevy::prelude::Plugin;
 use crate::common::world::material::BlockMaterialRegistry;
+use crate::{OreDefinition, TERRAIN_GENERATOR};
 
 pub struct BlockBaseMaterialsPlugin;
 impl Plugin for BlockBaseMaterialsPlugin {
@@ -37,6 +53,23 @@ impl Plugin for BlockBaseMaterialsPlugin {
         registry.register::<Stone>();
         registry.register::<Water>();
         registry.register::<Wood>();
+        registry.register::<CoalOre>();
+        registry.register::<IronOre>();
+        registry.register::<GoldOre>();
+        registry.register::<DiamondOre>();
+        registry.register::<Sand>();
+        registry.register::<Cactus>();
+        registry.register::<Snow>();
+        registry.register::<Gravel>();
+
+        // The ores replace the ones registered by the other apps sharing the generator
+        TERRAIN_GENERATOR
+            .write()
+            .unwrap()
+            .register_ore(OreDefinition::new::<CoalOre>(0..192, 14, 96))
+            .register_ore(OreDefinition::new::<IronOre>(0..128, 8, 64))
+            .register_ore(OreDefinition::new::<GoldOre>(0..64, 6, 24))
+            .register_ore(OreDefinition::new::<DiamondOre>(0..32, 4, 8));
     }
 }
 
@@ -0,0 +1,54 @@
+use bevy::color::{palettes::css, Color};
+
+use crate::{BlockMaterial, BlockMaterialFlags};
+
+pub struct CoalOre;
+
+impl BlockMaterial for CoalOre {
+    const ID: u64 = 8;
+
+    fn block_name() -> &'static str { "ore" }
+    fn variant() -> Option<&'static str> { Some("coal") }
+    fn base_color() -> Color { Color::srgb_u8(54, 54, 58) }
+    fn flags() -> BlockMaterialFlags { BlockMaterialFlags::SOLID }
+    fn perceptual_roughness() -> f32 { 0.9 }
+}
+
+pub struct IronOre;
+
+impl BlockMaterial for IronOre {
+    const ID: u64 = 9;
+
+    fn block_name() -> &'static str { "ore" }
+    fn variant() -> Option<&'static str> { Some("iron") }
+    fn base_color() -> Color { Color::srgb_u8(196, 152, 120) }
+    fn flags() -> BlockMaterialFlags { BlockMaterialFlags::SOLID }
+    fn perceptual_roughness() -> f32 { 0.7 }
+    fn metallic() -> f32 { 0.6 }
+}
+
+pub struct GoldOre;
+
+impl BlockMaterial for GoldOre {
+    const ID: u64 = 10;
+
+    fn block_name() -> &'static str { "ore" }
+    fn variant() -> Option<&'static str> { Some("gold") }
+    fn base_color() -> Color { css::GOLD.into() }
+    fn flags() -> BlockMaterialFlags { BlockMaterialFlags::SOLID }
+    fn perceptual_roughness() -> f32 { 0.4 }
+    fn metallic() -> f32 { 1.0 }
+}
+
+pub struct DiamondOre;
+
+impl BlockMaterial for DiamondOre {
+    const ID: u64 = 11;
+
+    fn block_name() -> &'static str { "ore" }
+    fn variant() -> Option<&'static str> { Some("diamond") }
+    fn base_color() -> Color { css::AQUAMARINE.into() }
+    fn flags() -> BlockMaterialFlags { BlockMaterialFlags::SOLID }
+    fn perceptual_roughness() -> f32 { 0.2 }
+    fn reflectance() -> f32 { 0.8 }
+}
@@ -0,0 +1,14 @@
+use bevy::color::Color;
+
+use crate::{BlockMaterial, BlockMaterialFlags};
+
+pub struct Sand;
+
+impl BlockMaterial for Sand {
+    const ID: u64 = 12;
+
+    fn block_name() -> &'static str { "sand" }
+    fn base_color() -> bevy::color::Color { Color::srgb_u8(219, 201, 144) }
+    fn flags() -> crate::BlockMaterialFlags { BlockMaterialFlags::SOLID }
+    fn perceptual_roughness() -> f32 { 0.95 }
+}
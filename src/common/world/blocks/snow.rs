@@ -0,0 +1,15 @@
+use bevy::color::Color;
+
+use crate::{BlockMaterial, BlockMaterialFlags};
+
+pub struct Snow;
+
+impl BlockMaterial for Snow {
+    const ID: u64 = 14;
+
+    fn block_name() -> &'static str { "snow" }
+    fn base_color() -> bevy::color::Color { Color::srgb_u8(240, 245, 250) }
+    fn flags() -> crate::BlockMaterialFlags { BlockMaterialFlags::SOLID }
+    fn perceptual_roughness() -> f32 { 0.6 }
+    fn reflectance() -> f32 { 0.7 }
+}
@@ -1,7 +1,7 @@
-use crate::{Block, ChunkMap, Player, CHUNK_HEIGHT, CHUNK_SIZE};
+use crate::{neighbour_offsets, Block, BlockChanged, BlockMaterialRegistry, ChunkMap, MaterialIdTable, Player, RegionStorage, ResultExt, WorldEditSet, CHUNK_HEIGHT, CHUNK_SIZE};
 use bevy::{
-    app::{Last, Plugin, PostUpdate, Update}, ecs::{
-        component::Component, entity::Entity, query::{Changed, With}, resource::Resource, schedule::{IntoScheduleConfigs, SystemSet}, system::{Commands, Query, Res, ResMut}
+    app::{AppExit, Last, Plugin, PostUpdate, Update}, ecs::{
+        component::Component, entity::Entity, event::EventReader, query::{Changed, With}, resource::Resource, schedule::{common_conditions::{not, resource_exists, resource_exists_and_changed}, IntoScheduleConfigs, SystemSet}, system::{Commands, Query, Res, ResMut}
     }, math::{FloatOrd, IVec3}, platform::collections::{HashMap, HashSet}, transform::components::GlobalTransform
 };
 use ndshape::ConstShape3u32;
@@ -13,6 +13,9 @@ const CHUNK_SIZE_U32: u32 = CHUNK_SIZE as u32;
 const CHUNK_HEIGHT_U32: u32 = CHUNK_HEIGHT as u32;
 pub type ChunkShape = ConstShape3u32<CHUNK_SIZE_U32, CHUNK_HEIGHT_U32, CHUNK_SIZE_U32>;
 
+/// Blocks of a chunk along each axis.
+pub const CHUNK_DIMS: IVec3 = IVec3::new(CHUNK_SIZE as i32, CHUNK_HEIGHT as i32, CHUNK_SIZE as i32);
+
 #[derive(Default, Resource)]
 pub struct ChunkEntities(HashMap<IVec3, Entity>);
 
@@ -51,6 +54,17 @@ impl DirtyChunks {
         self.0.insert(chunk);
     }
 
+    /// Marks the loaded neighbours of a chunk dirty, they have to be meshed again whenever the
+    /// chunk gets loaded since their border changes. Neighbours touching it by an edge or a
+    /// corner sample it for their ambient occlusion too.
+    pub fn mark_neighbours_dirty(&mut self, chunk: IVec3, chunks: &ChunkMap<Block, ChunkShape>) {
+        let dims = chunks.chunk_dims();
+        neighbour_offsets()
+            .map(|offset| chunk + offset * dims)
+            .filter(|neighbour| chunks.exists(*neighbour))
+            .for_each(|neighbour| self.mark_dirty(neighbour));
+    }
+
     pub fn iter_dirty(&self) -> impl Iterator<Item = &IVec3> {
         self.0.iter()
     }
@@ -60,6 +74,28 @@ impl DirtyChunks {
     }
 }
 
+/// Marks worlds whose chunks are streamed from a server instead of being loaded around the
+/// players.
+#[derive(Default, Resource)]
+pub struct StreamedWorld;
+
+/// Returns the minimum of the chunk containing the specified world position.
+#[inline]
+pub fn chunk_min_at(pos: IVec3) -> IVec3 {
+    pos & !IVec3::new(CHUNK_SIZE as i32 - 1, CHUNK_HEIGHT as i32 - 1, CHUNK_SIZE as i32 - 1)
+}
+
+/// Returns true if the chunk at `chunk_key` lies within the radius around `center`, both being
+/// chunk minimums.
+#[inline]
+pub fn chunk_in_radius(center: IVec3, chunk_key: IVec3, radius: &ChunkLoadRadius) -> bool {
+    let delta = (chunk_key - center)
+        / IVec3::new(CHUNK_SIZE as i32, CHUNK_HEIGHT as i32, CHUNK_SIZE as i32);
+
+    delta.x.pow(2) + delta.z.pow(2) <= radius.horizontal.pow(2)
+        && delta.y.pow(2) <= radius.vertical.pow(2)
+}
+
 #[derive(Resource)]
 pub struct CurrentLocalPlayer {
     pub chunk_min: IVec3,
@@ -103,58 +139,89 @@ impl Plugin for ChunkingPlugin {
         })
         .init_resource::<ChunkCommandQueue>()
         .init_resource::<DirtyChunks>()
+        .add_event::<BlockChanged>()
         .configure_sets(Update, ChunkLoadingSet)
+        .configure_sets(Update, WorldEditSet.after(ChunkLoadingSet))
+        .add_systems(
+            Update,
+            sync_region_materials
+                .run_if(resource_exists_and_changed::<BlockMaterialRegistry>)
+                .before(ChunkLoadingSet)
+        )
         .add_systems(
             Update,
-            (update_player_pos, update_view_chunks, create_chunks)
+            (
+                update_player_pos,
+                update_view_chunks.run_if(not(resource_exists::<StreamedWorld>)),
+                create_chunks,
+            )
                 .chain()
                 .in_set(ChunkLoadingSet)
         )
         .add_systems(PostUpdate, destroy_chunks)
-        .add_systems(Last, clear_dirty);
+        .add_systems(Last, (clear_dirty, save_chunks_on_exit));
     }
 }
 
+/// Queues the chunks around every player for loading and the ones out of reach of all of them for
+/// unloading, the area around the local player position is kept loaded when there's no player.
 fn update_view_chunks(
     player_pos: Res<CurrentLocalPlayer>,
+    players: Query<&GlobalTransform, With<Player>>,
     chunk_entities: Res<ChunkEntities>,
     view_radius: Res<ChunkLoadRadius>,
     mut chunk_command_queue: ResMut<ChunkCommandQueue>,
 ) {
-    for x in -view_radius.horizontal..view_radius.horizontal {
-        for z in -view_radius.horizontal..view_radius.horizontal {
-            for y in -view_radius.vertical..view_radius.vertical {
-                if x.pow(2) + z.pow(2) >= view_radius.horizontal.pow(2) {
-                    continue;
-                }
+    let mut centers: Vec<IVec3> = players
+        .iter()
+        .map(|transform| chunk_min_at(transform.translation().as_ivec3()))
+        .collect();
+    if centers.is_empty() {
+        centers.push(player_pos.chunk_min);
+    }
+    centers.sort_unstable_by_key(|center| center.to_array());
+    centers.dedup();
+
+    let mut queued: HashSet<IVec3> = chunk_command_queue.create.iter().copied().collect();
 
-                let chunk_key = player_pos.chunk_min
-                    + IVec3::new(
-                        (x * CHUNK_SIZE as i32).into(),
-                        (y * CHUNK_HEIGHT as i32).into(),
-                        (z * CHUNK_SIZE as i32).into(),
-                    );
+    for center in centers.iter() {
+        for x in -view_radius.horizontal..view_radius.horizontal {
+            for z in -view_radius.horizontal..view_radius.horizontal {
+                for y in -view_radius.vertical..view_radius.vertical {
+                    if x.pow(2) + z.pow(2) >= view_radius.horizontal.pow(2) {
+                        continue;
+                    }
 
-                if chunk_entities.entity(chunk_key).is_none() {
-                    chunk_command_queue.create.push(chunk_key);
+                    let chunk_key = *center
+                        + IVec3::new(
+                            x * CHUNK_SIZE as i32,
+                            y * CHUNK_HEIGHT as i32,
+                            z * CHUNK_SIZE as i32,
+                        );
+
+                    if chunk_entities.entity(chunk_key).is_none() && queued.insert(chunk_key) {
+                        chunk_command_queue.create.push(chunk_key);
+                    }
                 }
             }
         }
     }
 
     for loaded_chunk in chunk_entities.iter_keys() {
-        let delta: IVec3 = *loaded_chunk - player_pos.chunk_min;
-
-        if delta.x.pow(2) + delta.z.pow(2)
-            > (view_radius.horizontal.pow(2) * (CHUNK_SIZE as i32).pow(2)).into()
-            || delta.y.pow(2) > (view_radius.vertical.pow(2) * (CHUNK_HEIGHT as i32).pow(2)).into()
+        if !centers
+            .iter()
+            .any(|center| chunk_in_radius(*center, *loaded_chunk, &view_radius))
         {
             chunk_command_queue.destroy.push(*loaded_chunk);
         }
     }
 
     chunk_command_queue.create.sort_unstable_by_key(|key| {
-        FloatOrd(key.as_vec3().distance(player_pos.chunk_min.as_vec3()))
+        centers
+            .iter()
+            .map(|center| FloatOrd(key.as_vec3().distance(center.as_vec3())))
+            .min()
+            .unwrap()
     });
 }
 
@@ -173,15 +240,56 @@ pub fn destroy_chunks(
     mut chunk_command_queue: ResMut<ChunkCommandQueue>,
     mut chunk_entities: ResMut<ChunkEntities>,
     mut chunks: ResMut<ChunkMap<Block, ChunkShape>>,
+    storage: Option<Res<RegionStorage>>,
     mut cmds: Commands,
 ) {
     chunk_command_queue.destroy.drain(..).for_each(|command| {
-        cmds.entity(chunk_entities.detach_entity(command).unwrap())
-            .despawn();
-        chunks.remove(command);
+        let Some(entity) = chunk_entities.detach_entity(command) else {
+            return;
+        };
+        cmds.entity(entity).despawn();
+
+        if let (Some(buffer), Some(storage)) = (chunks.remove(command), storage.as_ref()) {
+            storage
+                .save_chunk(command, &buffer)
+                .log_err_with("Failed to save chunk");
+        }
     });
 }
 
+/// Keeps the material table of the region storage in sync with the material registry.
+pub fn sync_region_materials(
+    registry: Res<BlockMaterialRegistry>,
+    storage: Option<Res<RegionStorage>>,
+) {
+    if let Some(storage) = storage {
+        storage.set_materials(MaterialIdTable::from(&*registry));
+    }
+}
+
+/// Writes every loaded chunk to the region storage when the app is about to exit.
+pub fn save_chunks_on_exit(
+    mut exit_events: EventReader<AppExit>,
+    chunks: Res<ChunkMap<Block, ChunkShape>>,
+    storage: Option<Res<RegionStorage>>,
+) {
+    if exit_events.read().last().is_none() {
+        return;
+    }
+
+    let Some(storage) = storage else {
+        return;
+    };
+
+    for (key, buffer) in chunks.iter() {
+        storage
+            .save_chunk(key, buffer)
+            .log_err_with("Failed to save chunk");
+    }
+
+    storage.flush().log_err_with("Failed to flush region storage");
+}
+
 pub fn clear_dirty(mut dirty_chunks: ResMut<DirtyChunks>) {
     dirty_chunks.0.clear();
 }
@@ -193,7 +301,7 @@ pub fn update_player_pos(
 ) {
     if let Ok(ply) = player.single() {
         let player_coords = ply.translation().as_ivec3();
-        let nearest_chunk_origin = !IVec3::splat((CHUNK_SIZE - 1) as i32) & player_coords;
+        let nearest_chunk_origin = chunk_min_at(player_coords);
 
         chunk_pos.world_pos = player_coords;
 
@@ -0,0 +1,56 @@
+use bevy::prelude::*;
+use std::f32::consts::TAU;
+
+/// Length of a full day in the world, in seconds.
+pub const DAY_LENGTH_SECS: f32 = 20. * 60.;
+
+/// Times of the day which can be referred to by name.
+pub const TIME_NAMES: [&str; 5] = ["day", "noon", "sunset", "night", "midnight"];
+
+/// Times of the day matching [`TIME_NAMES`].
+const NAMED_TIMES: [f32; 5] = [0.04, 0.25, 0.5, 0.54, 0.75];
+
+/// How far the current day went, 0 is sunrise, 0.25 noon, 0.5 sunset and 0.75 midnight.
+#[derive(Resource, Clone, Copy, Debug, Deref, DerefMut)]
+pub struct TimeOfDay(pub f32);
+
+impl Default for TimeOfDay {
+    fn default() -> Self {
+        Self(NAMED_TIMES[0])
+    }
+}
+
+impl TimeOfDay {
+    pub fn from_name(name: &str) -> Option<Self> {
+        TIME_NAMES
+            .iter()
+            .position(|time_name| *time_name == name)
+            .map(|index| Self(NAMED_TIMES[index]))
+    }
+
+    /// Direction pointing towards the sun, it rises in the east and sets in the west.
+    pub fn sun_direction(&self) -> Vec3 {
+        let angle = self.0 * TAU;
+        Vec3::new(angle.cos(), angle.sin(), 0.2).normalize()
+    }
+
+    /// Returns the time as shown on a clock, sunrise being at 06:00.
+    pub fn clock(&self) -> String {
+        let minutes = ((self.0 + 0.25).fract() * 24. * 60.) as u32;
+        format!("{:02}:{:02}", minutes / 60, minutes % 60)
+    }
+}
+
+pub fn advance_time_of_day(mut time_of_day: ResMut<TimeOfDay>, time: Res<Time>) {
+    time_of_day.0 = (time_of_day.0 + time.delta_secs() / DAY_LENGTH_SECS).fract();
+}
+
+/// Makes days go by, servers send their time to clients when they join.
+pub struct TimeOfDayPlugin;
+
+impl Plugin for TimeOfDayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TimeOfDay>()
+            .add_systems(Update, advance_time_of_day);
+    }
+}
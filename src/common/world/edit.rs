@@ -0,0 +1,138 @@
+use crate::{
+    neighbour_offsets, Block, BlockMaterialFlags, BlockMaterialRegistry, ChunkMap, ChunkShape,
+    DirtyChunks,
+};
+use bevy::{
+    ecs::{
+        event::{Event, EventWriter},
+        schedule::SystemSet,
+        system::{ResMut, SystemParam},
+    },
+    math::IVec3,
+    platform::collections::HashSet,
+};
+use ilattice::extent::Extent;
+
+/// Sent whenever a block of the world is changed through [`WorldEdits`].
+#[derive(Event, Clone, Copy, Debug)]
+pub struct BlockChanged {
+    pub pos: IVec3,
+    pub old: Block,
+    pub new: Block,
+}
+
+/// Returns true if a player is allowed to break the specified block.
+pub fn is_breakable(registry: &BlockMaterialRegistry, block: Block) -> bool {
+    !block.is_empty()
+        && !registry
+            .block_flags(block)
+            .contains(BlockMaterialFlags::UNBREAKABLE)
+}
+
+/// Returns true if a player is allowed to place the specified block, players can't place the
+/// blocks they wouldn't be able to break.
+pub fn is_placeable(registry: &BlockMaterialRegistry, block: Block) -> bool {
+    is_breakable(registry, block)
+}
+
+/// Returns true if a player is allowed to place a block over the specified one.
+pub fn is_replaceable(registry: &BlockMaterialRegistry, block: Block) -> bool {
+    block.is_empty() || registry.block_flags(block).contains(BlockMaterialFlags::LIQUID)
+}
+
+/// Label for the systems editing the world, edits have to be done before chunks get remeshed.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug, Hash, SystemSet)]
+pub struct WorldEditSet;
+
+/// Provides an interface to place or break blocks in the world, keeping the affected chunks
+/// marked as dirty and notifying the changes with [`BlockChanged`] events.
+#[derive(SystemParam)]
+pub struct WorldEdits<'w> {
+    chunks: ResMut<'w, ChunkMap<Block, ChunkShape>>,
+    dirty_chunks: ResMut<'w, DirtyChunks>,
+    block_changed: EventWriter<'w, BlockChanged>,
+}
+
+impl WorldEdits<'_> {
+    /// Returns the block at the specified world position if its chunk is loaded.
+    #[inline]
+    pub fn block_at(&self, pos: IVec3) -> Option<Block> {
+        self.chunks.block_at(pos)
+    }
+
+    /// Sets the block at the specified world position and returns the previous one, or `None` if
+    /// the chunk holding it isn't loaded.
+    pub fn set_block(&mut self, pos: IVec3, block: Block) -> Option<Block> {
+        let mut touched_chunks = HashSet::new();
+        let old = self.set_block_inner(pos, block, &mut touched_chunks)?;
+
+        touched_chunks
+            .into_iter()
+            .for_each(|key| self.dirty_chunks.mark_dirty(key));
+
+        Some(old)
+    }
+
+    /// Sets every block in the extent expressed in world space, blocks in unloaded chunks are
+    /// skipped. Returns the number of blocks that changed.
+    pub fn set_blocks_in_extent(
+        &mut self,
+        extent: Extent<ilattice::glam::IVec3>,
+        block: Block,
+    ) -> usize {
+        let mut touched_chunks = HashSet::new();
+        let changed = extent
+            .iter3()
+            .map(|pos| IVec3::from(pos.to_array()))
+            .filter_map(|pos| self.set_block_inner(pos, block, &mut touched_chunks))
+            .filter(|old| *old != block)
+            .count();
+
+        touched_chunks
+            .into_iter()
+            .for_each(|key| self.dirty_chunks.mark_dirty(key));
+
+        changed
+    }
+
+    fn set_block_inner(
+        &mut self,
+        pos: IVec3,
+        block: Block,
+        touched_chunks: &mut HashSet<IVec3>,
+    ) -> Option<Block> {
+        let old = self.chunks.set_block(pos, block)?;
+
+        if old == block {
+            return Some(old);
+        }
+
+        self.block_changed.write(BlockChanged {
+            pos,
+            old,
+            new: block,
+        });
+
+        // Faces and ambient occlusion of the neighbouring chunks depend on the blocks along the
+        // border, blocks on an edge or a corner touch the chunks diagonal to theirs as well
+        let key = self.chunks.chunk_key(pos);
+        let dims = self.chunks.chunk_dims();
+        let local = pos - key;
+        let sides = IVec3::select(
+            local.cmpeq(IVec3::ZERO),
+            IVec3::NEG_ONE,
+            IVec3::select(local.cmpeq(dims - IVec3::ONE), IVec3::ONE, IVec3::ZERO),
+        );
+        touched_chunks.insert(key);
+
+        neighbour_offsets()
+            .filter(|offset| (offset.cmpeq(IVec3::ZERO) | offset.cmpeq(sides)).all())
+            .map(|offset| key + offset * dims)
+            .filter(|neighbour| self.chunks.exists(*neighbour))
+            .for_each(|neighbour| {
+                touched_chunks.insert(neighbour);
+            });
+
+        Some(old)
+    }
+}
@@ -0,0 +1,547 @@
+use crate::{
+    destroy_chunks, Block, BlockChanged, BlockMaterialRegistry, ChunkMap,
+    ChunkShape, DirtyChunks, MaterialBlock, TerrainGenSet, WorldEditSet, CHUNK_DIMS, CHUNK_SIZE,
+};
+use bevy::{
+    app::{Plugin, PostUpdate, Update},
+    ecs::{
+        event::EventReader,
+        resource::Resource,
+        schedule::{IntoScheduleConfigs, SystemSet},
+        system::{Res, ResMut},
+    },
+    math::IVec3,
+    platform::collections::{HashMap, HashSet},
+};
+use ndshape::{RuntimeShape, Shape};
+use std::collections::VecDeque;
+
+/// Highest light level, the level of direct sky light.
+pub const MAX_LIGHT: u8 = 15;
+
+const NEIGHBOURS: [IVec3; 6] = [
+    IVec3::NEG_X,
+    IVec3::NEG_Y,
+    IVec3::NEG_Z,
+    IVec3::X,
+    IVec3::Y,
+    IVec3::Z,
+];
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LightChannel {
+    Sky,
+    Block,
+}
+
+impl LightChannel {
+    #[inline]
+    const fn get(self, packed: u8) -> u8 {
+        match self {
+            Self::Sky => packed >> 4,
+            Self::Block => packed & 0xF,
+        }
+    }
+
+    #[inline]
+    const fn set(self, packed: u8, level: u8) -> u8 {
+        match self {
+            Self::Sky => (packed & 0x0F) | (level << 4),
+            Self::Block => (packed & 0xF0) | level,
+        }
+    }
+}
+
+/// Light levels of the loaded chunks, every voxel packs its sky light in the high nibble and its
+/// block light in the low nibble of a byte.
+#[derive(Default, Resource)]
+pub struct LightMap {
+    chunks: HashMap<IVec3, Box<[u8]>>,
+}
+
+impl LightMap {
+    /// Returns the packed light at the specified world position, unlit if its chunk isn't lit yet.
+    #[inline]
+    pub fn light_at(&self, pos: IVec3) -> u8 {
+        let (key, index) = Self::locate(pos);
+        self.chunks.get(&key).map_or(0, |light| light[index])
+    }
+
+    #[inline]
+    pub fn sky_light_at(&self, pos: IVec3) -> u8 {
+        LightChannel::Sky.get(self.light_at(pos))
+    }
+
+    #[inline]
+    pub fn block_light_at(&self, pos: IVec3) -> u8 {
+        LightChannel::Block.get(self.light_at(pos))
+    }
+
+    /// Check whether the chunk at the specified minimum has been lit.
+    #[inline]
+    pub fn contains(&self, key: IVec3) -> bool {
+        self.chunks.contains_key(&key)
+    }
+
+    /// Returns the light of the chunk at the specified minimum padded by one voxel taken from the
+    /// neighbouring chunks, laid out as the chunk shape grown by 2 along every axis.
+    pub fn padded_light(&self, key: IVec3) -> Vec<u8> {
+        let padded_shape =
+            RuntimeShape::<u32, 3>::new(CHUNK_DIMS.as_uvec3().to_array().map(|x| x + 2));
+        let mut padded = vec![0u8; padded_shape.size() as usize];
+        let dims = padded_shape.as_array().map(|x| x as i32);
+        let own_light = self.chunks.get(&key);
+
+        for x in 0..dims[0] {
+            for y in 0..dims[1] {
+                for z in 0..dims[2] {
+                    let interior = x > 0
+                        && y > 0
+                        && z > 0
+                        && x < dims[0] - 1
+                        && y < dims[1] - 1
+                        && z < dims[2] - 1;
+
+                    let local = IVec3::new(x - 1, y - 1, z - 1);
+                    let light = match (interior, own_light) {
+                        (true, Some(light)) => {
+                            light[ChunkShape {}.linearize(local.as_uvec3().to_array()) as usize]
+                        }
+                        (true, None) => 0,
+                        (false, _) => self.light_at(key + local),
+                    };
+
+                    padded[padded_shape.linearize([x as u32, y as u32, z as u32]) as usize] = light;
+                }
+            }
+        }
+
+        padded
+    }
+
+    /// Drops the light of the chunks which aren't loaded anymore.
+    pub fn retain_loaded(&mut self, chunks: &ChunkMap<Block, ChunkShape>) {
+        self.chunks.retain(|key, _| chunks.exists(*key));
+    }
+
+    #[inline]
+    fn get(&self, pos: IVec3, channel: LightChannel) -> Option<u8> {
+        let (key, index) = Self::locate(pos);
+        self.chunks.get(&key).map(|light| channel.get(light[index]))
+    }
+
+    #[inline]
+    fn set(&mut self, pos: IVec3, channel: LightChannel, level: u8) {
+        let (key, index) = Self::locate(pos);
+        if let Some(light) = self.chunks.get_mut(&key) {
+            light[index] = channel.set(light[index], level);
+        }
+    }
+
+    #[inline]
+    fn locate(pos: IVec3) -> (IVec3, usize) {
+        let key = pos & !(CHUNK_DIMS - IVec3::ONE);
+        let local = (pos - key).as_uvec3().to_array();
+        (key, ChunkShape {}.linearize(local) as usize)
+    }
+}
+
+/// Lighting properties of the registered materials.
+pub struct LightMaterials {
+    properties: Vec<(bool, u8)>,
+}
+
+impl From<&BlockMaterialRegistry> for LightMaterials {
+    fn from(registry: &BlockMaterialRegistry) -> Self {
+        Self {
+            properties: registry
+                .iter_materials()
+                .into_iter()
+                .map(|material| {
+                    let translucent = material.flags.is_translucent();
+                    let emissive = material.emissive.to_linear();
+                    let emission = (emissive.red.max(emissive.green).max(emissive.blue))
+                        .clamp(0., 1.)
+                        * MAX_LIGHT as f32;
+
+                    (translucent, emission.round() as u8)
+                })
+                .collect(),
+        }
+    }
+}
+
+impl LightMaterials {
+    /// Returns true if light can travel through the block.
+    #[inline]
+    pub fn is_translucent(&self, block: Block) -> bool {
+        block.is_empty()
+            || block.is_transparent()
+            || self
+                .properties
+                .get(block.as_mat_id() as usize)
+                .is_some_and(|(translucent, _)| *translucent)
+    }
+
+    /// Returns the block light level emitted by the block.
+    #[inline]
+    pub fn emission(&self, block: Block) -> u8 {
+        self.properties
+            .get(block.as_mat_id() as usize)
+            .map_or(0, |(_, emission)| *emission)
+    }
+}
+
+/// Breadth-first light propagation over the loaded chunks.
+pub struct LightPropagator<'a> {
+    chunks: &'a ChunkMap<Block, ChunkShape>,
+    light: &'a mut LightMap,
+    materials: &'a LightMaterials,
+    touched: HashSet<IVec3>,
+}
+
+impl<'a> LightPropagator<'a> {
+    pub fn new(
+        chunks: &'a ChunkMap<Block, ChunkShape>,
+        light: &'a mut LightMap,
+        materials: &'a LightMaterials,
+    ) -> Self {
+        Self {
+            chunks,
+            light,
+            materials,
+            touched: HashSet::new(),
+        }
+    }
+
+    /// Returns the minimums of the chunks whose light changed.
+    pub fn into_touched(self) -> HashSet<IVec3> {
+        self.touched
+    }
+
+    /// Computes the light of a freshly loaded chunk and spreads it to and from its neighbours.
+    pub fn light_chunk(&mut self, key: IVec3) {
+        let chunks = self.chunks;
+        let Some(buffer) = chunks.buffer_at(key) else {
+            return;
+        };
+
+        self.light
+            .chunks
+            .insert(key, vec![0u8; ChunkShape {}.size() as usize].into_boxed_slice());
+        self.touched.insert(key);
+
+        let mut sky_queue = VecDeque::new();
+        let mut block_queue = VecDeque::new();
+
+        // Open sky columns, only the top of the world has no chunk above it
+        if !self.chunks.exists(key + IVec3::Y * CHUNK_DIMS.y) {
+            let mut open_heights = [[CHUNK_DIMS.y; CHUNK_SIZE]; CHUNK_SIZE];
+
+            for x in 0..CHUNK_DIMS.x {
+                for z in 0..CHUNK_DIMS.z {
+                    let mut y = CHUNK_DIMS.y - 1;
+                    while y >= 0 {
+                        let pos = key + IVec3::new(x, y, z);
+                        if !self.materials.is_translucent(buffer.block_at(local(pos - key))) {
+                            break;
+                        }
+                        self.light.set(pos, LightChannel::Sky, MAX_LIGHT);
+                        y -= 1;
+                    }
+                    open_heights[x as usize][z as usize] = y + 1;
+                }
+            }
+
+            for x in 0..CHUNK_DIMS.x {
+                for z in 0..CHUNK_DIMS.z {
+                    let height = open_heights[x as usize][z as usize];
+
+                    // Light only needs to spread sideways where a neighbouring column is shadowed
+                    let shadowed_up_to = [(-1, 0), (1, 0), (0, -1), (0, 1)]
+                        .into_iter()
+                        .map(|(dx, dz)| {
+                            let (nx, nz) = (x + dx, z + dz);
+                            if nx < 0 || nz < 0 || nx >= CHUNK_DIMS.x || nz >= CHUNK_DIMS.z {
+                                self.neighbour_open_height(key + IVec3::new(nx, 0, nz))
+                            } else {
+                                open_heights[nx as usize][nz as usize]
+                            }
+                        })
+                        .max()
+                        .unwrap_or(0);
+
+                    for y in height..shadowed_up_to.max(height + 1).min(CHUNK_DIMS.y) {
+                        sky_queue.push_back(key + IVec3::new(x, y, z));
+                    }
+                }
+            }
+        }
+
+        if buffer.any(|block| self.materials.emission(block) > 0) {
+            for x in 0..CHUNK_DIMS.x {
+                for y in 0..CHUNK_DIMS.y {
+                    for z in 0..CHUNK_DIMS.z {
+                        let pos = IVec3::new(x, y, z);
+                        let emission = self.materials.emission(buffer.block_at(local(pos)));
+
+                        if emission > 0 {
+                            self.light.set(key + pos, LightChannel::Block, emission);
+                            block_queue.push_back(key + pos);
+                        }
+                    }
+                }
+            }
+        }
+
+        // Light coming from the neighbouring chunks borders
+        for dir in NEIGHBOURS {
+            let neighbour = key + dir * CHUNK_DIMS;
+            if !self.light.contains(neighbour) {
+                continue;
+            }
+
+            let axis = dir.abs().to_array().iter().position(|x| *x == 1).unwrap();
+            let layer = if dir[axis] > 0 { CHUNK_DIMS[axis] } else { -1 };
+            let (a, b) = match axis {
+                0 => (1, 2),
+                1 => (0, 2),
+                _ => (0, 1),
+            };
+
+            for u in 0..CHUNK_DIMS[a] {
+                for v in 0..CHUNK_DIMS[b] {
+                    let mut pos = IVec3::ZERO;
+                    pos[axis] = layer;
+                    pos[a] = u;
+                    pos[b] = v;
+
+                    let packed = self.light.light_at(key + pos);
+                    if LightChannel::Sky.get(packed) > 1 {
+                        sky_queue.push_back(key + pos);
+                    }
+                    if LightChannel::Block.get(packed) > 1 {
+                        block_queue.push_back(key + pos);
+                    }
+                }
+            }
+        }
+
+        self.propagate(LightChannel::Sky, sky_queue);
+        self.propagate(LightChannel::Block, block_queue);
+    }
+
+    /// Incrementally updates the light around a block that changed.
+    pub fn update_block(&mut self, pos: IVec3, block: Block) {
+        let translucent = self.materials.is_translucent(block);
+        let key = self.chunks.chunk_key(pos);
+        let top_of_world = pos.y - key.y == CHUNK_DIMS.y - 1
+            && !self.chunks.exists(key + IVec3::Y * CHUNK_DIMS.y);
+
+        for channel in [LightChannel::Sky, LightChannel::Block] {
+            let mut seeds = VecDeque::new();
+            let level = self.light.get(pos, channel).unwrap_or(0);
+
+            if level > 0 {
+                self.set(pos, channel, 0);
+                seeds = self.remove(channel, VecDeque::from([(pos, level)]));
+            }
+
+            if translucent {
+                NEIGHBOURS
+                    .iter()
+                    .map(|dir| pos + *dir)
+                    .filter(|neighbour| self.light.get(*neighbour, channel).unwrap_or(0) > 0)
+                    .for_each(|neighbour| seeds.push_back(neighbour));
+
+                if channel == LightChannel::Sky && top_of_world {
+                    self.set(pos, channel, MAX_LIGHT);
+                    seeds.push_back(pos);
+                }
+            }
+
+            if channel == LightChannel::Block {
+                let emission = self.materials.emission(block);
+                if emission > 0 {
+                    self.set(pos, channel, emission);
+                    seeds.push_back(pos);
+                }
+            }
+
+            self.propagate(channel, seeds);
+        }
+    }
+
+    #[inline]
+    fn set(&mut self, pos: IVec3, channel: LightChannel, level: u8) {
+        self.light.set(pos, channel, level);
+        self.touched.insert(LightMap::locate(pos).0);
+    }
+
+    /// Returns the height from which the column at `pos` in a neighbouring chunk is open to the
+    /// sky, relative to the chunk minimum.
+    ///
+    /// Columns of chunks which aren't lit yet count as open, they pull the light of this chunk
+    /// in once they are lit.
+    fn neighbour_open_height(&self, pos: IVec3) -> i32 {
+        let key = self.chunks.chunk_key(pos);
+        if !self.light.contains(key) {
+            return 0;
+        }
+
+        let mut y = CHUNK_DIMS.y - 1;
+        while y >= 0 && self.holds_light(IVec3::new(pos.x, key.y + y, pos.z)) {
+            y -= 1;
+        }
+        y + 1
+    }
+
+    #[inline]
+    fn holds_light(&self, pos: IVec3) -> bool {
+        self.chunks
+            .block_at(pos)
+            .is_some_and(|block| self.materials.is_translucent(block))
+    }
+
+    fn propagate(&mut self, channel: LightChannel, mut queue: VecDeque<IVec3>) {
+        while let Some(pos) = queue.pop_front() {
+            let level = self.light.get(pos, channel).unwrap_or(0);
+            if level <= 1 {
+                continue;
+            }
+
+            for dir in NEIGHBOURS {
+                let neighbour = pos + dir;
+                if !self.holds_light(neighbour) {
+                    continue;
+                }
+
+                // Direct sky light travels down through open air without fading
+                let new_level = if channel == LightChannel::Sky
+                    && dir == IVec3::NEG_Y
+                    && level == MAX_LIGHT
+                    && self.chunks.block_at(neighbour).is_some_and(|block| block.is_empty())
+                {
+                    MAX_LIGHT
+                } else {
+                    level - 1
+                };
+
+                if self.light.get(neighbour, channel).unwrap_or(MAX_LIGHT) < new_level {
+                    self.set(neighbour, channel, new_level);
+                    queue.push_back(neighbour);
+                }
+            }
+        }
+    }
+
+    /// Darkens the voxels lit by the removed sources and returns the voxels which still hold
+    /// light from other sources, to propagate it back.
+    fn remove(
+        &mut self,
+        channel: LightChannel,
+        mut queue: VecDeque<(IVec3, u8)>,
+    ) -> VecDeque<IVec3> {
+        let mut seeds = VecDeque::new();
+
+        while let Some((pos, level)) = queue.pop_front() {
+            for dir in NEIGHBOURS {
+                let neighbour = pos + dir;
+                let Some(neighbour_level) = self.light.get(neighbour, channel) else {
+                    continue;
+                };
+
+                if neighbour_level == 0 {
+                    continue;
+                }
+
+                let sky_column = channel == LightChannel::Sky
+                    && dir == IVec3::NEG_Y
+                    && level == MAX_LIGHT
+                    && neighbour_level == MAX_LIGHT;
+
+                if neighbour_level < level || sky_column {
+                    self.set(neighbour, channel, 0);
+                    queue.push_back((neighbour, neighbour_level));
+                } else {
+                    seeds.push_back(neighbour);
+                }
+            }
+        }
+
+        seeds
+    }
+}
+
+#[inline]
+fn local(pos: IVec3) -> ilattice::glam::UVec3 {
+    ilattice::glam::UVec3::from(pos.as_uvec3().to_array())
+}
+
+/// Label for the systems updating the light of the world.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug, Hash, SystemSet)]
+pub struct LightingSet;
+
+/// Loaded chunks lit per frame at most, the others stay unlit until the next frames.
+pub const MAX_CHUNKS_LIT_PER_FRAME: usize = 4;
+
+/// Lights the loaded chunks which aren't lit yet, `MAX_CHUNKS_LIT_PER_FRAME` at a time, and
+/// updates the light around the blocks that changed.
+pub fn update_lighting(
+    mut light_map: ResMut<LightMap>,
+    mut dirty_chunks: ResMut<DirtyChunks>,
+    mut block_changes: EventReader<BlockChanged>,
+    chunks: Res<ChunkMap<Block, ChunkShape>>,
+    registry: Res<BlockMaterialRegistry>,
+) {
+    let new_chunks: Vec<IVec3> = chunks
+        .iter()
+        .map(|(key, _)| key)
+        .filter(|key| !light_map.contains(*key))
+        .take(MAX_CHUNKS_LIT_PER_FRAME)
+        .collect();
+    let changes: Vec<BlockChanged> = block_changes.read().copied().collect();
+
+    if new_chunks.is_empty() && changes.is_empty() {
+        return;
+    }
+
+    let materials = LightMaterials::from(&*registry);
+    let mut propagator = LightPropagator::new(&chunks, &mut light_map, &materials);
+
+    new_chunks
+        .into_iter()
+        .for_each(|key| propagator.light_chunk(key));
+    changes
+        .into_iter()
+        .for_each(|change| propagator.update_block(change.pos, change.new));
+
+    propagator
+        .into_touched()
+        .into_iter()
+        .for_each(|key| dirty_chunks.mark_dirty(key));
+}
+
+pub fn prune_unloaded_light(
+    mut light_map: ResMut<LightMap>,
+    chunks: Res<ChunkMap<Block, ChunkShape>>,
+) {
+    light_map.retain_loaded(&chunks);
+}
+
+pub struct LightingPlugin;
+
+impl Plugin for LightingPlugin {
+    fn build(&self, app: &mut bevy::app::App) {
+        app.init_resource::<LightMap>()
+            .configure_sets(
+                Update,
+                LightingSet.after(TerrainGenSet).after(WorldEditSet),
+            )
+            .add_systems(Update, update_lighting.in_set(LightingSet))
+            .add_systems(
+                PostUpdate,
+                prune_unloaded_light.after(destroy_chunks),
+            );
+    }
+}
@@ -1,19 +1,24 @@
 use bevy::{
+    color::{ColorToComponents, LinearRgba},
     platform::collections::HashMap,
     prelude::{Color, Plugin, Resource},
 };
 use bitflags::bitflags;
+use serde::{Deserialize, Serialize};
 use std::any::TypeId;
 use tracing::info;
 
-use crate::Block;
+use crate::{Block, ClientError, GameError, MaterialBlock};
+
+/// Numeric ids above this one can't be told apart by the terrain meshes and shaders.
+pub const MAX_MATERIAL_ID: u64 = u8::MAX as u64;
 
 #[derive(Default)]
 pub struct MaterialRegistryInfo {
-    pub id: &'static str,
-    pub namespace: &'static str,
-    pub name: &'static str,
-    pub variant: Option<&'static str>,
+    pub id: String,
+    pub namespace: String,
+    pub name: String,
+    pub variant: Option<String>,
     pub base_color: Color,
     pub flags: BlockMaterialFlags,
     pub emissive: Color,
@@ -38,8 +43,12 @@ pub trait BlockMaterial {
     // --- Visual and Physical Properties ---
     fn base_color() -> Color;
     fn flags() -> BlockMaterialFlags;
+    /// Light emitted by the block, its brightest channel also sets the block light it gives off.
+    ///
+    /// Defaults to black so only the materials opting in glow, a white default would make every
+    /// block a full strength light source and leave no cave dark.
     fn emissive() -> Color {
-        Color::WHITE
+        Color::BLACK
     }
     fn perceptual_roughness() -> f32 {
         0.8
@@ -58,8 +67,13 @@ pub trait BlockMaterial {
         }
     }
 
+    /// Returns a block of this material, translucent materials get the transparency flag.
     fn into_block() -> Block {
-        Block(Self::ID)
+        if Self::flags().is_translucent() {
+            Block(Self::ID | Block::TRANSPARENT_FLAG)
+        } else {
+            Block(Self::ID)
+        }
     }
 }
 
@@ -147,6 +161,7 @@ macro_rules! __block_material_fn {
 }
 
 bitflags! {
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
     pub struct BlockMaterialFlags : u32 {
         const SOLID = 0;
         const LIQUID = 1 << 1;
@@ -155,12 +170,54 @@ bitflags! {
     }
 }
 
+impl BlockMaterialFlags {
+    /// Returns true if blocks with these flags can be seen through.
+    pub fn is_translucent(&self) -> bool {
+        self.intersects(Self::TRANSPARENT | Self::LIQUID)
+    }
+}
+
 impl Default for BlockMaterialFlags {
     fn default() -> Self {
         Self::SOLID
     }
 }
 
+/// A registered material as sent by the server, so that clients see the same materials under the
+/// same numeric ids.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct MaterialDefinition {
+    pub numeric_id: u64,
+    pub id: String,
+    pub flags: u32,
+    /// Linear RGBA components.
+    pub base_color: [f32; 4],
+    /// Linear RGBA components.
+    pub emissive: [f32; 4],
+    pub perceptual_roughness: f32,
+    pub metallic: f32,
+    pub reflectance: f32,
+}
+
+impl MaterialDefinition {
+    fn to_info(&self) -> MaterialRegistryInfo {
+        let mut parts = self.id.splitn(3, "::").map(str::to_string);
+
+        MaterialRegistryInfo {
+            namespace: parts.next().unwrap_or_default(),
+            name: parts.next().unwrap_or_default(),
+            variant: parts.next(),
+            id: self.id.clone(),
+            base_color: LinearRgba::from_f32_array(self.base_color).into(),
+            flags: BlockMaterialFlags::from_bits_retain(self.flags),
+            emissive: LinearRgba::from_f32_array(self.emissive).into(),
+            perceptual_roughness: self.perceptual_roughness,
+            metallic: self.metallic,
+            reflectance: self.reflectance,
+        }
+    }
+}
+
 /// A registry for block material types.
 /// This stores the block materials along their material id used to refer
 /// them in block data
@@ -182,12 +239,37 @@ impl BlockMaterialRegistry {
         self.materials.get_mut(id as usize)
     }
 
+    /// Returns a block of the registered material with the specified id, translucent materials get
+    /// the transparency flag.
+    pub fn block_for_id(&self, id: u64) -> Option<Block> {
+        self.get_by_id(id)
+            .filter(|material| id != 0 && !material.id.is_empty())
+            .map(|material| {
+                if material.flags.is_translucent() {
+                    Block::new_transparent(id)
+                } else {
+                    Block::new_opaque(id)
+                }
+            })
+    }
+
+    /// Returns the flags of the material of the specified block, unknown materials are solid.
+    pub fn block_flags(&self, block: Block) -> BlockMaterialFlags {
+        self.get_by_id(block.as_mat_id())
+            .map_or(BlockMaterialFlags::SOLID, |material| material.flags)
+    }
+
     pub fn get_by_type<M: 'static>(&self) -> Option<&MaterialRegistryInfo> {
         self.mat_by_typeid
             .get(&TypeId::of::<M>())
             .map(|id| self.materials.get(*id).unwrap())
     }
 
+    /// Returns the numeric ID of the material registered with the specified id string.
+    pub fn get_id_for_name(&self, id: &str) -> Option<u64> {
+        self.mat_by_id.get(id).map(|x| *x as u64)
+    }
+
     pub fn get_id_for_type<M: 'static>(&self) -> Option<u64> {
         self.mat_by_typeid
             .get(&TypeId::of::<M>())
@@ -211,14 +293,34 @@ impl BlockMaterialRegistry {
             );
         }
 
-        let numeric_id = self.materials.len();
-        let id: &'static str = Box::leak(id_string.clone().into_boxed_str());
+        if M::ID > MAX_MATERIAL_ID {
+            panic!(
+                "Material ID {} of {} is above {}.",
+                M::ID,
+                std::any::type_name::<M>(),
+                MAX_MATERIAL_ID
+            );
+        }
+
+        // Blocks store the material ID constant directly, so the registry has to be indexed by it
+        let numeric_id = M::ID as usize;
+        if self
+            .materials
+            .get(numeric_id)
+            .is_some_and(|material| !material.id.is_empty())
+        {
+            panic!(
+                "Material ID {} of {} is already in use.",
+                numeric_id,
+                std::any::type_name::<M>()
+            );
+        }
 
         let info = MaterialRegistryInfo {
-            id,
-            namespace: M::namespace(),
-            name: M::block_name(),
-            variant: M::variant(),
+            id: id_string.clone(),
+            namespace: M::namespace().to_string(),
+            name: M::block_name().to_string(),
+            variant: M::variant().map(str::to_string),
             base_color: M::base_color(),
             flags: M::flags(),
             emissive: M::emissive(),
@@ -227,8 +329,12 @@ impl BlockMaterialRegistry {
             reflectance: M::reflectance(),
         };
 
-        self.materials.push(info);
-        info!("Registered material {:?} (ID: {})", id, numeric_id);
+        if numeric_id >= self.materials.len() {
+            self.materials
+                .resize_with(numeric_id + 1, MaterialRegistryInfo::default);
+        }
+        self.materials[numeric_id] = info;
+        info!("Registered material {:?} (ID: {})", id_string, numeric_id);
         self.mat_by_id.insert(id_string, numeric_id);
         self.mat_by_typeid.insert(type_id, numeric_id);
     }
@@ -236,6 +342,82 @@ impl BlockMaterialRegistry {
     pub fn iter_materials(&self) -> impl IntoIterator<Item = &MaterialRegistryInfo> {
         self.materials.iter()
     }
+
+    /// Returns the definitions of every registered material.
+    pub fn definitions(&self) -> Vec<MaterialDefinition> {
+        self.materials
+            .iter()
+            .enumerate()
+            .filter(|(_, material)| !material.id.is_empty())
+            .map(|(numeric_id, material)| MaterialDefinition {
+                numeric_id: numeric_id as u64,
+                id: material.id.clone(),
+                flags: material.flags.bits(),
+                base_color: material.base_color.to_linear().to_f32_array(),
+                emissive: material.emissive.to_linear().to_f32_array(),
+                perceptual_roughness: material.perceptual_roughness,
+                metallic: material.metallic,
+                reflectance: material.reflectance,
+            })
+            .collect()
+    }
+
+    /// Replaces the registered materials with the definitions sent by a server.
+    ///
+    /// Material types registered locally stay usable if the server knows them under the same
+    /// numeric id, as their blocks are built from it. A local type the server registered under
+    /// another id makes the registries incompatible.
+    pub fn apply_definitions(
+        &mut self,
+        definitions: &[MaterialDefinition],
+    ) -> Result<(), GameError> {
+        for definition in definitions {
+            if definition.numeric_id > MAX_MATERIAL_ID {
+                return Err(ClientError::IncompatibleServer(format!(
+                    "material {} has the id {} which is above {}",
+                    definition.id, definition.numeric_id, MAX_MATERIAL_ID
+                ))
+                .into());
+            }
+
+            if let Some(local_id) = self
+                .get_id_for_name(&definition.id)
+                .filter(|local_id| *local_id != definition.numeric_id)
+            {
+                return Err(ClientError::IncompatibleServer(format!(
+                    "material {} has the id {} on the server but {} here",
+                    definition.id, definition.numeric_id, local_id
+                ))
+                .into());
+            }
+        }
+
+        let mut materials = Vec::new();
+        let mut mat_by_id = HashMap::default();
+
+        for definition in definitions {
+            let numeric_id = definition.numeric_id as usize;
+            if numeric_id >= materials.len() {
+                materials.resize_with(numeric_id + 1, MaterialRegistryInfo::default);
+            }
+
+            materials[numeric_id] = definition.to_info();
+            mat_by_id.insert(definition.id.clone(), numeric_id);
+        }
+
+        // Types of materials the server doesn't have can't be used anymore
+        self.mat_by_typeid.retain(|_, numeric_id| {
+            materials
+                .get(*numeric_id)
+                .is_some_and(|material| material.id == self.materials[*numeric_id].id)
+        });
+        self.materials = materials;
+        self.mat_by_id = mat_by_id;
+
+        info!("Synced {} materials from the server", definitions.len());
+
+        Ok(())
+    }
 }
 
 impl Default for BlockMaterialRegistry {
@@ -7,8 +7,17 @@ pub use blocks::*;
 pub mod chunk;
 pub use chunk::*;
 
+pub mod edit;
+pub use edit::*;
+
+pub mod light;
+pub use light::*;
+
 pub mod material;
 pub use material::*;
 
 pub mod terrain;
 pub use terrain::*;
+
+pub mod day_cycle;
+pub use day_cycle::*;
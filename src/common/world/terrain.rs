@@ -1,4 +1,4 @@
-use crate::{Block, BlockBuffer, Chunk, ChunkLoadingSet, ChunkMap, ChunkShape, DirtyChunks, TERRAIN_GENERATOR};
+use crate::{Block, BlockBuffer, Chunk, ChunkLoadingSet, ChunkMap, ChunkShape, DirtyChunks, RegionStorage, ResultExt, WorldSeed, TERRAIN_GENERATOR};
 use bevy::{
     prelude::*,
     tasks::{AsyncComputeTaskPool, Task},
@@ -8,22 +8,37 @@ use futures_lite::future;
 pub fn queue_terrain_gen(
     mut commands: Commands,
     new_chunks: Query<(Entity, &Chunk), Added<Chunk>>,
+    storage: Option<Res<RegionStorage>>,
+    seed: Res<WorldSeed>,
 ) {
     let task_pool = AsyncComputeTaskPool::get();
+    let seed = *seed;
 
     new_chunks
         .iter()
         .filter(|(_, key)| key.0.y < 288)
         .map(|(entity, key)| (entity, key.0))
         .map(|(entity, key)| {
+            let storage = storage.as_deref().cloned();
             (
                 entity,
                 (TerrainGenTask(task_pool.spawn(async move {
+                    // Chunks saved to disk take precedence over the terrain generator
+                    if let Some(chunk_data) = storage.and_then(|storage| {
+                        storage
+                            .load_chunk(key)
+                            .log_err_with("Failed to load chunk")
+                            .flatten()
+                    }) {
+                        return chunk_data;
+                    }
+
                     let mut chunk_data = BlockBuffer::<Block, ChunkShape>::new_empty(ChunkShape {});
                     TERRAIN_GENERATOR
                         .read()
                         .unwrap()
-                        .generate(key, &mut chunk_data);
+                        .generate(seed, key, &mut chunk_data);
+                    chunk_data.compact();
                     chunk_data
                 }))),
             )
@@ -33,16 +48,29 @@ pub fn queue_terrain_gen(
         });
 }
 
+/// Makes the frames queuing chunks wait for their generation, so that runs play out the same
+/// frame by frame. Meant for tests, generating chunks stalls the frames meanwhile.
+#[derive(Default, Resource)]
+pub struct BlockOnTerrainGen;
+
 pub fn process_terrain_gen(
     mut chunk_data: ResMut<ChunkMap<Block, ChunkShape>>,
     mut commands: Commands,
     mut dirty_chunks: ResMut<DirtyChunks>,
     mut gen_chunks: Query<(Entity, &Chunk, &mut TerrainGenTask)>,
+    block_on: Option<Res<BlockOnTerrainGen>>,
 ) {
     gen_chunks.iter_mut().for_each(|(entity, chunk, mut gen_task)| {
-        if let Some(data) = future::block_on(future::poll_once(&mut gen_task.0)) {
+        let generated = if block_on.is_some() {
+            Some(future::block_on(&mut gen_task.0))
+        } else {
+            future::block_on(future::poll_once(&mut gen_task.0))
+        };
+
+        if let Some(data) = generated {
             chunk_data.insert(chunk.0, data);
             dirty_chunks.mark_dirty(chunk.0);
+            dirty_chunks.mark_neighbours_dirty(chunk.0, &chunk_data);
             commands.entity(entity).remove::<TerrainGenTask>();
         }
     });
@@ -1,5 +1,8 @@
 pub mod common;
 pub use common::*;
 
+pub mod client;
+pub mod server;
+
 pub const CHUNK_SIZE: usize = 32;
 pub const CHUNK_HEIGHT: usize = 256;
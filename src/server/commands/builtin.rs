@@ -0,0 +1,190 @@
+use bevy::prelude::*;
+use bevy_renet::renet::RenetServer;
+use crate::{
+    BlockMaterialRegistry, Collider, DisplayName, GameMode, Lobby, MaterialBlock, NetworkMessage,
+    TimeOfDay, Velocity, WorldSeed, TIME_NAMES,
+};
+
+use crate::server::{
+    commands::{
+        ArgKind, CommandContext, CommandInvocation, CommandResult, CommandSender, CommandSpec,
+        Permission, RegisterCommand,
+    },
+    network::{broadcast, send_message, RejectedClients},
+};
+
+/// Registers the commands every server has.
+pub fn register_builtin_commands(app: &mut App) {
+    app.register_command(
+        CommandSpec::new("help", "Lists the commands you can run"),
+        help,
+    )
+    .register_command(
+        CommandSpec::new("seed", "Shows the seed the world was generated from"),
+        seed,
+    )
+    .register_command(
+        CommandSpec::new("tp", "Teleports a player to the feet position x y z")
+            .permission(Permission::Operator)
+            .arg("x", ArgKind::Number)
+            .arg("y", ArgKind::Number)
+            .arg("z", ArgKind::Number)
+            .optional_arg("player", ArgKind::Player),
+        teleport,
+    )
+    .register_command(
+        CommandSpec::new("give", "Puts a block in the hand of a player")
+            .permission(Permission::Operator)
+            .arg("material", ArgKind::Material)
+            .optional_arg("player", ArgKind::Player),
+        give,
+    )
+    .register_command(
+        CommandSpec::new("time", "Shows the time of the day, or sets it")
+            .permission(Permission::Operator)
+            .optional_arg("time", ArgKind::Choice(&TIME_NAMES)),
+        time,
+    )
+    .register_command(
+        CommandSpec::new("gamemode", "Changes what a player is allowed to do")
+            .permission(Permission::Operator)
+            .arg("mode", ArgKind::Choice(&GameMode::NAMES))
+            .optional_arg("player", ArgKind::Player),
+        game_mode,
+    )
+    .register_command(
+        CommandSpec::new("kick", "Disconnects a player from the server")
+            .permission(Permission::Operator)
+            .arg("player", ArgKind::Player)
+            .optional_arg("reason", ArgKind::Text),
+        kick,
+    );
+}
+
+fn help(In(invocation): In<CommandInvocation>, context: CommandContext) -> CommandResult {
+    Ok(context
+        .available(invocation.sender)
+        .map(|spec| format!("{} - {}", spec.usage(), spec.description))
+        .collect::<Vec<_>>()
+        .join("\n"))
+}
+
+fn seed(In(_): In<CommandInvocation>, seed: Res<WorldSeed>) -> CommandResult {
+    Ok(format!("Seed: {}", **seed))
+}
+
+fn teleport(
+    In(invocation): In<CommandInvocation>,
+    lobby: Res<Lobby>,
+    mut players: Query<(&mut Transform, &mut Velocity, &Collider, &DisplayName)>,
+) -> CommandResult {
+    let target = invocation.target(3)?;
+    let feet = Vec3::new(
+        invocation.number(0).unwrap_or_default(),
+        invocation.number(1).unwrap_or_default(),
+        invocation.number(2).unwrap_or_default(),
+    );
+
+    let (mut transform, mut velocity, collider, name) = lobby
+        .players
+        .get(&target)
+        .and_then(|player| players.get_mut(*player).ok())
+        .ok_or("The player left the game")?;
+
+    // The client picks up the new position when reconciling its prediction
+    transform.translation = feet + Vec3::Y * collider.eye_height;
+    velocity.0 = Vec3::ZERO;
+
+    Ok(format!(
+        "Teleported {} to {:.1} {:.1} {:.1}",
+        name.0, feet.x, feet.y, feet.z
+    ))
+}
+
+fn give(
+    In(invocation): In<CommandInvocation>,
+    lobby: Res<Lobby>,
+    names: Query<&DisplayName>,
+    registry: Res<BlockMaterialRegistry>,
+    mut server: ResMut<RenetServer>,
+) -> CommandResult {
+    let target = invocation.target(1)?;
+    let block = invocation.material(0).ok_or("Missing material")?;
+    let name = lobby
+        .players
+        .get(&target)
+        .and_then(|player| names.get(*player).ok())
+        .ok_or("The player left the game")?;
+
+    send_message(&mut server, target, &NetworkMessage::HeldBlock { block });
+
+    let material = registry
+        .get_by_id(block.as_mat_id())
+        .map_or("block", |material| material.name.as_str());
+    Ok(format!("Gave {material} to {}", name.0))
+}
+
+fn time(
+    In(invocation): In<CommandInvocation>,
+    mut time_of_day: ResMut<TimeOfDay>,
+    lobby: Res<Lobby>,
+    mut server: ResMut<RenetServer>,
+) -> CommandResult {
+    let Some(name) = invocation.choice(0) else {
+        return Ok(format!("It is {}", time_of_day.clock()));
+    };
+
+    *time_of_day = TimeOfDay::from_name(name).ok_or("Unknown time")?;
+    broadcast(
+        &mut server,
+        &lobby,
+        &NetworkMessage::SetTimeOfDay {
+            time: time_of_day.0,
+        },
+    );
+
+    Ok(format!("Set the time to {}", time_of_day.clock()))
+}
+
+fn game_mode(
+    In(invocation): In<CommandInvocation>,
+    lobby: Res<Lobby>,
+    mut players: Query<(&mut GameMode, &DisplayName)>,
+    mut server: ResMut<RenetServer>,
+) -> CommandResult {
+    let target = invocation.target(1)?;
+    let mode = invocation
+        .choice(0)
+        .and_then(GameMode::from_name)
+        .ok_or("Unknown game mode")?;
+
+    let (mut game_mode, name) = lobby
+        .players
+        .get(&target)
+        .and_then(|player| players.get_mut(*player).ok())
+        .ok_or("The player left the game")?;
+
+    *game_mode = mode;
+    send_message(&mut server, target, &NetworkMessage::GameModeChanged { mode });
+
+    Ok(format!("Set the game mode of {} to {}", name.0, mode.name()))
+}
+
+fn kick(
+    In(invocation): In<CommandInvocation>,
+    context: CommandContext,
+    mut server: ResMut<RenetServer>,
+    mut rejected: ResMut<RejectedClients>,
+    time: Res<Time>,
+) -> CommandResult {
+    let target = invocation.player(0).ok_or("Missing player")?;
+    let name = context.sender_name(CommandSender::Player(target));
+    let reason = invocation
+        .text(1)
+        .map_or_else(|| "kicked from the server".to_string(), str::to_string);
+
+    send_message(&mut server, target, &NetworkMessage::Disconnect { reason });
+    rejected.disconnect_later(target, time.elapsed());
+
+    Ok(format!("Kicked {name}"))
+}
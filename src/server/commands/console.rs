@@ -0,0 +1,48 @@
+use bevy::prelude::*;
+use std::{
+    io::BufRead,
+    sync::{
+        mpsc::{self, Receiver},
+        Mutex,
+    },
+};
+
+use crate::server::commands::{CommandSender, PendingCommands};
+
+/// Lines typed in the terminal the server runs in, read by a background thread.
+#[derive(Resource)]
+pub struct ConsoleInput(Mutex<Receiver<String>>);
+
+impl ConsoleInput {
+    /// Starts reading the standard input until it is closed.
+    pub fn spawn() -> Self {
+        let (sender, receiver) = mpsc::channel();
+
+        std::thread::spawn(move || {
+            for line in std::io::stdin().lock().lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Self(Mutex::new(receiver))
+    }
+}
+
+/// Queues the lines typed in the console as commands, the leading `/` can be left out there.
+pub fn read_console_commands(console: Res<ConsoleInput>, mut pending: ResMut<PendingCommands>) {
+    let Ok(receiver) = console.0.lock() else {
+        return;
+    };
+
+    for line in receiver.try_iter() {
+        if !line.trim().is_empty() {
+            pending.push(CommandSender::Console, line);
+        }
+    }
+}
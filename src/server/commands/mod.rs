@@ -0,0 +1,91 @@
+pub mod builtin;
+pub use builtin::*;
+
+pub mod console;
+pub use console::*;
+
+pub mod registry;
+pub use registry::*;
+
+use bevy::{ecs::system::SystemState, prelude::*};
+use bevy_renet::renet::RenetServer;
+use tracing::{info, warn};
+use crate::NetworkMessage;
+
+use crate::server::network::{handle_chat, send_message};
+
+/// Command lines waiting to be run, in the order they were received.
+#[derive(Resource, Default)]
+pub struct PendingCommands(Vec<(CommandSender, String)>);
+
+impl PendingCommands {
+    pub fn push(&mut self, sender: CommandSender, line: String) {
+        self.0.push((sender, line));
+    }
+}
+
+/// Runs the queued command lines and tells their senders how it went.
+pub fn run_pending_commands(world: &mut World, context: &mut SystemState<CommandContext>) {
+    let pending = std::mem::take(&mut world.resource_mut::<PendingCommands>().0);
+
+    for (sender, line) in pending {
+        let parsed = {
+            let context = context.get(world);
+            info!("{} ran {}", context.sender_name(sender), line.trim());
+            context.parse(sender, &line)
+        };
+
+        let result = parsed.and_then(|(handler, invocation)| {
+            world
+                .run_system_with(handler, invocation)
+                .unwrap_or_else(|e| Err(e.to_string()))
+        });
+
+        reply(world, sender, result);
+    }
+}
+
+fn reply(world: &mut World, sender: CommandSender, result: CommandResult) {
+    match sender {
+        CommandSender::Console => match result {
+            Ok(feedback) => info!("{feedback}"),
+            Err(error) => warn!("{error}"),
+        },
+        CommandSender::Player(client_id) => {
+            let text = result.unwrap_or_else(|error| error);
+
+            if let Some(mut server) = world.get_resource_mut::<RenetServer>() {
+                send_message(
+                    &mut server,
+                    client_id,
+                    &NetworkMessage::ChatMessage { sender: None, text },
+                );
+            }
+        }
+    }
+}
+
+/// Commands players run from the chat, and from the console once a [`ConsoleInput`] is inserted.
+pub struct CommandsPlugin {
+    /// Names of the players allowed to run the commands changing the game.
+    pub operators: Vec<String>,
+}
+
+impl Plugin for CommandsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Operators(self.operators.iter().cloned().collect()))
+            .init_resource::<CommandRegistry>()
+            .init_resource::<PendingCommands>()
+            .add_systems(
+                Update,
+                (
+                    read_console_commands.run_if(resource_exists::<ConsoleInput>),
+                    run_pending_commands,
+                )
+                    .chain()
+                    .after(handle_chat),
+            );
+
+        register_builtin_commands(app);
+    }
+}
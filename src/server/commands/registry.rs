@@ -0,0 +1,435 @@
+use bevy::{
+    ecs::system::{SystemId, SystemParam},
+    platform::collections::HashSet,
+    prelude::*,
+};
+use bevy_renet::renet::ClientId;
+use std::collections::BTreeMap;
+use crate::{Block, BlockMaterialRegistry, DisplayName, Lobby};
+
+use crate::server::network::Authenticated;
+
+/// Who runs a command.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CommandSender {
+    /// The terminal the server runs in.
+    Console,
+    Player(ClientId),
+}
+
+/// How trusted a sender has to be to run a command, from least to most trusted.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Permission {
+    Player,
+    /// Players listed in the operators of the server settings, who joined with a connect token.
+    Operator,
+    Console,
+}
+
+/// Type of the value expected for an argument.
+#[derive(Clone, Copy, Debug)]
+pub enum ArgKind {
+    Number,
+    /// The rest of the line, only makes sense for the last argument.
+    Text,
+    /// Name of a player in the game.
+    Player,
+    /// Id or name of a block material.
+    Material,
+    /// One of the listed words.
+    Choice(&'static [&'static str]),
+}
+
+#[derive(Clone, Debug)]
+pub struct ArgSpec {
+    pub name: &'static str,
+    pub kind: ArgKind,
+    pub optional: bool,
+}
+
+/// Value of an argument, parsed according to its [`ArgKind`].
+#[derive(Clone, Debug)]
+pub enum ArgValue {
+    Number(f32),
+    Text(String),
+    Player(ClientId),
+    Material(Block),
+    Choice(&'static str),
+}
+
+/// A command to run along with its parsed arguments, optional arguments which weren't given are
+/// left out.
+pub struct CommandInvocation {
+    pub sender: CommandSender,
+    pub args: Vec<ArgValue>,
+}
+
+impl CommandInvocation {
+    pub fn number(&self, index: usize) -> Option<f32> {
+        match self.args.get(index) {
+            Some(ArgValue::Number(value)) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn text(&self, index: usize) -> Option<&str> {
+        match self.args.get(index) {
+            Some(ArgValue::Text(text)) => Some(text.as_str()),
+            _ => None,
+        }
+    }
+
+    pub fn player(&self, index: usize) -> Option<ClientId> {
+        match self.args.get(index) {
+            Some(ArgValue::Player(client_id)) => Some(*client_id),
+            _ => None,
+        }
+    }
+
+    pub fn material(&self, index: usize) -> Option<Block> {
+        match self.args.get(index) {
+            Some(ArgValue::Material(block)) => Some(*block),
+            _ => None,
+        }
+    }
+
+    pub fn choice(&self, index: usize) -> Option<&'static str> {
+        match self.args.get(index) {
+            Some(ArgValue::Choice(choice)) => Some(*choice),
+            _ => None,
+        }
+    }
+
+    /// Returns the player argument at `index`, or the sender itself if it is a player and the
+    /// argument wasn't given.
+    pub fn target(&self, index: usize) -> Result<ClientId, String> {
+        match (self.player(index), self.sender) {
+            (Some(client_id), _) | (None, CommandSender::Player(client_id)) => Ok(client_id),
+            (None, CommandSender::Console) => Err("The console has to name a player".to_string()),
+        }
+    }
+}
+
+/// Feedback shown to the sender when the command succeeded, or why it failed.
+pub type CommandResult = Result<String, String>;
+
+/// System run when a command is invoked.
+pub type CommandHandler = SystemId<In<CommandInvocation>, CommandResult>;
+
+/// Name, arguments and permission of a command.
+#[derive(Clone, Debug)]
+pub struct CommandSpec {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub permission: Permission,
+    pub args: Vec<ArgSpec>,
+}
+
+impl CommandSpec {
+    /// A command anyone can run, without arguments.
+    pub fn new(name: &'static str, description: &'static str) -> Self {
+        Self {
+            name,
+            description,
+            permission: Permission::Player,
+            args: Vec::new(),
+        }
+    }
+
+    pub fn permission(mut self, permission: Permission) -> Self {
+        self.permission = permission;
+        self
+    }
+
+    pub fn arg(mut self, name: &'static str, kind: ArgKind) -> Self {
+        self.args.push(ArgSpec {
+            name,
+            kind,
+            optional: false,
+        });
+        self
+    }
+
+    /// Adds an argument which can be left out, it must come after the required ones.
+    pub fn optional_arg(mut self, name: &'static str, kind: ArgKind) -> Self {
+        self.args.push(ArgSpec {
+            name,
+            kind,
+            optional: true,
+        });
+        self
+    }
+
+    pub fn usage(&self) -> String {
+        self.args.iter().fold(format!("/{}", self.name), |usage, arg| {
+            if arg.optional {
+                format!("{usage} [{}]", arg.name)
+            } else {
+                format!("{usage} <{}>", arg.name)
+            }
+        })
+    }
+}
+
+struct RegisteredCommand {
+    spec: CommandSpec,
+    handler: CommandHandler,
+}
+
+/// Commands which can be run from the chat or the console, plugins add theirs with
+/// [`RegisterCommand::register_command`].
+#[derive(Resource, Default)]
+pub struct CommandRegistry {
+    commands: BTreeMap<&'static str, RegisteredCommand>,
+}
+
+impl CommandRegistry {
+    /// Returns the commands in alphabetical order.
+    pub fn iter(&self) -> impl Iterator<Item = &CommandSpec> {
+        self.commands.values().map(|command| &command.spec)
+    }
+}
+
+pub trait RegisterCommand {
+    /// Registers a command, `handler` is a system run with the parsed arguments whenever the
+    /// command is invoked.
+    fn register_command<M>(
+        &mut self,
+        spec: CommandSpec,
+        handler: impl IntoSystem<In<CommandInvocation>, CommandResult, M> + 'static,
+    ) -> &mut Self;
+}
+
+impl RegisterCommand for App {
+    fn register_command<M>(
+        &mut self,
+        spec: CommandSpec,
+        handler: impl IntoSystem<In<CommandInvocation>, CommandResult, M> + 'static,
+    ) -> &mut Self {
+        let handler = self.world_mut().register_system(handler);
+        let mut registry = self.world_mut().get_resource_or_init::<CommandRegistry>();
+
+        if registry.commands.contains_key(spec.name) {
+            panic!("A command named '{}' has already been registered.", spec.name);
+        }
+
+        registry
+            .commands
+            .insert(spec.name, RegisteredCommand { spec, handler });
+        self
+    }
+}
+
+/// Names of the players allowed to run operator commands once [`Authenticated`].
+#[derive(Resource, Default)]
+pub struct Operators(pub HashSet<String>);
+
+/// Everything needed to check, parse and complete the commands of a sender.
+#[derive(SystemParam)]
+pub struct CommandContext<'w, 's> {
+    registry: Res<'w, CommandRegistry>,
+    operators: Res<'w, Operators>,
+    lobby: Res<'w, Lobby>,
+    names: Query<'w, 's, (&'static DisplayName, Has<Authenticated>)>,
+    materials: Res<'w, BlockMaterialRegistry>,
+}
+
+impl CommandContext<'_, '_> {
+    pub fn player_name(&self, client_id: ClientId) -> Option<&str> {
+        self.lobby
+            .players
+            .get(&client_id)
+            .and_then(|player| self.names.get(*player).ok())
+            .map(|(name, _)| name.0.as_str())
+    }
+
+    pub fn sender_name(&self, sender: CommandSender) -> String {
+        match sender {
+            CommandSender::Console => "the console".to_string(),
+            CommandSender::Player(client_id) => self
+                .player_name(client_id)
+                .map_or_else(|| format!("player-{client_id}"), str::to_string),
+        }
+    }
+
+    pub fn permission(&self, sender: CommandSender) -> Permission {
+        match sender {
+            CommandSender::Console => Permission::Console,
+            // Anyone can pick the name of an operator when the server isn't in secure mode
+            CommandSender::Player(client_id) => self
+                .lobby
+                .players
+                .get(&client_id)
+                .and_then(|player| self.names.get(*player).ok())
+                .filter(|(name, authenticated)| {
+                    *authenticated && self.operators.0.contains(&name.0)
+                })
+                .map_or(Permission::Player, |_| Permission::Operator),
+        }
+    }
+
+    /// Returns the commands the sender is allowed to run, in alphabetical order.
+    pub fn available(&self, sender: CommandSender) -> impl Iterator<Item = &CommandSpec> {
+        let permission = self.permission(sender);
+        self.registry
+            .iter()
+            .filter(move |spec| spec.permission <= permission)
+    }
+
+    /// Finds the command of a line and parses its arguments, the leading `/` is optional.
+    pub fn parse(
+        &self,
+        sender: CommandSender,
+        line: &str,
+    ) -> Result<(CommandHandler, CommandInvocation), String> {
+        let mut words = line.trim().trim_start_matches('/').split_whitespace();
+        let name = words.next().unwrap_or_default();
+
+        // Commands the sender can't run are hidden rather than refused
+        let Some(command) = self
+            .registry
+            .commands
+            .get(name)
+            .filter(|command| command.spec.permission <= self.permission(sender))
+        else {
+            return Err(format!("Unknown command /{name}, try /help"));
+        };
+
+        let words: Vec<&str> = words.collect();
+        let usage_error = |error: String| format!("{error}, usage: {}", command.spec.usage());
+        let mut args = Vec::new();
+
+        for (index, arg) in command.spec.args.iter().enumerate() {
+            let Some(word) = words.get(index) else {
+                if arg.optional {
+                    break;
+                }
+                return Err(usage_error(format!("Missing <{}>", arg.name)));
+            };
+
+            let value = match arg.kind {
+                ArgKind::Text => ArgValue::Text(words[index..].join(" ")),
+                kind => self.parse_arg(kind, word).map_err(usage_error)?,
+            };
+            args.push(value);
+        }
+
+        let takes_rest = command
+            .spec
+            .args
+            .last()
+            .is_some_and(|arg| matches!(arg.kind, ArgKind::Text));
+        if !takes_rest && words.len() > command.spec.args.len() {
+            return Err(usage_error("Too many arguments".to_string()));
+        }
+
+        Ok((command.handler, CommandInvocation { sender, args }))
+    }
+
+    fn parse_arg(&self, kind: ArgKind, word: &str) -> Result<ArgValue, String> {
+        match kind {
+            ArgKind::Number => word
+                .parse::<f32>()
+                .ok()
+                .filter(|value| value.is_finite())
+                .map(ArgValue::Number)
+                .ok_or_else(|| format!("'{word}' isn't a number")),
+            ArgKind::Text => Ok(ArgValue::Text(word.to_string())),
+            ArgKind::Player => self
+                .lobby
+                .players
+                .keys()
+                .find(|client_id| self.player_name(**client_id) == Some(word))
+                .map(|client_id| ArgValue::Player(*client_id))
+                .ok_or_else(|| format!("No player named '{word}'")),
+            ArgKind::Material => self
+                .materials
+                .get_id_for_name(word)
+                .or_else(|| {
+                    self.material_names()
+                        .find(|(_, name)| name == word)
+                        .map(|(id, _)| id)
+                })
+                .and_then(|id| self.materials.block_for_id(id))
+                .map(ArgValue::Material)
+                .ok_or_else(|| format!("No material named '{word}'")),
+            ArgKind::Choice(choices) => choices
+                .iter()
+                .copied()
+                .find(|choice| *choice == word)
+                .map(ArgValue::Choice)
+                .ok_or_else(|| format!("Expected one of {}", choices.join(", "))),
+        }
+    }
+
+    /// Returns the numeric ids and names of the materials blocks can be made of, the materials
+    /// sharing their name with others are named `name:variant`.
+    fn material_names(&self) -> impl Iterator<Item = (u64, String)> + '_ {
+        self.materials
+            .iter_materials()
+            .into_iter()
+            .enumerate()
+            .filter(|(id, material)| *id != 0 && !material.id.is_empty())
+            .map(|(id, material)| {
+                let name = match &material.variant {
+                    Some(variant) => format!("{}:{variant}", material.name),
+                    None => material.name.clone(),
+                };
+                (id as u64, name)
+            })
+    }
+
+    /// Returns the words which can complete the last word of a command line.
+    pub fn complete(&self, sender: CommandSender, input: &str) -> Vec<String> {
+        let words: Vec<&str> = input.trim_start_matches('/').split(' ').collect();
+        let Some((last, previous)) = words.split_last() else {
+            return Vec::new();
+        };
+
+        let candidates: Vec<String> = match previous.split_first() {
+            None => self
+                .available(sender)
+                .map(|spec| format!("/{}", spec.name))
+                .collect(),
+            Some((name, args)) => {
+                let kind = self
+                    .available(sender)
+                    .find(|spec| spec.name == *name)
+                    .and_then(|spec| spec.args.get(args.len()))
+                    .map(|arg| arg.kind);
+
+                match kind {
+                    Some(ArgKind::Player) => self
+                        .lobby
+                        .players
+                        .keys()
+                        .filter_map(|client_id| self.player_name(*client_id))
+                        .map(str::to_string)
+                        .collect(),
+                    Some(ArgKind::Material) => {
+                        self.material_names().map(|(_, name)| name).collect()
+                    }
+                    Some(ArgKind::Choice(choices)) => {
+                        choices.iter().map(|choice| choice.to_string()).collect()
+                    }
+                    _ => Vec::new(),
+                }
+            }
+        };
+
+        // Command names are suggested with their slash
+        let prefix = if previous.is_empty() {
+            format!("/{last}")
+        } else {
+            last.to_string()
+        };
+
+        let mut suggestions: Vec<String> = candidates
+            .into_iter()
+            .filter(|candidate| candidate.starts_with(&prefix))
+            .collect();
+        suggestions.sort();
+        suggestions.dedup();
+        suggestions
+    }
+}
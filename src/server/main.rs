@@ -1,5 +1,61 @@
-mod world;
+use bevy::{app::ScheduleRunnerPlugin, prelude::*};
+use std::time::Duration;
+use tracing::{error, warn};
+use voxel_engine::{
+    server::{commands, network, world},
+    *,
+};
+
+mod shutdown;
+
+/// Rate at which the main loop polls the network, in frames per second.
+const FRAME_RATE: f64 = 60.0;
 
 fn main() {
-    
-}
\ No newline at end of file
+    let _guard = setup_file_logging();
+    let mut settings = ServerSettings::load()
+        .log_err_with("Failed to load server settings")
+        .unwrap_or_default();
+
+    // A port passed on the command line wins over the settings
+    if let Some(port) = std::env::args().nth(1).and_then(|port| port.parse().ok()) {
+        settings.port = port;
+    }
+
+    // Falling back to unsecure mode would let anyone in, refuse to start instead
+    let private_key = match settings.private_key() {
+        Ok(private_key) => private_key,
+        Err(e) => {
+            error!("Invalid private key in the server settings: {e}");
+            return;
+        }
+    };
+
+    if private_key.is_none() && !settings.operators.is_empty() {
+        warn!("Operators can only run their commands once the server is in secure mode");
+    }
+
+    App::new()
+        .add_plugins(MinimalPlugins.set(ScheduleRunnerPlugin::run_loop(
+            Duration::from_secs_f64(1. / FRAME_RATE),
+        )))
+        .add_plugins(bevy::transform::TransformPlugin)
+        .insert_resource(Time::<Fixed>::from_hz(TICK_RATE))
+        .add_plugins(world::ServerWorldPlugin {
+            seed: settings.seed,
+            world_dir: Some(DEFAULT_WORLD_DIR.into()),
+        })
+        .add_plugins(network::ServerNetcodePlugin {
+            port: settings.port,
+            public_addr: settings.public_addr(),
+            max_clients: settings.max_clients,
+            private_key,
+        })
+        .add_plugins(network::ServerNetworkPlugin)
+        .add_plugins(commands::CommandsPlugin {
+            operators: settings.operators,
+        })
+        .insert_resource(commands::ConsoleInput::spawn())
+        .add_plugins(shutdown::ShutdownPlugin)
+        .run();
+}
@@ -0,0 +1,85 @@
+use bevy::prelude::*;
+use bevy_renet::renet::{ClientId, RenetServer};
+use tracing::info;
+use crate::{DisplayName, Lobby, NetworkMessage, MAX_CHAT_LENGTH};
+
+use crate::server::{
+    commands::{CommandContext, CommandSender, PendingCommands},
+    network::{broadcast, send_message},
+};
+
+/// A line typed by a player.
+#[derive(Event, Clone)]
+pub struct ChatReceived {
+    pub client_id: ClientId,
+    pub text: String,
+}
+
+/// A player asked how to complete a command line.
+#[derive(Event, Clone)]
+pub struct CompletionRequest {
+    pub client_id: ClientId,
+    pub input: String,
+}
+
+/// Shares the lines of the players with everyone in the game, and queues their commands.
+pub fn handle_chat(
+    mut received: EventReader<ChatReceived>,
+    mut server: ResMut<RenetServer>,
+    lobby: Res<Lobby>,
+    names: Query<&DisplayName>,
+    mut pending: ResMut<PendingCommands>,
+) {
+    for ChatReceived { client_id, text } in received.read() {
+        // Clients which didn't log in yet have no say
+        let Some(name) = lobby
+            .players
+            .get(client_id)
+            .and_then(|player| names.get(*player).ok())
+        else {
+            continue;
+        };
+
+        let text: String = text.trim().chars().take(MAX_CHAT_LENGTH).collect();
+        if text.is_empty() {
+            continue;
+        }
+
+        if text.starts_with('/') {
+            pending.push(CommandSender::Player(*client_id), text);
+            continue;
+        }
+
+        info!("<{}> {text}", name.0);
+        broadcast(
+            &mut server,
+            &lobby,
+            &NetworkMessage::ChatMessage {
+                sender: Some(name.0.clone()),
+                text,
+            },
+        );
+    }
+}
+
+pub fn answer_completion_requests(
+    mut requests: EventReader<CompletionRequest>,
+    mut server: ResMut<RenetServer>,
+    context: CommandContext,
+) {
+    for CompletionRequest { client_id, input } in requests.read() {
+        if context.player_name(*client_id).is_none() {
+            continue;
+        }
+
+        let suggestions = context.complete(CommandSender::Player(*client_id), input);
+        send_message(
+            &mut server,
+            *client_id,
+            &NetworkMessage::Completions {
+                input: input.clone(),
+                suggestions,
+            },
+        );
+    }
+}
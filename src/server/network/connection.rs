@@ -0,0 +1,264 @@
+use bevy::prelude::*;
+use bevy_renet::{
+    netcode::{NetcodeServerPlugin, NetcodeServerTransport},
+    renet::{ClientId, RenetServer, ServerEvent},
+    RenetServerPlugin,
+};
+use std::net::{Ipv4Addr, SocketAddr, UdpSocket};
+use tracing::{info, warn};
+use crate::{
+    create_dedicated_server, Block, BlockMaterialRegistry, Collider, Lobby, NetworkConfig,
+    NetworkMessage, Player, PrivateKey, ResultExt, WorldEditSet, WorldEdits, RELIABLE_CHANNEL,
+    UNRELIABLE_CHANNEL,
+};
+
+use crate::server::network::{
+    answer_completion_requests, apply_edit_request, broadcast_block_changes,
+    broadcast_player_snapshots, disconnect_rejected_clients, handle_chat, handle_login_requests,
+    simulate_player_inputs, stream_chunks, ChatReceived, CompletionRequest, InputQueue,
+    LoginRequest, RejectedClients, ServerTick,
+};
+
+/// Accepts clients over UDP through netcode.
+pub struct ServerNetcodePlugin {
+    pub port: u16,
+    /// Address the connect tokens of clients have to be issued for.
+    pub public_addr: SocketAddr,
+    pub max_clients: usize,
+    /// Key the connect tokens of clients must be signed with, anyone can join without one.
+    pub private_key: Option<PrivateKey>,
+}
+
+/// Clients can only join with a connect token signed with the private key of the server, the
+/// usernames they carry can then be trusted.
+#[derive(Resource)]
+pub struct SecureMode;
+
+impl Plugin for ServerNetcodePlugin {
+    fn build(&self, app: &mut App) {
+        let bind_addr = SocketAddr::from((Ipv4Addr::UNSPECIFIED, self.port));
+        let socket = UdpSocket::bind(bind_addr).unwrap();
+        let (server, transport) = create_dedicated_server(
+            socket,
+            self.public_addr,
+            self.max_clients,
+            self.private_key,
+            NetworkConfig::default().connection_config(),
+        );
+
+        info!("Listening on port {}, reachable at {}", self.port, self.public_addr);
+        if self.private_key.is_some() {
+            info!("Secure mode is on, clients need a connect token to join");
+            app.insert_resource(SecureMode);
+        }
+
+        app.add_plugins(NetcodeServerPlugin)
+            .insert_resource(server)
+            .insert_resource(transport)
+            .add_systems(Last, disconnect_clients_on_exit);
+    }
+}
+
+/// Game side of the server networking, a transport plugin provides the [`RenetServer`].
+pub struct ServerNetworkPlugin;
+
+impl Plugin for ServerNetworkPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(RenetServerPlugin)
+            .insert_resource(NetworkConfig::default())
+            .init_resource::<Lobby>()
+            .init_resource::<ServerTick>()
+            .init_resource::<RejectedClients>()
+            .add_event::<LoginRequest>()
+            .add_event::<ChatReceived>()
+            .add_event::<CompletionRequest>()
+            .add_systems(
+                Update,
+                (
+                    handle_server_events,
+                    receive_client_messages,
+                    handle_login_requests,
+                )
+                    .chain()
+                    .in_set(WorldEditSet),
+            )
+            .add_systems(Update, disconnect_rejected_clients)
+            .add_systems(
+                Update,
+                (handle_chat, answer_completion_requests).after(receive_client_messages),
+            )
+            .add_systems(Update, broadcast_block_changes.after(WorldEditSet))
+            .add_systems(
+                FixedUpdate,
+                (
+                    simulate_player_inputs,
+                    broadcast_player_snapshots,
+                    stream_chunks,
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// Sends a message to every client in the game on the reliable channel.
+pub fn broadcast(server: &mut RenetServer, lobby: &Lobby, message: &NetworkMessage) {
+    if let Some(bytes) = message
+        .to_bytes()
+        .log_err_with("Failed to encode network message")
+    {
+        for client_id in lobby.players.keys() {
+            server.send_message(*client_id, RELIABLE_CHANNEL, bytes.clone());
+        }
+    }
+}
+
+/// Sends a message to a single client on the reliable channel.
+pub fn send_message(server: &mut RenetServer, client_id: ClientId, message: &NetworkMessage) {
+    if let Some(bytes) = message
+        .to_bytes()
+        .log_err_with("Failed to encode network message")
+    {
+        server.send_message(client_id, RELIABLE_CHANNEL, bytes);
+    }
+}
+
+pub fn handle_server_events(
+    mut events: EventReader<ServerEvent>,
+    mut server: ResMut<RenetServer>,
+    mut lobby: ResMut<Lobby>,
+    mut commands: Commands,
+) {
+    for event in events.read() {
+        match event {
+            ServerEvent::ClientConnected { client_id } => {
+                // The player only joins the game once the client logged in
+                info!("Client {client_id} connected");
+            }
+            ServerEvent::ClientDisconnected { client_id, reason } => {
+                info!("Client {client_id} disconnected: {reason}");
+
+                if let Some(player) = lobby.players.remove(client_id) {
+                    commands.entity(player).despawn();
+                    broadcast(
+                        &mut server,
+                        &lobby,
+                        &NetworkMessage::PlayerDisconnected { id: *client_id },
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Returns the eye position of the player controlled by a client.
+fn player_eye(
+    lobby: &Lobby,
+    players: &Query<(&Transform, &mut InputQueue, &Collider), With<Player>>,
+    client_id: ClientId,
+) -> Option<Vec3> {
+    lobby
+        .players
+        .get(&client_id)
+        .and_then(|player| players.get(*player).ok())
+        .map(|(transform, _, _)| transform.translation)
+}
+
+pub fn receive_client_messages(
+    mut server: ResMut<RenetServer>,
+    lobby: Res<Lobby>,
+    mut players: Query<(&Transform, &mut InputQueue, &Collider), With<Player>>,
+    registry: Res<BlockMaterialRegistry>,
+    mut edits: WorldEdits,
+    mut logins: EventWriter<LoginRequest>,
+    mut chat: EventWriter<ChatReceived>,
+    mut completion_requests: EventWriter<CompletionRequest>,
+) {
+    for client_id in server.clients_id() {
+        for channel in [RELIABLE_CHANNEL, UNRELIABLE_CHANNEL] {
+            while let Some(bytes) = server.receive_message(client_id, channel) {
+                let Some(message) = NetworkMessage::from_bytes(&bytes)
+                    .log_err_with("Received an invalid message")
+                else {
+                    continue;
+                };
+
+                match message {
+                    NetworkMessage::Login { protocol_version } => {
+                        logins.write(LoginRequest {
+                            client_id,
+                            protocol_version,
+                        });
+                    }
+                    NetworkMessage::PlayerInputs(inputs) => {
+                        if let Some((_, mut queue, _)) = lobby
+                            .players
+                            .get(&client_id)
+                            .and_then(|player| players.get_mut(*player).ok())
+                        {
+                            queue.extend(inputs);
+                        }
+                    }
+                    NetworkMessage::BreakBlock { request, pos } => {
+                        let eye = player_eye(&lobby, &players, client_id);
+                        let accepted = eye.is_some_and(|eye| {
+                            let block = Block::EMPTY_BLOCK;
+                            apply_edit_request(&mut edits, &registry, &[], eye, pos, block)
+                        });
+
+                        send_message(
+                            &mut server,
+                            client_id,
+                            &NetworkMessage::EditResult { request, accepted },
+                        );
+                    }
+                    NetworkMessage::PlaceBlock {
+                        request,
+                        pos,
+                        block,
+                    } => {
+                        let eye = player_eye(&lobby, &players, client_id);
+                        let bodies: Vec<(Vec3, Collider)> = players
+                            .iter()
+                            .map(|(transform, _, collider)| {
+                                let feet = transform.translation - Vec3::Y * collider.eye_height;
+                                (feet, *collider)
+                            })
+                            .collect();
+
+                        let accepted = !block.is_empty()
+                            && eye.is_some_and(|eye| {
+                                apply_edit_request(&mut edits, &registry, &bodies, eye, pos, block)
+                            });
+
+                        send_message(
+                            &mut server,
+                            client_id,
+                            &NetworkMessage::EditResult { request, accepted },
+                        );
+                    }
+                    NetworkMessage::Chat { text } => {
+                        chat.write(ChatReceived { client_id, text });
+                    }
+                    NetworkMessage::CompleteCommand { input } => {
+                        completion_requests.write(CompletionRequest { client_id, input });
+                    }
+                    message => warn!("Unexpected message from client {client_id}: {message:?}"),
+                }
+            }
+        }
+    }
+}
+
+/// Tells every client the server is going away before the app exits.
+pub fn disconnect_clients_on_exit(
+    mut exit_events: EventReader<AppExit>,
+    mut server: ResMut<RenetServer>,
+    mut transport: ResMut<NetcodeServerTransport>,
+) {
+    if exit_events.read().last().is_none() {
+        return;
+    }
+
+    info!("Disconnecting {} clients", server.connected_clients());
+    transport.disconnect_all(&mut server);
+}
@@ -0,0 +1,95 @@
+use bevy::{platform::collections::HashMap, prelude::*};
+use bevy_renet::renet::RenetServer;
+use crate::{
+    is_breakable, is_placeable, is_replaceable, Block, BlockChanged, BlockMaterialRegistry,
+    ChunkMap, ChunkShape, Collider, MaterialBlock, NetworkMessage, WorldEdits, PLAYER_REACH,
+};
+
+use crate::server::{
+    network::{send_message, StreamedChunks},
+    world::ConnectedClient,
+};
+
+/// Extra reach granted to clients to make up for the latency of their position updates.
+const REACH_TOLERANCE: f32 = 1.5;
+
+/// Validates a block edit requested by a player whose eyes are at `eye` and applies it, returns
+/// whether it was applied.
+///
+/// Edits are refused out of reach, in unloaded chunks, when breaking or placing unbreakable
+/// blocks, when placing over blocks that can't be replaced or when placing inside a player,
+/// `bodies` holds the feet and collider of every player.
+pub fn apply_edit_request(
+    edits: &mut WorldEdits,
+    registry: &BlockMaterialRegistry,
+    bodies: &[(Vec3, Collider)],
+    eye: Vec3,
+    pos: IVec3,
+    block: Block,
+) -> bool {
+    if (pos.as_vec3() + Vec3::splat(0.5)).distance(eye) > PLAYER_REACH + REACH_TOLERANCE {
+        return false;
+    }
+
+    if !block.is_empty()
+        && bodies
+            .iter()
+            .any(|(feet, collider)| collider.overlaps_block(*feet, pos))
+    {
+        return false;
+    }
+
+    // Placed blocks are rebuilt from the registry so clients can't forge the transparency flag
+    let block = if block.is_empty() {
+        Some(block)
+    } else {
+        registry
+            .block_for_id(block.as_mat_id())
+            .filter(|block| is_placeable(registry, *block))
+    };
+
+    let valid = block.is_some_and(|block| {
+        edits.block_at(pos).is_some_and(|current| {
+            if block.is_empty() {
+                is_breakable(registry, current)
+            } else {
+                is_replaceable(registry, current)
+            }
+        })
+    });
+
+    valid && block.is_some_and(|block| edits.set_block(pos, block).is_some())
+}
+
+/// Sends the blocks changed during the frame to every client having their chunk loaded, batched
+/// into a single message per client.
+pub fn broadcast_block_changes(
+    mut block_changes: EventReader<BlockChanged>,
+    clients: Query<(&ConnectedClient, &StreamedChunks)>,
+    chunks: Res<ChunkMap<Block, ChunkShape>>,
+    mut server: ResMut<RenetServer>,
+) {
+    let mut changes_by_chunk: HashMap<IVec3, Vec<(IVec3, Block)>> = HashMap::default();
+    for change in block_changes.read() {
+        changes_by_chunk
+            .entry(chunks.chunk_key(change.pos))
+            .or_default()
+            .push((change.pos, change.new));
+    }
+
+    if changes_by_chunk.is_empty() {
+        return;
+    }
+
+    for (client, streamed) in clients.iter() {
+        let changes: Vec<(IVec3, Block)> = changes_by_chunk
+            .iter()
+            .filter(|(key, _)| streamed.contains(**key))
+            .flat_map(|(_, changes)| changes.iter().copied())
+            .collect();
+
+        if !changes.is_empty() {
+            send_message(&mut server, **client, &NetworkMessage::BlockChanges(changes));
+        }
+    }
+}
@@ -0,0 +1,162 @@
+use bevy::prelude::*;
+use bevy_renet::{
+    netcode::NetcodeServerTransport,
+    renet::{ClientId, RenetServer},
+};
+use std::time::Duration;
+use tracing::info;
+use crate::{
+    decode_username, BlockMaterialRegistry, Collider, DisplayName, GameMode, HeadRotation,
+    Health, IsOnGround, Lobby, MovementMode, NetworkMessage, Player, PlayerBundle, TimeOfDay,
+    Velocity, WorldSeed, PROTOCOL_VERSION,
+};
+
+use crate::server::{
+    network::{broadcast, send_message, InputQueue, SecureMode, StreamedChunks},
+    world::{ConnectedClient, ServerPlayerBundle},
+};
+
+/// Where players appear when they join the server.
+const SPAWN_POSITION: Vec3 = Vec3::new(2.0, 160.0, 2.0);
+
+/// Time given to a rejected client to receive the reason before it is disconnected.
+const REJECTION_GRACE: Duration = Duration::from_secs(1);
+
+/// The name of the player was read from a connect token signed by the server, it can be trusted
+/// with permissions.
+#[derive(Component)]
+pub struct Authenticated;
+
+/// A client asked to join the game.
+#[derive(Event, Clone, Copy)]
+pub struct LoginRequest {
+    pub client_id: ClientId,
+    pub protocol_version: u32,
+}
+
+/// Clients told why they can't join, along with when to disconnect them.
+#[derive(Resource, Default)]
+pub struct RejectedClients(Vec<(ClientId, Duration)>);
+
+impl RejectedClients {
+    /// Disconnects a client which was just sent the reason, once it had time to receive it.
+    pub fn disconnect_later(&mut self, client_id: ClientId, now: Duration) {
+        self.0.push((client_id, now + REJECTION_GRACE));
+    }
+}
+
+/// Lets compatible clients in and spawns their player, the others are told why they can't join.
+pub fn handle_login_requests(
+    mut requests: EventReader<LoginRequest>,
+    mut server: ResMut<RenetServer>,
+    mut lobby: ResMut<Lobby>,
+    mut rejected: ResMut<RejectedClients>,
+    transport: Option<Res<NetcodeServerTransport>>,
+    secure: Option<Res<SecureMode>>,
+    registry: Res<BlockMaterialRegistry>,
+    seed: Res<WorldSeed>,
+    time_of_day: Res<TimeOfDay>,
+    time: Res<Time>,
+    mut commands: Commands,
+) {
+    for LoginRequest {
+        client_id,
+        protocol_version,
+    } in requests.read().copied()
+    {
+        if lobby.players.contains_key(&client_id) {
+            continue;
+        }
+
+        if protocol_version != PROTOCOL_VERSION {
+            let reason = format!(
+                "the server runs protocol version {PROTOCOL_VERSION} but the client runs version \
+                 {protocol_version}"
+            );
+            info!("Rejected client {client_id}: {reason}");
+
+            send_message(&mut server, client_id, &NetworkMessage::Disconnect { reason });
+            rejected.disconnect_later(client_id, time.elapsed());
+            continue;
+        }
+
+        // Unsecure clients could claim any username, they get a generated one instead
+        let username = transport
+            .as_ref()
+            .filter(|_| secure.is_some())
+            .and_then(|transport| transport.user_data(client_id))
+            .and_then(|user_data| decode_username(&user_data));
+        let authenticated = username.is_some();
+        let name = username.unwrap_or_else(|| format!("player-{client_id}"));
+
+        info!("Client {client_id} logged in as {name}");
+
+        send_message(
+            &mut server,
+            client_id,
+            &NetworkMessage::LoginAccepted {
+                protocol_version: PROTOCOL_VERSION,
+                seed: **seed,
+                materials: registry.definitions(),
+            },
+        );
+        send_message(
+            &mut server,
+            client_id,
+            &NetworkMessage::SetTimeOfDay {
+                time: time_of_day.0,
+            },
+        );
+
+        let player = commands
+            .spawn(ServerPlayerBundle {
+                shared: PlayerBundle {
+                    player: Player,
+                    name: DisplayName(name),
+                    health: Health::new(20),
+                    transform: Transform::from_translation(SPAWN_POSITION),
+                    global_transform: GlobalTransform::default(),
+                },
+                is_on_ground: IsOnGround(false),
+                velocity: Velocity::default(),
+                collider: Collider::default(),
+                movement_mode: MovementMode::default(),
+                game_mode: GameMode::default(),
+                head_rotation: HeadRotation::default(),
+                client: ConnectedClient(client_id),
+                streamed_chunks: StreamedChunks::default(),
+                input_queue: InputQueue::default(),
+            })
+            .id();
+
+        if authenticated {
+            commands.entity(player).insert(Authenticated);
+        }
+
+        broadcast(&mut server, &lobby, &NetworkMessage::PlayerConnected { id: client_id });
+
+        // The newcomer also has to learn about the players who joined before it
+        for id in lobby.players.keys() {
+            send_message(&mut server, client_id, &NetworkMessage::PlayerConnected { id: *id });
+        }
+
+        lobby.players.insert(client_id, player);
+    }
+}
+
+pub fn disconnect_rejected_clients(
+    mut server: ResMut<RenetServer>,
+    mut rejected: ResMut<RejectedClients>,
+    time: Res<Time>,
+) {
+    let now = time.elapsed();
+
+    rejected.0.retain(|(client_id, disconnect_at)| {
+        if *disconnect_at > now {
+            return true;
+        }
+
+        server.disconnect(*client_id);
+        false
+    });
+}
@@ -0,0 +1,20 @@
+pub mod chat;
+pub use chat::*;
+
+pub mod connection;
+pub use connection::*;
+
+pub mod edits;
+pub use edits::*;
+
+pub mod streaming;
+pub use streaming::*;
+
+pub mod snapshots;
+pub use snapshots::*;
+
+pub mod movement;
+pub use movement::*;
+
+pub mod login;
+pub use login::*;
@@ -0,0 +1,148 @@
+use bevy::prelude::*;
+use bevy_renet::renet::RenetServer;
+use std::{collections::VecDeque, time::Duration};
+use crate::{
+    solid_blocks, step_player, Block, BlockMaterialRegistry, BodyState, ChunkMap, ChunkShape,
+    Collider, GameMode, HeadRotation, IsOnGround, MovementMode, NetworkMessage, PlayerInput,
+    ResultExt, Velocity, MOVEMENT_RATE, MOVEMENT_STEP, TICK_RATE, UNRELIABLE_CHANNEL,
+};
+
+use crate::server::world::ConnectedClient;
+
+/// Most inputs a client can bank while sending none, for it to catch up after a hiccup.
+///
+/// Inputs are applied at `MOVEMENT_RATE` per second on average, the bank is kept small so a client
+/// flooding the server can't move much faster than it should even in bursts.
+pub const MAX_BANKED_INPUTS: f64 = MOVEMENT_RATE / TICK_RATE * 2.;
+
+/// Inputs buffered per player past which the oldest ones are dropped.
+pub const MAX_QUEUED_INPUTS: usize = 64;
+
+/// Inputs received from a client and not applied yet.
+#[derive(Component, Default)]
+pub struct InputQueue {
+    pending: VecDeque<PlayerInput>,
+    last_sequence: Option<u32>,
+    /// Inputs the client may still have applied, earned over time.
+    credit: f64,
+}
+
+impl InputQueue {
+    /// Queues the inputs newer than the ones already received.
+    pub fn extend(&mut self, inputs: impl IntoIterator<Item = PlayerInput>) {
+        for input in inputs {
+            let latest = self
+                .pending
+                .back()
+                .map(|input| input.sequence)
+                .or(self.last_sequence);
+
+            if latest.is_some_and(|latest| input.sequence <= latest) {
+                continue;
+            }
+
+            if self.pending.len() == MAX_QUEUED_INPUTS {
+                self.pending.pop_front();
+            }
+
+            self.pending.push_back(input);
+        }
+    }
+
+    /// Takes up to `count` of the oldest inputs to apply them, older ones are ignored from now on.
+    pub fn take(&mut self, count: usize) -> impl Iterator<Item = PlayerInput> + '_ {
+        let count = self.pending.len().min(count);
+        if let Some(last) = count.checked_sub(1).and_then(|last| self.pending.get(last)) {
+            self.last_sequence = Some(last.sequence);
+        }
+
+        self.pending.drain(..count)
+    }
+
+    /// Takes the inputs due after `elapsed` more time, so that no more than `MOVEMENT_RATE` of them
+    /// are applied per second. Time the client sent no inputs for is banked, up to
+    /// `MAX_BANKED_INPUTS`.
+    pub fn take_due(&mut self, elapsed: Duration) -> impl Iterator<Item = PlayerInput> + '_ {
+        self.credit = (self.credit + elapsed.as_secs_f64() * MOVEMENT_RATE).min(MAX_BANKED_INPUTS);
+
+        let count = self.pending.len().min(self.credit as usize);
+        self.credit -= count as f64;
+        self.take(count)
+    }
+}
+
+/// Moves the players following the inputs of their clients, then tells every client where its
+/// player ended up so it can correct its prediction.
+pub fn simulate_player_inputs(
+    mut players: Query<(
+        &ConnectedClient,
+        &mut InputQueue,
+        &mut Transform,
+        &mut Velocity,
+        &mut IsOnGround,
+        &mut MovementMode,
+        &mut HeadRotation,
+        &Collider,
+        &GameMode,
+    )>,
+    chunks: Res<ChunkMap<Block, ChunkShape>>,
+    registry: Res<BlockMaterialRegistry>,
+    time: Res<Time>,
+    mut server: ResMut<RenetServer>,
+) {
+    let is_solid = solid_blocks(&chunks, &registry);
+
+    for (
+        client,
+        mut queue,
+        mut transform,
+        mut velocity,
+        mut on_ground,
+        mut mode,
+        mut head,
+        collider,
+        game_mode,
+    ) in players.iter_mut()
+    {
+        let mut state = BodyState {
+            position: transform.translation - Vec3::Y * collider.eye_height,
+            velocity: velocity.0,
+            on_ground: on_ground.0,
+        };
+
+        let Some(last_input) = queue
+            .take_due(time.delta())
+            .map(|mut input| {
+                // Clients can ask to fly, the server decides whether they may
+                if !game_mode.allows_flight() {
+                    input.mode = MovementMode::Walk;
+                }
+                input
+            })
+            .inspect(|input| step_player(&is_solid, collider, input, &mut state, MOVEMENT_STEP))
+            .last()
+        else {
+            continue;
+        };
+
+        transform.translation = state.position + Vec3::Y * collider.eye_height;
+        transform.rotation = Quat::from_rotation_y(last_input.yaw);
+        velocity.0 = state.velocity;
+        on_ground.0 = state.on_ground;
+        *mode = last_input.mode;
+        *head = HeadRotation {
+            yaw: last_input.yaw,
+            pitch: last_input.pitch,
+        };
+
+        if let Some(bytes) = (NetworkMessage::PlayerState {
+            sequence: last_input.sequence,
+            state,
+        })
+        .to_bytes()
+        .log_err_with("Failed to encode player state")
+        {
+            server.send_message(**client, UNRELIABLE_CHANNEL, bytes);
+        }
+    }
+}
@@ -0,0 +1,48 @@
+use bevy::prelude::*;
+use bevy_renet::renet::RenetServer;
+use crate::{HeadRotation, NetworkMessage, PlayerSnapshot, ResultExt, UNRELIABLE_CHANNEL};
+
+use crate::server::world::ConnectedClient;
+
+/// Number of fixed updates run since the server started.
+#[derive(Resource, Default, Clone, Copy, Deref)]
+pub struct ServerTick(pub u32);
+
+/// Advances the server tick and sends every client the state of all the players.
+///
+/// Snapshots go through the unreliable channel, clients interpolate between the ones they get
+/// so a dropped snapshot only costs some accuracy.
+pub fn broadcast_player_snapshots(
+    players: Query<(&ConnectedClient, &Transform, &HeadRotation)>,
+    mut server: ResMut<RenetServer>,
+    mut tick: ResMut<ServerTick>,
+) {
+    tick.0 = tick.0.wrapping_add(1);
+
+    if players.is_empty() {
+        return;
+    }
+
+    let snapshots = players
+        .iter()
+        .map(|(client, transform, head)| PlayerSnapshot {
+            id: **client,
+            position: transform.translation,
+            yaw: head.yaw,
+            pitch: head.pitch,
+        })
+        .collect();
+
+    if let Some(bytes) = (NetworkMessage::PlayerSnapshots {
+        tick: tick.0,
+        players: snapshots,
+    })
+    .to_bytes()
+    .log_err_with("Failed to encode player snapshots")
+    {
+        // Clients which haven't logged in yet may not understand the message
+        for (client, _, _) in players.iter() {
+            server.send_message(**client, UNRELIABLE_CHANNEL, bytes.clone());
+        }
+    }
+}
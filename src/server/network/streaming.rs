@@ -0,0 +1,93 @@
+use bevy::{platform::collections::HashSet, prelude::*};
+use bevy_renet::renet::RenetServer;
+use crate::{
+    chunk_in_radius, chunk_min_at, encode_chunk, Block, BlockMaterialRegistry, ChunkLoadRadius,
+    ChunkMap, ChunkShape, MaterialIdTable, NetworkConfig, NetworkMessage, ResultExt,
+    RELIABLE_CHANNEL,
+};
+
+use crate::server::{network::send_message, world::ConnectedClient};
+
+/// Fraction of the bandwidth of a tick spent on chunk data, the rest is left to other messages.
+const CHUNK_BANDWIDTH_DIVISOR: u64 = 2;
+
+/// Chunks a client was sent and hasn't been told to unload since.
+#[derive(Component, Default)]
+pub struct StreamedChunks(HashSet<IVec3>);
+
+impl StreamedChunks {
+    #[inline]
+    pub fn contains(&self, key: IVec3) -> bool {
+        self.0.contains(&key)
+    }
+}
+
+/// Sends every client the chunks around its player nearest first, within a per-tick byte budget,
+/// and tells it to unload the ones out of range.
+pub fn stream_chunks(
+    mut clients: Query<(&ConnectedClient, &GlobalTransform, &mut StreamedChunks)>,
+    mut server: ResMut<RenetServer>,
+    chunks: Res<ChunkMap<Block, ChunkShape>>,
+    registry: Res<BlockMaterialRegistry>,
+    view_radius: Res<ChunkLoadRadius>,
+    config: Res<NetworkConfig>,
+) {
+    let client_count = clients.iter().count() as u64;
+    if client_count == 0 {
+        return;
+    }
+
+    let budget = (config.bytes_per_tick / CHUNK_BANDWIDTH_DIVISOR / client_count) as usize;
+    let materials = MaterialIdTable::from(&*registry);
+
+    for (client, transform, mut streamed) in clients.iter_mut() {
+        let center = chunk_min_at(transform.translation().as_ivec3());
+
+        let out_of_range: Vec<IVec3> = streamed
+            .0
+            .iter()
+            .filter(|key| !chunk_in_radius(center, **key, &view_radius) || !chunks.exists(**key))
+            .copied()
+            .collect();
+
+        for key in out_of_range {
+            send_message(&mut server, **client, &NetworkMessage::UnloadChunk { key });
+            streamed.0.remove(&key);
+        }
+
+        let mut pending: Vec<IVec3> = chunks
+            .iter()
+            .map(|(key, _)| key)
+            .filter(|key| !streamed.contains(*key) && chunk_in_radius(center, *key, &view_radius))
+            .collect();
+        pending.sort_unstable_by_key(|key| (*key - center).length_squared());
+
+        let mut sent_bytes = 0;
+        for key in pending {
+            let Some(bytes) = chunks
+                .buffer_at(key)
+                .and_then(|buffer| {
+                    encode_chunk(buffer, &materials).log_err_with("Failed to encode chunk")
+                })
+                .and_then(|data| {
+                    NetworkMessage::ChunkData { key, data }
+                        .to_bytes()
+                        .log_err_with("Failed to encode network message")
+                })
+            else {
+                continue;
+            };
+
+            // Always let one chunk through so huge chunks can't stall the stream
+            if (sent_bytes > 0 && sent_bytes + bytes.len() > budget)
+                || !server.can_send_message(**client, RELIABLE_CHANNEL, bytes.len())
+            {
+                break;
+            }
+
+            sent_bytes += bytes.len();
+            server.send_message(**client, RELIABLE_CHANNEL, bytes);
+            streamed.0.insert(key);
+        }
+    }
+}
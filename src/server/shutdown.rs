@@ -0,0 +1,47 @@
+use bevy::prelude::*;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+use tracing::info;
+use voxel_engine::{GameError, ResultExt, ServerError};
+
+/// Raised by the SIGINT handler, the app exits on the next frame once set.
+#[derive(Resource, Clone, Default)]
+pub struct ShutdownSignal(Arc<AtomicBool>);
+
+impl ShutdownSignal {
+    pub fn is_raised(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// Exits the app gracefully on SIGINT, chunks and regions are flushed by
+/// [`voxel_engine::save_chunks_on_exit`] when the exit event is sent.
+pub struct ShutdownPlugin;
+
+impl Plugin for ShutdownPlugin {
+    fn build(&self, app: &mut App) {
+        let signal = ShutdownSignal::default();
+        let handler_signal = signal.clone();
+
+        ctrlc::set_handler(move || handler_signal.0.store(true, Ordering::SeqCst))
+            .map_err(|e| GameError::Server(ServerError::InternalError(e.to_string())))
+            .log_err_with("Failed to install the SIGINT handler");
+
+        app.insert_resource(signal)
+            .add_systems(Update, exit_on_signal);
+    }
+}
+
+fn exit_on_signal(
+    signal: Res<ShutdownSignal>,
+    mut exit: EventWriter<AppExit>,
+    mut exiting: Local<bool>,
+) {
+    if signal.is_raised() && !*exiting {
+        info!("Shutting down");
+        *exiting = true;
+        exit.write(AppExit::Success);
+    }
+}
@@ -1,5 +1,37 @@
-pub mod chunk;
-pub use chunk::*;
-
 pub mod player;
 pub use player::*;
+
+use bevy::prelude::*;
+use std::path::PathBuf;
+use crate::*;
+
+/// World simulation of the dedicated server, everything the client world does minus rendering.
+pub struct ServerWorldPlugin {
+    /// Seed new worlds are generated from, a random one is picked when `None`.
+    pub seed: Option<u64>,
+    /// Directory the world is saved to, it only lives in memory when `None`.
+    pub world_dir: Option<PathBuf>,
+}
+
+impl Plugin for ServerWorldPlugin {
+    fn build(&self, app: &mut App) {
+        let storage = self.world_dir.as_ref().and_then(|world_dir| {
+            RegionStorage::open(world_dir).log_err_with("Failed to open world storage")
+        });
+
+        app.insert_resource(WorldSeed::load_or_create(storage.as_ref(), self.seed));
+        if let Some(storage) = storage {
+            app.insert_resource(storage);
+        }
+
+        app.insert_resource(ChunkMap::<Block, ChunkShape>::new(ChunkShape {}))
+            .add_plugins(day_cycle::TimeOfDayPlugin)
+            .add_plugins(chunk::ChunkingPlugin)
+            // Ordering of plugins is important here;
+            .add_plugins(generation::TerrainGeneratorPlugin)
+            .add_plugins(terrain::WorldTerrainGenPlugin)
+            .add_plugins(physics::PhysicsPlugin)
+            .add_plugins(material::BlockMaterialPlugin)
+            .add_plugins(world::blocks::BlockBaseMaterialsPlugin);
+    }
+}
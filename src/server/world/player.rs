@@ -1,11 +1,15 @@
 use bevy::prelude::*;
-use voxel_engine::common::components::player::PlayerBundle;
+use bevy_renet::renet::ClientId;
+use crate::{
+    common::components::player::PlayerBundle, Collider, GameMode, HeadRotation, IsOnGround,
+    MovementMode, Velocity,
+};
 
-#[derive(Component)]
-pub struct IsOnGround(pub bool);
+use crate::server::network::{InputQueue, StreamedChunks};
 
-#[derive(Component, Default)]
-pub struct Velocity(pub Vec3);
+/// Client controlling a player.
+#[derive(Component, Clone, Copy, Deref)]
+pub struct ConnectedClient(pub ClientId);
 
 #[derive(Bundle)]
 pub struct ServerPlayerBundle {
@@ -14,4 +18,11 @@ pub struct ServerPlayerBundle {
 
     pub is_on_ground: IsOnGround,
     pub velocity: Velocity,
+    pub collider: Collider,
+    pub movement_mode: MovementMode,
+    pub game_mode: GameMode,
+    pub head_rotation: HeadRotation,
+    pub client: ConnectedClient,
+    pub streamed_chunks: StreamedChunks,
+    pub input_queue: InputQueue,
 }
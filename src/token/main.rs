@@ -0,0 +1,48 @@
+//! Issues the connect tokens players need to join a server in secure mode.
+//!
+//! `token keygen` prints a new private key to put in the server settings, `token issue <username>
+//! <server address> <output file>` writes a token signed with the key of the server settings. The
+//! server only accepts the tokens issued for the `public_addr` of its settings.
+
+use std::{fs::File, net::SocketAddr, process::ExitCode};
+use voxel_engine::*;
+
+const USAGE: &str = "usage: token keygen | token issue <username> <server address> <output file>";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    match args.iter().map(String::as_str).collect::<Vec<_>>().as_slice() {
+        ["keygen"] => {
+            println!("{}", encode_private_key(&generate_private_key()));
+            ExitCode::SUCCESS
+        }
+        ["issue", username, server_addr, output] => {
+            match issue(username, server_addr, output) {
+                Ok(()) => ExitCode::SUCCESS,
+                Err(e) => {
+                    eprintln!("Failed to issue connect token: {e}");
+                    ExitCode::FAILURE
+                }
+            }
+        }
+        _ => {
+            eprintln!("{USAGE}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn issue(username: &str, server_addr: &str, output: &str) -> Result<(), GameError> {
+    let private_key = ServerSettings::load()?
+        .private_key()?
+        .ok_or_else(|| GameError::NotFound("private key in the server settings".to_string()))?;
+    let server_addr = server_addr
+        .parse::<SocketAddr>()
+        .map_err(|e| GameError::Parser(e.to_string()))?;
+
+    let token = issue_connect_token(&private_key, username, vec![server_addr])?;
+    token.write(&mut File::create(output)?)?;
+
+    Ok(())
+}
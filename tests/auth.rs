@@ -0,0 +1,160 @@
+use bevy_renet::{
+    netcode::{NetcodeClientTransport, NetcodeServerTransport},
+    netcode::{NETCODE_KEY_BYTES, NETCODE_USER_DATA_BYTES},
+    renet::{RenetClient, RenetServer},
+};
+use std::{
+    net::{Ipv4Addr, SocketAddr, UdpSocket},
+    time::Duration,
+};
+use voxel_engine::{
+    create_client_connection, create_dedicated_server, decode_username, encode_private_key,
+    encode_username, generate_private_key, issue_connect_token, parse_private_key, GameError,
+    NetworkConfig, PrivateKey, MAX_USERNAME_BYTES,
+};
+
+/// Server in secure mode listening on a free local port.
+struct SecureServer {
+    server: RenetServer,
+    transport: NetcodeServerTransport,
+    addr: SocketAddr,
+}
+
+impl SecureServer {
+    /// Starts a server telling its clients it's reachable at `public_ip`.
+    fn start(private_key: PrivateKey, public_ip: Ipv4Addr) -> Self {
+        let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let addr = socket.local_addr().unwrap();
+        let (server, transport) = create_dedicated_server(
+            socket,
+            SocketAddr::from((public_ip, addr.port())),
+            1,
+            Some(private_key),
+            NetworkConfig::default().connection_config(),
+        );
+
+        Self {
+            server,
+            transport,
+            addr,
+        }
+    }
+
+    /// Exchanges packets with a client until it's connected or gives up, returns whether it
+    /// got in.
+    fn connect(
+        &mut self,
+        client: &mut RenetClient,
+        transport: &mut NetcodeClientTransport,
+    ) -> bool {
+        let delta = Duration::from_millis(10);
+
+        for _ in 0..300 {
+            client.update(delta);
+            if transport.update(delta, client).is_err() || client.is_connected() {
+                break;
+            }
+            self.server.update(delta);
+            // Refused connect tokens are reported here, the client only sees them time out
+            let _ = self.transport.update(delta, &mut self.server);
+
+            let _ = transport.send_packets(client);
+            self.transport.send_packets(&mut self.server);
+            std::thread::sleep(delta);
+        }
+
+        client.is_connected()
+    }
+}
+
+#[test]
+fn private_keys_round_trip() {
+    let key = generate_private_key();
+    let encoded = encode_private_key(&key);
+
+    assert_eq!(encoded.len(), NETCODE_KEY_BYTES * 2);
+    assert_eq!(parse_private_key(&encoded).unwrap(), key);
+    assert_eq!(parse_private_key(&format!("  {encoded}\n")).unwrap(), key);
+    assert_eq!(parse_private_key(&encoded.to_uppercase()).unwrap(), key);
+}
+
+#[test]
+fn malformed_private_keys_are_rejected() {
+    let encoded = encode_private_key(&generate_private_key());
+    let non_hex = format!("zz{}", &encoded[2..]);
+    let multi_byte = format!("é{}", &encoded[2..]);
+
+    for hex in ["", &encoded[1..], &format!("{encoded}00"), &non_hex, &multi_byte] {
+        assert!(matches!(parse_private_key(hex), Err(GameError::Parser(_))), "{hex:?}");
+    }
+}
+
+#[test]
+fn usernames_round_trip() {
+    for username in ["a", "Steve", "joueur_é", &"x".repeat(MAX_USERNAME_BYTES)] {
+        let user_data = encode_username(username).unwrap();
+
+        assert_eq!(decode_username(&user_data).as_deref(), Some(username));
+    }
+}
+
+#[test]
+fn out_of_bounds_usernames_are_rejected() {
+    for username in ["", &"x".repeat(MAX_USERNAME_BYTES + 1)] {
+        assert!(matches!(encode_username(username), Err(GameError::Parser(_))));
+    }
+}
+
+#[test]
+fn malformed_user_data_is_not_a_username() {
+    let mut empty = [0; NETCODE_USER_DATA_BYTES];
+    assert_eq!(decode_username(&empty), None);
+
+    empty[0] = MAX_USERNAME_BYTES as u8 + 1;
+    assert_eq!(decode_username(&empty), None);
+
+    let mut invalid_utf8 = [0; NETCODE_USER_DATA_BYTES];
+    invalid_utf8[..3].copy_from_slice(&[2, 0xC3, 0x28]);
+    assert_eq!(decode_username(&invalid_utf8), None);
+}
+
+#[test]
+fn issued_tokens_let_players_in() {
+    let private_key = generate_private_key();
+    let mut server = SecureServer::start(private_key, Ipv4Addr::LOCALHOST);
+
+    let token = issue_connect_token(&private_key, "Steve", vec![server.addr]).unwrap();
+    let client_id = token.client_id;
+    let (mut client, mut transport) = create_client_connection(
+        server.addr,
+        Some(token),
+        NetworkConfig::default().connection_config(),
+    );
+
+    assert!(server.connect(&mut client, &mut transport));
+    let user_data = server.transport.user_data(client_id).unwrap();
+    assert_eq!(decode_username(&user_data).as_deref(), Some("Steve"));
+}
+
+#[test]
+fn tokens_for_other_addresses_or_keys_are_refused() {
+    let private_key = generate_private_key();
+    let mut server = SecureServer::start(private_key, Ipv4Addr::LOCALHOST);
+    let mut unlisted = SecureServer::start(private_key, Ipv4Addr::UNSPECIFIED);
+
+    let refused = [
+        (&mut server, generate_private_key()),
+        // The tokens carry the address the server is reached at, not the one it's bound to
+        (&mut unlisted, private_key),
+    ];
+    for (server, private_key) in refused {
+        let token = issue_connect_token(&private_key, "Steve", vec![server.addr]).unwrap();
+        let (mut client, mut transport) = create_client_connection(
+            server.addr,
+            Some(token),
+            NetworkConfig::default().connection_config(),
+        );
+
+        assert!(!server.connect(&mut client, &mut transport));
+    }
+}
@@ -0,0 +1,208 @@
+use bevy::prelude::*;
+use ilattice::glam::UVec3;
+use voxel_engine::{
+    Block, BlockBaseMaterialsPlugin, BlockBuffer, BlockMaterial, BlockMaterialPlugin, Cactus,
+    ChunkShape, ClimateNoise, ClimateRange, Grass, Gravel, Leaves, Sand, Snow, Stone,
+    TerrainGeneratorPlugin, Water, WorldSeed, Wood, CHUNK_HEIGHT, CHUNK_SIZE, TERRAIN_GENERATOR,
+};
+
+const SEED: WorldSeed = WorldSeed(0xb10e_5eed);
+
+/// Chunk of the world generated with the built-in biomes.
+struct GeneratedChunk {
+    buffer: BlockBuffer<Block, ChunkShape>,
+}
+
+impl GeneratedChunk {
+    /// Generates the chunk at the bottom of the world in the chunk column at `column`.
+    fn generate(column: IVec2) -> Self {
+        App::new().add_plugins((
+            BlockMaterialPlugin,
+            BlockBaseMaterialsPlugin,
+            TerrainGeneratorPlugin,
+        ));
+        let generator = TERRAIN_GENERATOR.read().unwrap();
+
+        let mut buffer = BlockBuffer::<Block, ChunkShape>::new_empty(ChunkShape {});
+        generator.generate(SEED, IVec3::new(column.x, 0, column.y), &mut buffer);
+        Self { buffer }
+    }
+
+    fn block_at(&self, x: u32, y: i32, z: u32) -> Block {
+        self.buffer.block_at(UVec3::new(x, y as u32, z))
+    }
+
+    /// Returns the highest block of a column which is neither empty nor one of `skipped`.
+    fn top(&self, x: u32, z: u32, skipped: &[Block]) -> Block {
+        (0..CHUNK_HEIGHT as i32)
+            .rev()
+            .map(|y| self.block_at(x, y, z))
+            .find(|block| !block.is_empty() && !skipped.contains(block))
+            .unwrap_or(Block::EMPTY_BLOCK)
+    }
+
+    /// Returns the positions of every block of `block`, with the block right below them.
+    fn blocks_of(&self, block: Block) -> Vec<(IVec3, Block)> {
+        let size = CHUNK_SIZE as u32;
+        (0..size)
+            .flat_map(|z| (0..size).map(move |x| (x, z)))
+            .flat_map(|(x, z)| (1..CHUNK_HEIGHT as i32).map(move |y| (x, y, z)))
+            .filter(|(x, y, z)| self.block_at(*x, *y, *z) == block)
+            .map(|(x, y, z)| (IVec3::new(x as i32, y, z as i32), self.block_at(x, y - 1, z)))
+            .collect()
+    }
+}
+
+/// Returns a chunk column whose climate lies within `climate`.
+fn column_in(climate: &ClimateRange) -> IVec2 {
+    let noise = ClimateNoise::new(SEED);
+    let half = CHUNK_SIZE as i32 / 2;
+
+    (-64..64)
+        .flat_map(|z| (-64..64).map(move |x| IVec2::new(x, z) * 8 * CHUNK_SIZE as i32))
+        .find(|column| climate.distance(noise.at(*column + half)) == 0.0)
+        .unwrap_or_else(|| panic!("No column in {climate:?}"))
+}
+
+/// Built-in biome, with what its surface is made of.
+struct Biome {
+    name: &'static str,
+    /// Climates deep inside of the biome, away from its neighbours.
+    climate: ClimateRange,
+    /// Blocks most of the ground is covered with.
+    ground: Vec<Block>,
+    /// Block found on top of some columns.
+    top: Block,
+}
+
+fn biome(name: &'static str) -> Biome {
+    const LAND: std::ops::Range<f32> = -0.1..0.2;
+
+    let (climate, ground, top) = match name {
+        "ocean" => (
+            ClimateRange {
+                continentalness: -1.0..-0.4,
+                ..ClimateRange::ANY
+            },
+            vec![Gravel::into_block()],
+            Water::into_block(),
+        ),
+        "mountains" => (
+            ClimateRange {
+                continentalness: 0.5..1.0,
+                ..ClimateRange::ANY
+            },
+            vec![Stone::into_block(), Snow::into_block()],
+            Stone::into_block(),
+        ),
+        "plains" => (
+            ClimateRange {
+                temperature: -0.1..0.1,
+                humidity: -1.0..0.15,
+                continentalness: LAND,
+            },
+            vec![Grass::into_block()],
+            Leaves::into_block(),
+        ),
+        "desert" => (
+            ClimateRange {
+                temperature: 0.4..1.0,
+                humidity: -1.0..-0.15,
+                continentalness: LAND,
+            },
+            vec![Sand::into_block()],
+            Cactus::into_block(),
+        ),
+        "snowy taiga" => (
+            ClimateRange {
+                temperature: -1.0..-0.4,
+                continentalness: LAND,
+                ..ClimateRange::ANY
+            },
+            vec![Snow::into_block()],
+            Leaves::into_block(),
+        ),
+        "swamp" => (
+            ClimateRange {
+                temperature: -0.1..1.0,
+                humidity: 0.45..1.0,
+                continentalness: LAND,
+            },
+            vec![Grass::into_block()],
+            Water::into_block(),
+        ),
+        _ => unreachable!("{name} isn't a built-in biome"),
+    };
+
+    Biome {
+        name,
+        climate,
+        ground,
+        top,
+    }
+}
+
+const BIOMES: [&str; 6] = ["ocean", "mountains", "plains", "desert", "snowy taiga", "swamp"];
+
+#[test]
+fn every_biome_generates_its_surface() {
+    let decorations = [Wood::into_block(), Leaves::into_block(), Cactus::into_block()];
+    let covering = [decorations.as_slice(), &[Water::into_block()]].concat();
+
+    for biome in BIOMES.map(biome) {
+        let chunk = GeneratedChunk::generate(column_in(&biome.climate));
+        let size = CHUNK_SIZE as u32;
+        let (tops, grounds): (Vec<_>, Vec<_>) = (0..size)
+            .flat_map(|z| (0..size).map(move |x| (x, z)))
+            .map(|(x, z)| (chunk.top(x, z, &[]), chunk.top(x, z, &covering)))
+            .unzip();
+
+        let covered = grounds.iter().filter(|block| biome.ground.contains(block)).count();
+        assert!(covered > grounds.len() / 2, "{} ground covered {covered} times", biome.name);
+        assert!(tops.contains(&biome.top), "no {:?} on top of {}", biome.top, biome.name);
+    }
+}
+
+#[test]
+fn cacti_stand_on_sand() {
+    let chunk = GeneratedChunk::generate(column_in(&biome("desert").climate));
+
+    let cacti = chunk.blocks_of(Cactus::into_block());
+    assert!(!cacti.is_empty());
+    for (pos, below) in cacti {
+        assert!(below == Sand::into_block() || below == Cactus::into_block(), "cactus at {pos}");
+    }
+}
+
+#[test]
+fn trees_cross_chunk_borders() {
+    let column = column_in(&biome("snowy taiga").climate);
+    let west = GeneratedChunk::generate(column);
+    let east = GeneratedChunk::generate(column + IVec2::X * CHUNK_SIZE as i32);
+    let tree = [Wood::into_block(), Leaves::into_block()];
+
+    // Counts the tree blocks right next to each other across the border between the chunks
+    let last = CHUNK_SIZE as u32 - 1;
+    let crossing = (0..CHUNK_SIZE as u32)
+        .flat_map(|z| (0..CHUNK_HEIGHT as i32).map(move |y| (y, z)))
+        .filter(|(y, z)| {
+            tree.contains(&west.block_at(last, *y, *z)) && tree.contains(&east.block_at(0, *y, *z))
+        });
+    assert!(crossing.count() > 0);
+
+    // Trunks are three blocks wide and their sides can hang over slopes, but never all of them
+    let size = CHUNK_SIZE as i32;
+    let overhanging = west.blocks_of(Wood::into_block()).into_iter().filter(|(pos, below)| {
+        below.is_empty() && pos.x > 0 && pos.x < size - 1 && pos.z > 0 && pos.z < size - 1
+    });
+
+    for (pos, _) in overhanging {
+        let supported = (-1..=1).any(|dz| {
+            (-1..=1).any(|dx| {
+                let (x, z) = ((pos.x + dx) as u32, (pos.z + dz) as u32);
+                !west.block_at(x, pos.y - 1, z).is_empty()
+            })
+        });
+        assert!(supported, "floating trunk at {pos}");
+    }
+}
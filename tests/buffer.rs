@@ -0,0 +1,104 @@
+use ilattice::{extent::Extent, glam::UVec3};
+use ndshape::{ConstShape3u32, Shape};
+use voxel_engine::BlockBuffer;
+
+type TestShape = ConstShape3u32<16, 16, 16>;
+
+const VOLUME: u32 = 16 * 16 * 16;
+
+fn pos(index: u32) -> UVec3 {
+    UVec3::from(TestShape {}.delinearize(index))
+}
+
+#[test]
+fn new_buffers_are_compact_and_uniform() {
+    let buffer = BlockBuffer::new(TestShape {}, 7u16);
+
+    assert!(buffer.is_compact());
+    assert_eq!(buffer.block_at(pos(0)), 7);
+    assert_eq!(buffer.block_at(pos(VOLUME - 1)), 7);
+    assert!(buffer.slice().iter().all(|block| *block == 7));
+}
+
+#[test]
+fn palettes_grow_past_every_bit_width() {
+    let mut buffer = BlockBuffer::<u16, _>::new_empty(TestShape {});
+
+    // Every new value grows the palette, crossing widths which do and don't divide a word
+    for value in 1..=600u16 {
+        buffer.set_block(pos(value as u32 * 5), value);
+
+        assert!(buffer.is_compact());
+        if value.is_power_of_two() || value % 97 == 0 {
+            let blocks = buffer.slice();
+            for (index, block) in blocks.iter().enumerate() {
+                let expected = match index % 5 {
+                    0 if index / 5 <= value as usize => index as u16 / 5,
+                    _ => 0,
+                };
+                assert_eq!(*block, expected, "voxel {index} after writing {value}");
+            }
+        }
+    }
+}
+
+#[test]
+fn overwritten_blocks_leave_the_palette_when_it_grows() {
+    let mut buffer = BlockBuffer::<u16, _>::new_empty(TestShape {});
+    buffer.set_block(pos(0), 1);
+    buffer.set_block(pos(0), 0);
+    assert!(buffer.any(|block| block == 1));
+
+    buffer.set_block(pos(5), 2);
+    assert!(!buffer.any(|block| block == 1));
+    assert!(buffer.any(|block| block == 2));
+    assert_eq!(buffer.block_at(pos(5)), 2);
+    assert_eq!(buffer.block_at(pos(0)), 0);
+}
+
+#[test]
+fn mutable_access_unpacks_until_compacted() {
+    let mut buffer = BlockBuffer::<u16, _>::new_empty(TestShape {});
+    *buffer.block_at_mut(pos(42)) = 3;
+    assert!(!buffer.is_compact());
+
+    buffer.compact();
+    assert!(buffer.is_compact());
+    assert_eq!(buffer.block_at(pos(42)), 3);
+    assert_eq!(buffer.block_at(pos(41)), 0);
+    assert!(!buffer.any(|block| block == 1));
+}
+
+#[test]
+fn compact_keeps_dense_buffers_when_packing_takes_more_room() {
+    let mut noisy = BlockBuffer::<u8, _>::new_empty(TestShape {});
+    for (index, block) in noisy.slice_mut().iter_mut().enumerate() {
+        *block = index as u8;
+    }
+
+    noisy.compact();
+    assert!(!noisy.is_compact());
+    assert!(noisy.slice().iter().enumerate().all(|(index, block)| *block == index as u8));
+
+    let mut striped = BlockBuffer::<u8, _>::new_empty(TestShape {});
+    for (index, block) in striped.slice_mut().iter_mut().enumerate() {
+        *block = (index % 2) as u8;
+    }
+
+    striped.compact();
+    assert!(striped.is_compact());
+    assert!(striped.slice().iter().enumerate().all(|(index, block)| *block == (index % 2) as u8));
+}
+
+#[test]
+fn filled_extents_stay_compact() {
+    let mut buffer = BlockBuffer::<u16, _>::new_empty(TestShape {});
+    let extent = Extent::from_min_and_shape(UVec3::new(2, 3, 4), UVec3::new(4, 5, 6));
+    buffer.fill_extent(extent, 9);
+
+    assert!(buffer.is_compact());
+    for index in 0..VOLUME {
+        let expected = if extent.contains(pos(index)) { 9 } else { 0 };
+        assert_eq!(buffer.block_at(pos(index)), expected);
+    }
+}
@@ -0,0 +1,101 @@
+use bevy::math::IVec3;
+use voxel_engine::{
+    carve_cheese_caves, carve_terrain_density, carve_worm_caves, chunk_extent, Block, BlockBuffer,
+    BlockMaterial, CaveSettings, ChunkShape, ColumnTerrain, DensitySettings, Stone, TerrainShape,
+    Water, WorldSeed, CAVE_MIN_Y, CHEESE_MIN_DEPTH, CHUNK_SIZE, SEA_LEVEL, WORM_MIN_DEPTH,
+};
+
+const SEED: WorldSeed = WorldSeed(0xca5e_5eed);
+
+const WORMS: CaveSettings = CaveSettings {
+    cheese_threshold: 1.0,
+    worm_chance: 1.0,
+    worm_radius: 4.0,
+};
+
+/// Carves the caves of a chunk whose columns are all flat at `height`.
+fn carved_chunk(key: IVec3, height: f32, caves: CaveSettings) -> BlockBuffer<Block, ChunkShape> {
+    let density = DensitySettings {
+        overhang_height: 0.01,
+        caves,
+    };
+    let columns = vec![ColumnTerrain { height, density }; CHUNK_SIZE * CHUNK_SIZE];
+    let shape = TerrainShape::sample(SEED, key, CHUNK_SIZE, &columns);
+
+    let mut buffer = BlockBuffer::new_empty(ChunkShape {});
+    carve_terrain_density(&mut buffer, key, &shape);
+    carve_cheese_caves(&mut buffer, &shape);
+    carve_worm_caves(SEED, key, &shape, &mut buffer, |_| caves);
+    buffer
+}
+
+#[test]
+fn worms_stay_below_the_surface() {
+    // The surface lies under the sea
+    let buffer = carved_chunk(IVec3::new(32, 0, -64), 90.5, WORMS);
+    let surface = 90;
+
+    let mut hollowed = 0;
+    for pos in chunk_extent().iter3() {
+        let block = buffer.block_at(pos);
+        if pos.y >= SEA_LEVEL as u32 {
+            assert!(block.is_empty(), "{pos}");
+        } else if pos.y > surface {
+            assert_eq!(block, Water::into_block(), "{pos}");
+        } else if pos.y + WORM_MIN_DEPTH > surface {
+            assert_eq!(block, Stone::into_block(), "{pos}");
+        } else if block.is_empty() {
+            hollowed += 1;
+        }
+    }
+    assert!(hollowed > 0);
+}
+
+#[test]
+fn worms_keep_above_the_bottom_of_the_caves() {
+    let buffer = carved_chunk(IVec3::new(-32, 0, 0), 200.5, WORMS);
+
+    let mut hollowed = 0;
+    for pos in chunk_extent().iter3() {
+        let block = buffer.block_at(pos);
+        if (pos.y as i32) < CAVE_MIN_Y {
+            assert_eq!(block, Stone::into_block(), "{pos}");
+        } else if block.is_empty() {
+            hollowed += 1;
+        }
+    }
+    assert!(hollowed > 0);
+}
+
+#[test]
+fn unlikely_worms_carve_nothing() {
+    let caves = CaveSettings {
+        worm_chance: 0.0,
+        ..WORMS
+    };
+    let buffer = carved_chunk(IVec3::ZERO, 200.5, caves);
+
+    let mut ground = chunk_extent().iter3().filter(|pos| pos.y <= 200);
+    assert!(ground.all(|pos| buffer.block_at(pos) == Stone::into_block()));
+}
+
+#[test]
+fn cheese_caves_hollow_out_their_blocks() {
+    let caves = CaveSettings {
+        cheese_threshold: -1.0,
+        worm_chance: 0.0,
+        ..WORMS
+    };
+    let height = 40.5;
+    let buffer = carved_chunk(IVec3::ZERO, height, caves);
+
+    for pos in chunk_extent().iter3().filter(|pos| (pos.y as f32) < height) {
+        let y = pos.y as f32;
+        let expected = if y >= CAVE_MIN_Y as f32 && y < height - CHEESE_MIN_DEPTH {
+            Block::EMPTY_BLOCK
+        } else {
+            Stone::into_block()
+        };
+        assert_eq!(buffer.block_at(pos), expected, "{pos}");
+    }
+}
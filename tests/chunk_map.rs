@@ -0,0 +1,106 @@
+use bevy::math::IVec3;
+use ndshape::ConstShape3u32;
+use voxel_engine::{
+    neighbour_offsets, Block, BlockBuffer, ChunkMap, ChunkShape, DirtyChunks, CHUNK_DIMS,
+};
+
+type TestShape = ConstShape3u32<16, 16, 16>;
+
+const SIZE: i32 = 16;
+
+/// Returns a map where every neighbour of the chunk at the origin but the one at `missing` is
+/// filled with a block telling which neighbour it is.
+fn map_around_origin(missing: IVec3) -> ChunkMap<u8, TestShape> {
+    let mut map = ChunkMap::new(TestShape {});
+    map.insert_empty(IVec3::ZERO);
+
+    for (marker, offset) in neighbour_offsets().enumerate() {
+        if offset != missing {
+            map.insert(offset * SIZE, BlockBuffer::new(TestShape {}, marker as u8 + 1));
+        }
+    }
+    map
+}
+
+#[test]
+fn borders_cover_faces_edges_and_corners() {
+    let map = map_around_origin(IVec3::splat(2));
+    let borders = map.neighbour_borders(IVec3::ZERO);
+
+    for (marker, offset) in neighbour_offsets().enumerate() {
+        // Positions just outside the chunk along the offset, at both ends of the other axes
+        for pos in [
+            IVec3::select(offset.cmpeq(IVec3::ZERO), IVec3::ZERO, offset),
+            IVec3::select(offset.cmpeq(IVec3::ZERO), IVec3::splat(SIZE - 1), offset),
+        ] {
+            let pos = IVec3::select(offset.cmpgt(IVec3::ZERO), IVec3::splat(SIZE), pos);
+            assert_eq!(borders.block_at(pos), Some(marker as u8 + 1), "{offset} at {pos}");
+        }
+    }
+}
+
+#[test]
+fn borders_keep_the_layout_of_the_neighbours() {
+    let mut map = map_around_origin(IVec3::splat(2));
+    let blocks = [
+        // Face of the +X neighbour, edge of the -Y -Z one and corner of the +X +Y -Z one
+        (IVec3::new(SIZE, 3, 7), 50),
+        (IVec3::new(SIZE, 12, 0), 51),
+        (IVec3::new(9, -1, -1), 52),
+        (IVec3::new(SIZE, SIZE, -1), 53),
+        (IVec3::new(-1, 0, SIZE - 1), 54),
+    ];
+    for (pos, block) in blocks {
+        map.set_block(pos, block);
+    }
+
+    let borders = map.neighbour_borders(IVec3::ZERO);
+    for (pos, block) in blocks {
+        assert_eq!(borders.block_at(pos), Some(block), "{pos}");
+    }
+
+    // Blocks next to the edited ones are left alone
+    assert_ne!(borders.block_at(IVec3::new(SIZE, 3, 8)), Some(50));
+    assert_ne!(borders.block_at(IVec3::new(SIZE, 4, 7)), Some(50));
+    assert_ne!(borders.block_at(IVec3::new(8, -1, -1)), Some(52));
+}
+
+#[test]
+fn borders_skip_unloaded_neighbours() {
+    let missing = IVec3::new(1, -1, 0);
+    let map = map_around_origin(missing);
+    let borders = map.neighbour_borders(IVec3::ZERO);
+
+    assert_eq!(borders.block_at(IVec3::new(SIZE, -1, 5)), None);
+    assert!(borders.block_at(IVec3::new(SIZE, 0, 5)).is_some());
+    assert!(borders.block_at(IVec3::new(SIZE, -1, -1)).is_some());
+}
+
+#[test]
+fn borders_only_hold_the_blocks_touching_the_chunk() {
+    let map = map_around_origin(IVec3::splat(2));
+    let borders = map.neighbour_borders(IVec3::ZERO);
+
+    assert_eq!(borders.block_at(IVec3::ZERO), None);
+    assert_eq!(borders.block_at(IVec3::splat(SIZE - 1)), None);
+    assert_eq!(borders.block_at(IVec3::new(-2, 0, 0)), None);
+    assert_eq!(borders.block_at(IVec3::new(0, SIZE + 1, 0)), None);
+}
+
+#[test]
+fn loaded_chunks_dirty_every_neighbour_touching_them() {
+    let key = CHUNK_DIMS * IVec3::new(2, -1, 3);
+    let mut map = ChunkMap::<Block, ChunkShape>::new(ChunkShape {});
+    for offset in neighbour_offsets().filter(|offset| *offset != IVec3::ONE) {
+        map.insert_empty(key + offset * CHUNK_DIMS);
+    }
+
+    let mut dirty = DirtyChunks::default();
+    dirty.mark_neighbours_dirty(key, &map);
+
+    // The missing corner isn't loaded, and the chunk itself is left alone
+    assert_eq!(dirty.num_dirty(), 25);
+    for offset in neighbour_offsets().filter(|offset| *offset != IVec3::ONE) {
+        assert!(dirty.iter_dirty().any(|chunk| *chunk == key + offset * CHUNK_DIMS), "{offset}");
+    }
+}
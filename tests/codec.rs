@@ -0,0 +1,144 @@
+use bevy::prelude::*;
+use bincode::Encode;
+use ilattice::glam::UVec3;
+use ndshape::ConstShape3u32;
+use voxel_engine::{
+    decode_chunk, encode_chunk, Block, BlockBaseMaterialsPlugin, BlockBuffer, BlockMaterial,
+    BlockMaterialPlugin, BlockMaterialRegistry, ChunkShape, Dirt, GameError, InvalidData,
+    MaterialIdTable, Stone, Water, CHUNK_CODEC_VERSION,
+};
+
+type SmallShape = ConstShape3u32<4, 4, 4>;
+
+const SMALL_VOLUME: u32 = 4 * 4 * 4;
+
+/// Mirrors the header of the chunk format to forge payloads the encoder would never write.
+#[derive(Encode)]
+struct RawHeader {
+    magic: [u8; 4],
+    version: u16,
+    shape: [u32; 3],
+}
+
+#[derive(Encode)]
+enum RawBody {
+    RunLength(Vec<(u32, u32)>),
+}
+
+#[derive(Encode)]
+struct RawPayload {
+    palette: Vec<(String, bool)>,
+    body: RawBody,
+}
+
+fn materials() -> MaterialIdTable {
+    let mut app = App::new();
+    app.add_plugins((BlockMaterialPlugin, BlockBaseMaterialsPlugin));
+
+    MaterialIdTable::from(app.world().resource::<BlockMaterialRegistry>())
+}
+
+fn raw_chunk(version: u16, runs: Vec<(u32, u32)>) -> Vec<u8> {
+    let config = bincode::config::standard();
+    let header = RawHeader {
+        magic: *b"RCCK",
+        version,
+        shape: [4, 4, 4],
+    };
+    let payload = RawPayload {
+        palette: vec![(Stone::id_string(), false)],
+        body: RawBody::RunLength(runs),
+    };
+
+    let mut data = bincode::encode_to_vec(header, config).unwrap();
+    data.extend(bincode::encode_to_vec(payload, config).unwrap());
+    data
+}
+
+fn decode_small(data: &[u8]) -> Result<BlockBuffer<Block, SmallShape>, GameError> {
+    decode_chunk(data, SmallShape {}, &materials())
+}
+
+fn assert_chunk_error(result: Result<BlockBuffer<Block, SmallShape>, GameError>) {
+    assert!(matches!(
+        result,
+        Err(GameError::InvalidData(InvalidData::ChunkData(_)))
+    ));
+}
+
+#[test]
+fn chunks_round_trip() {
+    let materials = materials();
+    let mut buffer = BlockBuffer::<Block, ChunkShape>::new_empty(ChunkShape {});
+    buffer.set_block(UVec3::new(0, 0, 0), Stone::into_block());
+    buffer.set_block(UVec3::new(5, 7, 3), Dirt::into_block());
+    buffer.set_block(UVec3::new(31, 31, 31), Water::into_block());
+
+    let data = encode_chunk(&buffer, &materials).unwrap();
+    let decoded = decode_chunk(&data, ChunkShape {}, &materials).unwrap();
+
+    assert_eq!(decoded.slice(), buffer.slice());
+    assert!(decoded.block_at(UVec3::new(31, 31, 31)).is_transparent());
+}
+
+#[test]
+fn forged_chunks_decode_like_encoded_ones() {
+    let decoded = decode_small(&raw_chunk(CHUNK_CODEC_VERSION, vec![(SMALL_VOLUME, 0)])).unwrap();
+
+    assert!(decoded.slice().iter().all(|block| *block == Stone::into_block()));
+}
+
+#[test]
+fn bad_magic_is_rejected() {
+    let mut data = encode_chunk(
+        &BlockBuffer::<Block, SmallShape>::new_empty(SmallShape {}),
+        &materials(),
+    )
+    .unwrap();
+    data[..4].copy_from_slice(b"NOPE");
+
+    assert_chunk_error(decode_small(&data));
+}
+
+#[test]
+fn unknown_version_is_rejected() {
+    let data = raw_chunk(CHUNK_CODEC_VERSION + 1, vec![(SMALL_VOLUME, 0)]);
+
+    assert_chunk_error(decode_small(&data));
+}
+
+#[test]
+fn shape_mismatch_is_rejected() {
+    let materials = materials();
+    let data = encode_chunk(
+        &BlockBuffer::<Block, SmallShape>::new_empty(SmallShape {}),
+        &materials,
+    )
+    .unwrap();
+
+    assert!(matches!(
+        decode_chunk(&data, ChunkShape {}, &materials),
+        Err(GameError::InvalidData(InvalidData::ChunkData(_)))
+    ));
+}
+
+#[test]
+fn out_of_range_palette_index_is_rejected() {
+    let data = raw_chunk(CHUNK_CODEC_VERSION, vec![(SMALL_VOLUME, 1)]);
+
+    assert_chunk_error(decode_small(&data));
+}
+
+#[test]
+fn runs_overflowing_the_chunk_are_rejected() {
+    let data = raw_chunk(CHUNK_CODEC_VERSION, vec![(SMALL_VOLUME, 0), (1, 0)]);
+
+    assert_chunk_error(decode_small(&data));
+}
+
+#[test]
+fn runs_not_covering_the_chunk_are_rejected() {
+    let data = raw_chunk(CHUNK_CODEC_VERSION, vec![(SMALL_VOLUME - 1, 0)]);
+
+    assert_chunk_error(decode_small(&data));
+}
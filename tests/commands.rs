@@ -0,0 +1,198 @@
+use bevy::{ecs::system::SystemState, prelude::*};
+use voxel_engine::{
+    server::{
+        commands::{
+            ArgKind, CommandContext, CommandInvocation, CommandResult, CommandSender,
+            CommandSpec, CommandsPlugin, Permission, RegisterCommand,
+        },
+        network::Authenticated,
+    },
+    BlockBaseMaterialsPlugin, BlockMaterial, BlockMaterialPlugin, DisplayName, IronOre, Lobby,
+    Stone,
+};
+
+const ALICE: CommandSender = CommandSender::Player(1);
+const BOB: CommandSender = CommandSender::Player(2);
+/// Listed as an operator, but joined without a connect token.
+const CAROL: CommandSender = CommandSender::Player(3);
+
+fn echo(In(invocation): In<CommandInvocation>) -> CommandResult {
+    Ok(invocation.text(0).unwrap_or_default().to_string())
+}
+
+/// Returns a server where alice is an operator and bob isn't, carol claims to be one.
+fn server() -> App {
+    let mut app = App::new();
+    app.add_plugins((
+        BlockMaterialPlugin,
+        BlockBaseMaterialsPlugin,
+        CommandsPlugin {
+            operators: vec!["alice".to_string(), "carol".to_string()],
+        },
+    ))
+    .init_resource::<Lobby>()
+    .register_command(
+        CommandSpec::new("echo", "Repeats the text").arg("text", ArgKind::Text),
+        echo,
+    );
+
+    let players = [(1, "alice", true), (2, "bob", true), (3, "carol", false)];
+    for (client_id, name, authenticated) in players {
+        let mut player = app.world_mut().spawn(DisplayName(name.to_string()));
+        if authenticated {
+            player.insert(Authenticated);
+        }
+        let player = player.id();
+
+        app.world_mut()
+            .resource_mut::<Lobby>()
+            .players
+            .insert(client_id, player);
+    }
+
+    app
+}
+
+fn with_context<T>(app: &mut App, f: impl FnOnce(&CommandContext) -> T) -> T {
+    let mut state = SystemState::<CommandContext>::new(app.world_mut());
+    f(&state.get(app.world()))
+}
+
+fn parse_error(app: &mut App, sender: CommandSender, line: &str) -> String {
+    with_context(app, |context| context.parse(sender, line).err().expect(line))
+}
+
+#[test]
+fn commands_parse_their_arguments() {
+    let mut app = server();
+
+    with_context(&mut app, |context| {
+        let (_, teleport) = context.parse(ALICE, "/tp 1 2.5 -3 bob").unwrap();
+        assert_eq!(teleport.sender, ALICE);
+        assert_eq!(teleport.number(0), Some(1.));
+        assert_eq!(teleport.number(1), Some(2.5));
+        assert_eq!(teleport.number(2), Some(-3.));
+        assert_eq!(teleport.target(3), Ok(2));
+
+        // The slash is optional and left out arguments target the sender
+        let (_, teleport) = context.parse(ALICE, "  tp 1 2 3").unwrap();
+        assert_eq!(teleport.args.len(), 3);
+        assert_eq!(teleport.target(3), Ok(1));
+
+        let (_, give) = context.parse(ALICE, "/give stone").unwrap();
+        assert_eq!(give.material(0), Some(Stone::into_block()));
+
+        // Materials sharing their name are told apart by their variant
+        let (_, give) = context.parse(ALICE, "/give ore:iron").unwrap();
+        assert_eq!(give.material(0), Some(IronOre::into_block()));
+        let (_, give) = context.parse(ALICE, "/give rust_crafted::ore::iron").unwrap();
+        assert_eq!(give.material(0), Some(IronOre::into_block()));
+
+        let (_, time) = context.parse(CommandSender::Console, "/time night").unwrap();
+        assert_eq!(time.choice(0), Some("night"));
+        assert!(time.target(1).is_err());
+
+        let (_, kick) = context.parse(ALICE, "/kick bob  being   rude").unwrap();
+        assert_eq!(kick.player(0), Some(2));
+        assert_eq!(kick.text(1), Some("being rude"));
+    });
+}
+
+#[test]
+fn parsed_commands_run_their_handler() {
+    let mut app = server();
+    let (handler, invocation) =
+        with_context(&mut app, |context| context.parse(BOB, "/echo hello there")).unwrap();
+
+    let result = app.world_mut().run_system_with(handler, invocation).unwrap();
+    assert_eq!(result, Ok("hello there".to_string()));
+}
+
+#[test]
+fn argument_errors_show_the_usage() {
+    let mut app = server();
+    let usage = "usage: /tp <x> <y> <z> [player]";
+
+    for (line, error) in [
+        ("/tp 1 2", format!("Missing <z>, {usage}")),
+        ("/tp 1 two 3", format!("'two' isn't a number, {usage}")),
+        ("/tp 1 NaN 3", format!("'NaN' isn't a number, {usage}")),
+        ("/tp 1 2 3 dave", format!("No player named 'dave', {usage}")),
+        ("/tp 1 2 3 bob up", format!("Too many arguments, {usage}")),
+        (
+            "/give unobtainium",
+            "No material named 'unobtainium', usage: /give <material> [player]".to_string(),
+        ),
+        (
+            "/give ore",
+            "No material named 'ore', usage: /give <material> [player]".to_string(),
+        ),
+        (
+            "/gamemode flying",
+            "Expected one of creative, survival, usage: /gamemode <mode> [player]".to_string(),
+        ),
+        ("/dance", "Unknown command /dance, try /help".to_string()),
+        ("", "Unknown command /, try /help".to_string()),
+    ] {
+        assert_eq!(parse_error(&mut app, ALICE, line), error);
+    }
+}
+
+#[test]
+fn operator_commands_need_an_authenticated_operator() {
+    let mut app = server();
+
+    with_context(&mut app, |context| {
+        assert_eq!(context.permission(CommandSender::Console), Permission::Console);
+        assert_eq!(context.permission(ALICE), Permission::Operator);
+        assert_eq!(context.permission(BOB), Permission::Player);
+        assert_eq!(context.permission(CAROL), Permission::Player);
+
+        assert!(context.parse(BOB, "/seed").is_ok());
+        assert!(context.parse(CommandSender::Console, "/tp 0 0 0 carol").is_ok());
+    });
+
+    // Commands the sender can't run are hidden
+    for sender in [BOB, CAROL] {
+        assert_eq!(
+            parse_error(&mut app, sender, "/tp 0 0 0"),
+            "Unknown command /tp, try /help"
+        );
+    }
+}
+
+#[test]
+fn completions_follow_the_command_line() {
+    let mut app = server();
+
+    with_context(&mut app, |context| {
+        assert_eq!(context.complete(ALICE, "/t"), ["/time", "/tp"]);
+        assert_eq!(context.complete(CAROL, "/t"), Vec::<String>::new());
+        assert_eq!(context.complete(BOB, "/"), ["/echo", "/help", "/seed"]);
+        assert_eq!(context.complete(BOB, "se"), ["/seed"]);
+
+        assert_eq!(context.complete(ALICE, "/tp 1 2 3 "), ["alice", "bob", "carol"]);
+        assert_eq!(context.complete(ALICE, "/tp 1 2 3 b"), ["bob"]);
+        assert_eq!(context.complete(ALICE, "/give sto"), ["stone"]);
+        assert_eq!(
+            context.complete(ALICE, "/give ore"),
+            ["ore:coal", "ore:diamond", "ore:gold", "ore:iron"]
+        );
+        assert_eq!(context.complete(ALICE, "/gamemode "), ["creative", "survival"]);
+        assert_eq!(context.complete(ALICE, "/time mid"), ["midnight"]);
+
+        // Nothing is suggested past the arguments or for free text
+        assert_eq!(context.complete(ALICE, "/tp 1 2 3 bob "), Vec::<String>::new());
+        assert_eq!(context.complete(ALICE, "/echo "), Vec::<String>::new());
+        assert_eq!(context.complete(BOB, "/give st"), Vec::<String>::new());
+    });
+}
+
+#[test]
+#[should_panic(expected = "A command named 'echo' has already been registered.")]
+fn commands_are_registered_once() {
+    server().register_command(
+        CommandSpec::new("echo", "Repeats the text again").arg("text", ArgKind::Text),
+        echo,
+    );
+}
@@ -0,0 +1,131 @@
+use bevy::math::IVec3;
+use ilattice::glam::UVec3;
+use voxel_engine::{
+    chunk_extent, CaveSettings, ColumnTerrain, DensitySettings, TerrainShape, WorldSeed,
+    CAVE_MIN_Y, CHEESE_MIN_DEPTH, CHUNK_HEIGHT, CHUNK_SIZE, SURFACE_MARGIN,
+};
+
+const SEED: WorldSeed = WorldSeed(0xde45_17e5);
+
+const NO_CAVES: CaveSettings = CaveSettings {
+    cheese_threshold: 1.0,
+    worm_chance: 0.0,
+    worm_radius: 0.0,
+};
+
+/// Returns the terrain of `size` by `size` columns all at the same height.
+fn columns(
+    size: usize,
+    height: f32,
+    overhang_height: f32,
+    caves: CaveSettings,
+) -> Vec<ColumnTerrain> {
+    let density = DensitySettings {
+        overhang_height,
+        caves,
+    };
+    vec![ColumnTerrain { height, density }; size * size]
+}
+
+#[test]
+fn flat_terrain_has_its_surface_at_its_height() {
+    // The heightmap outweighs the 3D noise everywhere
+    let key = IVec3::new(64, 0, -32);
+    let flat = columns(CHUNK_SIZE, 120.5, 0.01, NO_CAVES);
+    let shape = TerrainShape::sample(SEED, key, CHUNK_SIZE, &flat);
+
+    for (x, z) in [(0, 0), (13, 7), (31, 31)] {
+        assert_eq!(shape.surface(x, z), Some(120));
+        assert!(shape.is_solid(UVec3::new(x, 120, z)));
+        assert!(!shape.is_solid(UVec3::new(x, 121, z)));
+
+        assert_eq!(shape.depth(UVec3::new(x, 116, z)), Some(4));
+        assert_eq!(shape.depth(UVec3::new(x, 0, z)), Some(120));
+        assert_eq!(shape.depth(UVec3::new(x, 126, z)), None);
+    }
+
+    // Blocks below surfaces higher than the margin above the chunk can't tell how deep they are
+    let deep = columns(CHUNK_SIZE, 500.5, 0.01, NO_CAVES);
+    let deep = TerrainShape::sample(SEED, key, CHUNK_SIZE, &deep);
+    assert_eq!(deep.surface(5, 5), None);
+    assert!(deep.is_solid(UVec3::new(5, (CHUNK_HEIGHT + SURFACE_MARGIN - 1) as u32, 5)));
+    assert_eq!(deep.depth(UVec3::new(5, 0, 5)), None);
+    assert_eq!(deep.depth(UVec3::new(5, CHUNK_HEIGHT as u32 - 1, 5)), None);
+}
+
+#[test]
+fn surfaces_stay_within_their_heights() {
+    let key = IVec3::ZERO;
+    let terrain = columns(CHUNK_SIZE, 112.5, 12.0, NO_CAVES);
+    let heights = terrain[0].surface_heights();
+    let shape = TerrainShape::sample(SEED, key, CHUNK_SIZE, &terrain);
+
+    let mut surfaces = 0;
+    for z in 0..CHUNK_SIZE as u32 {
+        for x in 0..CHUNK_SIZE as u32 {
+            if let Some(y) = shape.surface(x, z) {
+                assert!(heights.contains(&((key.y + y as i32) as f32)), "{x} {y} {z}");
+                surfaces += 1;
+            }
+        }
+    }
+
+    // The 3D noise moves the surface around rather than flattening it
+    assert!(surfaces > CHUNK_SIZE * CHUNK_SIZE / 2);
+    assert!((0..CHUNK_SIZE as u32).any(|x| shape.surface(x, 0) != shape.surface(0, 0)));
+}
+
+#[test]
+fn area_shapes_line_up_with_chunk_shapes() {
+    const MARGIN: usize = 8;
+
+    let key = IVec3::new(-32, 0, 32);
+    let terrain = columns(CHUNK_SIZE, 112.5, 12.0, NO_CAVES);
+    let chunk = TerrainShape::sample(SEED, key, CHUNK_SIZE, &terrain);
+
+    let size = CHUNK_SIZE + 2 * MARGIN;
+    let min = key - IVec3::new(MARGIN as i32, 0, MARGIN as i32);
+    let area = TerrainShape::sample(SEED, min, size, &columns(size, 112.5, 12.0, NO_CAVES));
+
+    let offset = UVec3::new(MARGIN as u32, 0, MARGIN as u32);
+    for pos in chunk_extent().iter3() {
+        assert_eq!(chunk.is_solid(pos), area.is_solid(pos + offset), "{pos}");
+    }
+}
+
+#[test]
+fn cheese_caves_keep_below_the_surface() {
+    // Every block the cheese caves can reach is hollowed out
+    let caves = CaveSettings {
+        cheese_threshold: -1.0,
+        ..NO_CAVES
+    };
+    let height = 40.5;
+    let terrain = columns(CHUNK_SIZE, height, 0.01, caves);
+    let shape = TerrainShape::sample(SEED, IVec3::ZERO, CHUNK_SIZE, &terrain);
+
+    for pos in chunk_extent().iter3() {
+        let y = pos.y as f32;
+        let reachable = y >= CAVE_MIN_Y as f32 && y < height - CHEESE_MIN_DEPTH;
+        assert_eq!(shape.is_cave(pos), reachable, "{pos}");
+    }
+}
+
+#[test]
+fn blended_settings_are_weighted_averages() {
+    let settings = |overhang_height, worm_chance| DensitySettings {
+        overhang_height,
+        caves: CaveSettings {
+            worm_chance,
+            ..NO_CAVES
+        },
+    };
+
+    let blended = DensitySettings::blend([(1.0, settings(4.0, 0.0)), (3.0, settings(12.0, 0.4))]);
+    assert_eq!(blended.overhang_height, 10.0);
+    assert!((blended.caves.worm_chance - 0.3).abs() < 1e-6);
+    assert_eq!(blended.caves.cheese_threshold, 1.0);
+
+    let nothing = DensitySettings::blend([]);
+    assert_eq!(nothing.overhang_height, DensitySettings::default().overhang_height);
+}
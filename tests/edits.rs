@@ -0,0 +1,99 @@
+use bevy::{ecs::system::RunSystemOnce, prelude::*};
+use voxel_engine::{
+    neighbour_offsets, Block, BlockChanged, BlockMaterial, ChunkMap, ChunkShape, DirtyChunks,
+    Stone, WorldEdits, CHUNK_DIMS,
+};
+
+/// Returns a world with the chunk at the origin and all of its neighbours loaded and clean.
+fn world() -> World {
+    let mut chunks = ChunkMap::<Block, ChunkShape>::new(ChunkShape {});
+    chunks.insert_empty(IVec3::ZERO);
+    for offset in neighbour_offsets() {
+        chunks.insert_empty(offset * CHUNK_DIMS);
+    }
+
+    let mut world = World::new();
+    world.insert_resource(chunks);
+    world.init_resource::<DirtyChunks>();
+    world.init_resource::<Events<BlockChanged>>();
+    world
+}
+
+/// Places stone at `pos` and returns the offsets of the chunks marked dirty, sorted.
+fn dirtied_by_placing(pos: IVec3) -> Vec<IVec3> {
+    let mut world = world();
+    world
+        .run_system_once(move |mut edits: WorldEdits| {
+            edits.set_block(pos, Stone::into_block());
+        })
+        .unwrap();
+
+    let mut dirty: Vec<_> = world
+        .resource::<DirtyChunks>()
+        .iter_dirty()
+        .map(|key| *key / CHUNK_DIMS)
+        .collect();
+    dirty.sort_by_key(|offset| offset.to_array());
+    dirty
+}
+
+fn offsets(offsets: &[[i32; 3]]) -> Vec<IVec3> {
+    let mut offsets: Vec<_> = offsets.iter().map(|offset| IVec3::from_array(*offset)).collect();
+    offsets.sort_by_key(|offset| offset.to_array());
+    offsets
+}
+
+#[test]
+fn inner_blocks_only_dirty_their_chunk() {
+    assert_eq!(dirtied_by_placing(IVec3::new(5, 100, 7)), offsets(&[[0, 0, 0]]));
+}
+
+#[test]
+fn border_blocks_dirty_the_chunks_they_touch() {
+    let face = IVec3::new(0, 100, 7);
+    assert_eq!(dirtied_by_placing(face), offsets(&[[0, 0, 0], [-1, 0, 0]]));
+
+    let edge = IVec3::new(CHUNK_DIMS.x - 1, 100, 0);
+    assert_eq!(
+        dirtied_by_placing(edge),
+        offsets(&[[0, 0, 0], [1, 0, 0], [0, 0, -1], [1, 0, -1]])
+    );
+
+    let corner = IVec3::new(0, CHUNK_DIMS.y - 1, CHUNK_DIMS.z - 1);
+    assert_eq!(
+        dirtied_by_placing(corner),
+        offsets(&[
+            [0, 0, 0],
+            [-1, 0, 0],
+            [0, 1, 0],
+            [0, 0, 1],
+            [-1, 1, 0],
+            [-1, 0, 1],
+            [0, 1, 1],
+            [-1, 1, 1],
+        ])
+    );
+}
+
+#[test]
+fn changes_are_notified_once_per_changed_block() {
+    let mut world = world();
+    let pos = IVec3::new(3, 4, 5);
+
+    let olds = world
+        .run_system_once(move |mut edits: WorldEdits| {
+            let first = edits.set_block(pos, Stone::into_block());
+            let again = edits.set_block(pos, Stone::into_block());
+            let unloaded = edits.set_block(CHUNK_DIMS * 5, Stone::into_block());
+            (first, again, unloaded)
+        })
+        .unwrap();
+    assert_eq!(olds, (Some(Block::EMPTY_BLOCK), Some(Stone::into_block()), None));
+
+    let events = world.resource::<Events<BlockChanged>>();
+    let changes: Vec<_> = events.iter_current_update_events().collect();
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].pos, pos);
+    assert_eq!(changes[0].old, Block::EMPTY_BLOCK);
+    assert_eq!(changes[0].new, Stone::into_block());
+}
@@ -0,0 +1,334 @@
+use bevy::prelude::*;
+use std::marker::PhantomData;
+use ilattice::glam::{UVec2, UVec3};
+use voxel_engine::{
+    chunk_extent, encode_chunk, place_ores, BiomeTerrainGenerator, Block, BlockBaseMaterialsPlugin,
+    BlockBuffer, BlockMaterial, BlockMaterialPlugin, BlockMaterialRegistry, CaveSettings,
+    ChunkShape, ClimateNoise, ClimateRange, DensitySettings, IntoBoxedTerrainGenerator, IronOre,
+    MaterialIdTable, OreDefinition, PlainsBiomeTerrainGenerator, Sand, Snow, Stone,
+    TerrainGenerator, TerrainGeneratorPlugin, TerrainShape, WorldSeed, CHUNK_HEIGHT, CHUNK_SIZE,
+    TERRAIN_GENERATOR,
+};
+
+/// Heights the iron veins of the test generator are placed between.
+const IRON_HEIGHTS: std::ops::Range<i32> = 16..80;
+
+/// Columns spread around the world, far apart enough to land in different climates.
+const COLUMNS: [IVec2; 3] = [IVec2::new(0, 0), IVec2::new(64, -32), IVec2::new(-320, 480)];
+
+fn materials() -> MaterialIdTable {
+    let mut app = App::new();
+    app.add_plugins((BlockMaterialPlugin, BlockBaseMaterialsPlugin));
+
+    MaterialIdTable::from(app.world().resource::<BlockMaterialRegistry>())
+}
+
+/// Generates every chunk of the columns and returns their encoded bytes.
+fn generate_columns(
+    generator: &TerrainGenerator,
+    materials: &MaterialIdTable,
+    seed: WorldSeed,
+) -> Vec<Vec<u8>> {
+    COLUMNS
+        .iter()
+        .flat_map(|column| {
+            [0, CHUNK_HEIGHT as i32].map(|y| IVec3::new(column.x, y, column.y))
+        })
+        .map(|key| {
+            let mut buffer = BlockBuffer::<Block, ChunkShape>::new_empty(ChunkShape {});
+            generator.generate(seed, key, &mut buffer);
+            encode_chunk(&buffer, materials).unwrap()
+        })
+        .collect()
+}
+
+fn plains_generator() -> TerrainGenerator {
+    let mut generator = TerrainGenerator::default();
+    generator
+        .register_biome(ClimateRange::ANY, PlainsBiomeTerrainGenerator.into_boxed_generator())
+        .register_ore(OreDefinition::new::<IronOre>(IRON_HEIGHTS, 8, 8));
+    generator
+}
+
+#[test]
+fn same_seed_generates_identical_chunks() {
+    let generator = plains_generator();
+    let materials = materials();
+    let seed = WorldSeed(0x5eed_1234_abcd);
+
+    assert_eq!(
+        generate_columns(&generator, &materials, seed),
+        generate_columns(&generator, &materials, seed)
+    );
+}
+
+#[test]
+fn climate_biomes_generate_identical_chunks() {
+    // Registering the materials also registers the built-in ores in the shared generator
+    let materials = materials();
+    App::new().add_plugins(TerrainGeneratorPlugin);
+
+    let generator = TERRAIN_GENERATOR.read().unwrap();
+    let seed = WorldSeed(0x5eed_1234_abcd);
+
+    assert_eq!(
+        generate_columns(&generator, &materials, seed),
+        generate_columns(&generator, &materials, seed)
+    );
+}
+
+#[test]
+fn plugins_built_again_register_nothing_twice() {
+    let materials = materials();
+    App::new().add_plugins(TerrainGeneratorPlugin);
+    let seed = WorldSeed(0x5eed_1234_abcd);
+    let generated = generate_columns(&TERRAIN_GENERATOR.read().unwrap(), &materials, seed);
+
+    // Every app registers the built-in biomes and ores in the shared generator again
+    App::new().add_plugins((BlockMaterialPlugin, BlockBaseMaterialsPlugin, TerrainGeneratorPlugin));
+    assert_eq!(
+        generate_columns(&TERRAIN_GENERATOR.read().unwrap(), &materials, seed),
+        generated
+    );
+}
+
+#[test]
+fn different_seeds_generate_different_chunks() {
+    let generator = plains_generator();
+    let materials = materials();
+
+    assert_ne!(
+        generate_columns(&generator, &materials, WorldSeed(1)),
+        generate_columns(&generator, &materials, WorldSeed(2))
+    );
+}
+
+#[test]
+fn derived_seeds_differ_per_feature() {
+    let seed = WorldSeed(42);
+
+    assert_eq!(seed.derive("height"), seed.derive("height"));
+    assert_ne!(seed.derive("height"), seed.derive("biomes"));
+    assert_ne!(seed.derive("height"), WorldSeed(43).derive("height"));
+}
+
+#[test]
+fn ores_are_placed_within_their_heights() {
+    let generator = plains_generator();
+    let mut buffer = BlockBuffer::<Block, ChunkShape>::new_empty(ChunkShape {});
+    generator.generate(WorldSeed(7), IVec3::ZERO, &mut buffer);
+
+    let iron_heights: Vec<_> = chunk_extent()
+        .iter3()
+        .filter(|pos| buffer.block_at(*pos) == IronOre::into_block())
+        .map(|pos| pos.y as i32)
+        .collect();
+
+    assert!(!iron_heights.is_empty());
+    assert!(iron_heights.iter().all(|y| IRON_HEIGHTS.contains(y)));
+}
+
+/// Biome with a flat surface covered with `S`, to tell which biome carved a column.
+struct FlatBiome<S> {
+    height: f32,
+    surface: PhantomData<S>,
+}
+
+const LOWLANDS: FlatBiome<Sand> = FlatBiome {
+    height: 136.5,
+    surface: PhantomData,
+};
+
+const HIGHLANDS: FlatBiome<Snow> = FlatBiome {
+    height: 152.5,
+    surface: PhantomData,
+};
+
+impl<S: BlockMaterial + Send + Sync + 'static> BiomeTerrainGenerator for FlatBiome<S> {
+    fn density_settings(&self) -> DensitySettings {
+        // The heightmap outweighs the 3D noise everywhere, and no caves are carved
+        DensitySettings {
+            overhang_height: 0.01,
+            caves: CaveSettings {
+                cheese_threshold: 1.0,
+                worm_chance: 0.0,
+                worm_radius: 0.0,
+            },
+        }
+    }
+
+    fn terrain_height(&self, _noise: f32) -> f32 {
+        self.height
+    }
+
+    fn carve_column(
+        &self,
+        _seed: WorldSeed,
+        _chunk_key: IVec3,
+        column: UVec2,
+        shape: &TerrainShape,
+        buffer: &mut BlockBuffer<Block, ChunkShape>,
+    ) {
+        if let Some(y) = shape.surface(column.x, column.y) {
+            *buffer.block_at_mut(UVec3::new(column.x, y, column.y)) = S::into_block();
+        }
+    }
+
+    fn decorate_column(
+        &self,
+        _seed: WorldSeed,
+        _surface: IVec3,
+        _chunk_key: IVec3,
+        _buffer: &mut BlockBuffer<Block, ChunkShape>,
+    ) {
+    }
+}
+
+/// Returns the world height and the block of the surface of the first column of a chunk.
+fn flat_surface(generator: &TerrainGenerator, seed: WorldSeed, column: IVec2) -> (i32, Block) {
+    let key = IVec3::new(column.x, 0, column.y);
+    let mut buffer = BlockBuffer::<Block, ChunkShape>::new_empty(ChunkShape {});
+    generator.generate(seed, key, &mut buffer);
+
+    (0..CHUNK_HEIGHT as u32)
+        .rev()
+        .map(|y| (y as i32, buffer.block_at(UVec3::new(0, y, 0))))
+        .find(|(_, block)| *block != Block::EMPTY_BLOCK)
+        .unwrap()
+}
+
+/// Returns the first chunk column whose first column has a temperature within `temperatures`.
+fn column_with_temperature(seed: WorldSeed, temperatures: std::ops::Range<f32>) -> IVec2 {
+    let climate = ClimateNoise::new(seed);
+
+    (-64..64)
+        .flat_map(|z| (-64..64).map(move |x| IVec2::new(x, z) * CHUNK_SIZE as i32))
+        .find(|column| temperatures.contains(&climate.at(*column).temperature))
+        .expect("No column in the temperatures")
+}
+
+fn flat_generator() -> TerrainGenerator {
+    let mut generator = TerrainGenerator::default();
+    generator
+        .register_biome(
+            ClimateRange {
+                temperature: -1.0..0.0,
+                ..ClimateRange::ANY
+            },
+            LOWLANDS.into_boxed_generator(),
+        )
+        .register_biome(
+            ClimateRange {
+                temperature: 0.0..1.0,
+                ..ClimateRange::ANY
+            },
+            HIGHLANDS.into_boxed_generator(),
+        );
+    generator
+}
+
+#[test]
+fn biomes_are_picked_by_climate() {
+    let generator = flat_generator();
+    let seed = WorldSeed(11);
+
+    let cold = column_with_temperature(seed, -1.0..-0.2);
+    assert_eq!(flat_surface(&generator, seed, cold), (136, Sand::into_block()));
+
+    let warm = column_with_temperature(seed, 0.2..1.0);
+    assert_eq!(flat_surface(&generator, seed, warm), (152, Snow::into_block()));
+}
+
+#[test]
+fn biome_borders_blend_their_heights() {
+    let generator = flat_generator();
+    let seed = WorldSeed(11);
+
+    let border = column_with_temperature(seed, -0.01..0.01);
+    let (height, surface) = flat_surface(&generator, seed, border);
+
+    assert!(height > 136 && height < 152, "border surface at {height}");
+    assert!(surface == Sand::into_block() || surface == Snow::into_block());
+}
+
+#[test]
+fn registering_a_biome_again_replaces_it() {
+    let mut generator = TerrainGenerator::default();
+    generator
+        .register_biome(ClimateRange::ANY, LOWLANDS.into_boxed_generator())
+        .register_biome(ClimateRange::ANY, HIGHLANDS.into_boxed_generator())
+        .register_biome(
+            ClimateRange {
+                temperature: 5.0..6.0,
+                ..ClimateRange::ANY
+            },
+            HIGHLANDS.into_boxed_generator(),
+        );
+
+    // The highlands were moved out of every climate, rather than blended with the lowlands
+    let seed = WorldSeed(11);
+    for column in COLUMNS {
+        assert_eq!(flat_surface(&generator, seed, column), (136, Sand::into_block()));
+    }
+}
+
+/// Returns a chunk of stone with the veins of `ore` crossing it placed in it.
+fn stone_with_veins(ore: &OreDefinition, key: IVec3) -> BlockBuffer<Block, ChunkShape> {
+    let mut buffer = BlockBuffer::new(ChunkShape {}, Stone::into_block());
+    place_ores(WorldSeed(3), key, std::slice::from_ref(ore), &mut buffer);
+    buffer
+}
+
+#[test]
+fn veins_cross_chunk_borders_like_any_other_block() {
+    let ore = OreDefinition::new::<IronOre>(0..CHUNK_HEIGHT as i32, 12, 128);
+    let size = CHUNK_SIZE as u32;
+    let iron = IronOre::into_block();
+
+    // Counts the ore blocks right next to each other across the border between two chunks, and
+    // across the middle of a chunk
+    let (mut border, mut middle) = (0, 0);
+    for z in 0..16 {
+        let key = IVec3::new(0, 0, z * CHUNK_SIZE as i32);
+        let left = stone_with_veins(&ore, key - IVec3::X * CHUNK_SIZE as i32);
+        let right = stone_with_veins(&ore, key);
+
+        for (y, z) in (0..CHUNK_HEIGHT as u32).flat_map(|y| (0..size).map(move |z| (y, z))) {
+            let pair = |a: &BlockBuffer<_, _>, ax, b: &BlockBuffer<_, _>, bx| {
+                a.block_at(UVec3::new(ax, y, z)) == iron
+                    && b.block_at(UVec3::new(bx, y, z)) == iron
+            };
+            border += pair(&left, size - 1, &right, 0) as u32;
+            middle += pair(&right, size / 2 - 1, &right, size / 2) as u32;
+        }
+    }
+
+    let ratio = border as f32 / middle as f32;
+    assert!((0.75..1.33).contains(&ratio), "{border} pairs across borders, {middle} inside");
+}
+
+#[test]
+fn veins_crossing_chunks_stay_within_their_heights() {
+    let top = CHUNK_HEIGHT as i32;
+    let heights = top - 4..top + 4;
+    let ore = OreDefinition::new::<IronOre>(heights.clone(), 12, 128);
+    let mut iron_heights = Vec::new();
+
+    for x in 0..8 {
+        for y in [0, top] {
+            let key = IVec3::new(x * CHUNK_SIZE as i32, y, 0);
+            let buffer = stone_with_veins(&ore, key);
+
+            iron_heights.extend(
+                chunk_extent()
+                    .iter3()
+                    .filter(|pos| buffer.block_at(*pos) == IronOre::into_block())
+                    .map(|pos| key.y + pos.y as i32),
+            );
+        }
+    }
+
+    assert!(iron_heights.iter().all(|y| heights.contains(y)));
+    assert!(iron_heights.iter().any(|y| *y < top));
+    assert!(iron_heights.iter().any(|y| *y >= top));
+}
@@ -0,0 +1,76 @@
+use bevy::math::Vec3;
+use std::f32::consts::PI;
+use voxel_engine::{client::network::SnapshotBuffer, PlayerSnapshot};
+
+fn at(x: f32, yaw: f32) -> PlayerSnapshot {
+    PlayerSnapshot {
+        id: 1,
+        position: Vec3::new(x, 0., 0.),
+        yaw,
+        pitch: 0.,
+    }
+}
+
+/// Returns a buffer holding a snapshot at each of the `ticks`, with the player at x = tick.
+fn buffer_of(ticks: &[u32]) -> SnapshotBuffer {
+    let mut buffer = SnapshotBuffer::default();
+    for tick in ticks {
+        buffer.push(*tick, at(*tick as f32, 0.));
+    }
+    buffer
+}
+
+fn x_at(buffer: &mut SnapshotBuffer, tick: f64) -> Option<f32> {
+    buffer.sample(tick).map(|snapshot| snapshot.position.x)
+}
+
+#[test]
+fn players_are_interpolated_between_snapshots() {
+    let mut buffer = buffer_of(&[10, 12, 14]);
+
+    assert_eq!(x_at(&mut buffer, 10.), Some(10.));
+    assert_eq!(x_at(&mut buffer, 11.), Some(11.));
+    assert_eq!(x_at(&mut buffer, 12.5), Some(12.5));
+}
+
+#[test]
+fn players_are_held_past_the_latest_snapshot() {
+    let mut buffer = buffer_of(&[10, 12]);
+
+    // Nothing is extrapolated, the player waits at the latest known position
+    assert_eq!(x_at(&mut buffer, 15.), Some(12.));
+    assert_eq!(x_at(&mut buffer, 30.), Some(12.));
+}
+
+#[test]
+fn players_are_held_before_the_first_snapshot() {
+    let mut buffer = buffer_of(&[10, 12]);
+
+    assert_eq!(x_at(&mut buffer, 8.), Some(10.));
+    assert_eq!(x_at(&mut buffer, 11.), Some(11.));
+}
+
+#[test]
+fn empty_buffers_have_nothing_to_sample() {
+    assert_eq!(x_at(&mut SnapshotBuffer::default(), 10.), None);
+}
+
+#[test]
+fn late_snapshots_are_dropped() {
+    let mut buffer = buffer_of(&[10, 14]);
+    buffer.push(12, at(100., 0.));
+    buffer.push(14, at(100., 0.));
+
+    assert_eq!(x_at(&mut buffer, 12.), Some(12.));
+    assert_eq!(x_at(&mut buffer, 14.), Some(14.));
+}
+
+#[test]
+fn yaw_turns_the_short_way_around() {
+    let mut buffer = SnapshotBuffer::default();
+    buffer.push(0, at(0., PI - 0.1));
+    buffer.push(2, at(0., -PI + 0.1));
+
+    let yaw = buffer.sample(1.).unwrap().yaw;
+    assert!((yaw.abs() - PI).abs() < 1e-4, "{yaw}");
+}
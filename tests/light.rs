@@ -0,0 +1,166 @@
+use bevy::prelude::*;
+use voxel_engine::{
+    Block, BlockBaseMaterialsPlugin, BlockMaterial, BlockMaterialFlags, BlockMaterialPlugin,
+    BlockMaterialRegistry, ChunkMap, ChunkShape, LightMap, LightMaterials, LightPropagator, Stone,
+    MAX_LIGHT,
+};
+
+const CHUNK: i32 = 32;
+
+struct Lamp;
+
+impl BlockMaterial for Lamp {
+    const ID: u64 = 100;
+
+    fn block_name() -> &'static str { "lamp" }
+    fn base_color() -> Color { Color::WHITE }
+    fn flags() -> BlockMaterialFlags { BlockMaterialFlags::SOLID }
+    fn emissive() -> Color { Color::WHITE }
+}
+
+struct TestWorld {
+    chunks: ChunkMap<Block, ChunkShape>,
+    light: LightMap,
+    materials: LightMaterials,
+}
+
+impl TestWorld {
+    /// Loads empty chunks at the specified minimums, without lighting them.
+    fn new(keys: &[IVec3]) -> Self {
+        let mut app = App::new();
+        app.add_plugins((BlockMaterialPlugin, BlockBaseMaterialsPlugin));
+        app.world_mut()
+            .resource_mut::<BlockMaterialRegistry>()
+            .register::<Lamp>();
+
+        let mut chunks = ChunkMap::new(ChunkShape {});
+        for key in keys {
+            chunks.insert_empty(*key);
+        }
+
+        Self {
+            chunks,
+            light: LightMap::default(),
+            materials: LightMaterials::from(app.world().resource::<BlockMaterialRegistry>()),
+        }
+    }
+
+    fn light_chunks(&mut self, keys: &[IVec3]) {
+        let mut propagator = LightPropagator::new(&self.chunks, &mut self.light, &self.materials);
+        for key in keys {
+            propagator.light_chunk(*key);
+        }
+    }
+
+    /// Changes a block of a lit world and updates the light around it.
+    fn set_block(&mut self, pos: IVec3, block: Block) {
+        self.chunks.set_block(pos, block);
+        LightPropagator::new(&self.chunks, &mut self.light, &self.materials)
+            .update_block(pos, block);
+    }
+
+    /// Covers the columns `x` of the chunk at `key` with a layer of stone at height `y`.
+    fn roof(&mut self, key: IVec3, x: std::ops::Range<i32>, y: i32) {
+        for x in x {
+            for z in 0..CHUNK {
+                self.chunks.set_block(key + IVec3::new(x, y, z), Stone::into_block());
+            }
+        }
+    }
+}
+
+#[test]
+fn open_sky_lights_every_empty_voxel() {
+    let mut world = TestWorld::new(&[IVec3::ZERO]);
+    world.light_chunks(&[IVec3::ZERO]);
+
+    for pos in [IVec3::new(0, 255, 0), IVec3::new(5, 100, 17), IVec3::new(31, 0, 31)] {
+        assert_eq!(world.light.sky_light_at(pos), MAX_LIGHT);
+        assert_eq!(world.light.block_light_at(pos), 0);
+    }
+}
+
+#[test]
+fn sky_light_fades_under_a_roof() {
+    let mut world = TestWorld::new(&[IVec3::ZERO]);
+    world.roof(IVec3::ZERO, 0..16, 100);
+    world.light_chunks(&[IVec3::ZERO]);
+
+    // Light falls straight down the open columns and spreads sideways under the roof
+    assert_eq!(world.light.sky_light_at(IVec3::new(16, 50, 10)), MAX_LIGHT);
+    assert_eq!(world.light.sky_light_at(IVec3::new(15, 99, 10)), MAX_LIGHT - 1);
+    assert_eq!(world.light.sky_light_at(IVec3::new(10, 50, 10)), MAX_LIGHT - 6);
+    assert_eq!(world.light.sky_light_at(IVec3::new(2, 0, 10)), 1);
+    assert_eq!(world.light.sky_light_at(IVec3::new(1, 0, 10)), 0);
+    assert_eq!(world.light.sky_light_at(IVec3::new(10, 101, 10)), MAX_LIGHT);
+}
+
+#[test]
+fn sky_light_spreads_under_the_roof_of_a_lit_neighbour() {
+    let roofed = IVec3::new(CHUNK, 0, 0);
+    let mut world = TestWorld::new(&[IVec3::ZERO, roofed]);
+    world.roof(roofed, 0..CHUNK, 100);
+
+    world.light_chunks(&[roofed]);
+    assert_eq!(world.light.sky_light_at(roofed + IVec3::new(0, 50, 10)), 0);
+
+    // The open chunk lit next to it has to light the whole height shadowed by the roof
+    world.light_chunks(&[IVec3::ZERO]);
+    assert_eq!(world.light.sky_light_at(roofed + IVec3::new(0, 99, 10)), MAX_LIGHT - 1);
+    assert_eq!(world.light.sky_light_at(roofed + IVec3::new(0, 50, 10)), MAX_LIGHT - 1);
+    assert_eq!(world.light.sky_light_at(roofed + IVec3::new(3, 50, 10)), MAX_LIGHT - 4);
+    assert_eq!(world.light.sky_light_at(roofed + IVec3::new(3, 101, 10)), MAX_LIGHT);
+}
+
+#[test]
+fn block_light_spreads_from_emissive_blocks() {
+    let lamp = IVec3::new(10, 50, 10);
+    let mut world = TestWorld::new(&[IVec3::ZERO]);
+    world.chunks.set_block(lamp, Lamp::into_block());
+    world.light_chunks(&[IVec3::ZERO]);
+
+    assert_eq!(world.light.block_light_at(lamp), MAX_LIGHT);
+    assert_eq!(world.light.block_light_at(lamp + IVec3::X * 3), MAX_LIGHT - 3);
+    assert_eq!(world.light.block_light_at(lamp + IVec3::new(2, -3, 1)), MAX_LIGHT - 6);
+    assert_eq!(world.light.block_light_at(lamp + IVec3::Y * 14), 1);
+    assert_eq!(world.light.block_light_at(lamp + IVec3::Y * 15), 0);
+}
+
+#[test]
+fn removed_lamps_take_their_light_away() {
+    let (first, second) = (IVec3::new(5, 50, 10), IVec3::new(15, 50, 10));
+    let mut world = TestWorld::new(&[IVec3::ZERO]);
+    world.light_chunks(&[IVec3::ZERO]);
+
+    world.set_block(first, Lamp::into_block());
+    world.set_block(second, Lamp::into_block());
+    assert_eq!(world.light.block_light_at(IVec3::new(10, 50, 10)), MAX_LIGHT - 5);
+
+    // Voxels closer to the other lamp keep its light
+    world.set_block(first, Block::EMPTY_BLOCK);
+    assert_eq!(world.light.block_light_at(first), MAX_LIGHT - 10);
+    assert_eq!(world.light.block_light_at(first - IVec3::X * 4), 1);
+    assert_eq!(world.light.block_light_at(IVec3::new(10, 50, 10)), MAX_LIGHT - 5);
+
+    world.set_block(second, Block::EMPTY_BLOCK);
+    for x in 0..CHUNK {
+        assert_eq!(world.light.block_light_at(IVec3::new(x, 50, 10)), 0);
+    }
+}
+
+#[test]
+fn placed_blocks_cast_a_shadow_until_removed() {
+    let pos = IVec3::new(10, 100, 10);
+    let mut world = TestWorld::new(&[IVec3::ZERO]);
+    world.light_chunks(&[IVec3::ZERO]);
+
+    world.set_block(pos, Stone::into_block());
+    assert_eq!(world.light.sky_light_at(pos), 0);
+    assert_eq!(world.light.sky_light_at(pos - IVec3::Y), MAX_LIGHT - 1);
+    assert_eq!(world.light.sky_light_at(IVec3::new(10, 0, 10)), MAX_LIGHT - 1);
+    assert_eq!(world.light.sky_light_at(IVec3::new(11, 50, 10)), MAX_LIGHT);
+
+    world.set_block(pos, Block::EMPTY_BLOCK);
+    assert_eq!(world.light.sky_light_at(pos), MAX_LIGHT);
+    assert_eq!(world.light.sky_light_at(IVec3::new(10, 0, 10)), MAX_LIGHT);
+}
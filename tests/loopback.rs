@@ -0,0 +1,227 @@
+use bevy::{prelude::*, transform::TransformPlugin};
+use bevy_renet::renet::{ClientId, RenetClient, RenetServer};
+use std::time::Duration;
+use voxel_engine::{
+    client::{network::ClientNetworkPlugin, systems::BlockEditRequest},
+    server::{commands::CommandsPlugin, network::ServerNetworkPlugin, world::ServerWorldPlugin},
+    Bedrock, Block, BlockBaseMaterialsPlugin, BlockMaterial, BlockMaterialPlugin,
+    BlockOnTerrainGen, ChunkLoadRadius, ChunkMap, ChunkShape, ChunkingPlugin, LinkConditions,
+    LoopbackHarness, NetworkMessage, Stone, CHUNK_HEIGHT, PROTOCOL_VERSION, RELIABLE_CHANNEL,
+};
+
+const STEP: Duration = Duration::from_nanos(16_666_667);
+const MAX_STEPS: usize = 600;
+
+/// Steps the game harnesses wait for, for the players to join and get their chunks.
+const MAX_GAME_STEPS: usize = 3000;
+
+/// Chunks streamed to the players, which spawn in the chunk at the origin.
+const STREAMED_CHUNKS: [IVec3; 2] = [IVec3::ZERO, IVec3::new(0, -(CHUNK_HEIGHT as i32), 0)];
+
+fn lossy_harness(seed: u64) -> LoopbackHarness {
+    let mut server = App::new();
+    server.add_plugins(MinimalPlugins);
+
+    let clients = (0..2).map(|_| {
+        let mut client = App::new();
+        client.add_plugins(MinimalPlugins);
+        client
+    });
+
+    LoopbackHarness::new(
+        server,
+        clients,
+        LinkConditions {
+            latency: Duration::from_millis(50),
+            jitter: Duration::from_millis(30),
+            packet_loss: 0.2,
+        },
+        seed,
+        STEP,
+    )
+}
+
+fn all_connected(harness: &mut LoopbackHarness) -> bool {
+    harness
+        .clients
+        .iter()
+        .all(|client| client.world().resource::<RenetClient>().is_connected())
+        && harness.server.world().resource::<RenetServer>().connected_clients() == 2
+}
+
+/// Sends a login from the first client and returns after how many steps the server got it.
+fn deliver_login(harness: &mut LoopbackHarness) -> Option<usize> {
+    let sender: ClientId = 1;
+    let login = NetworkMessage::Login {
+        protocol_version: PROTOCOL_VERSION,
+    };
+
+    harness.clients[0]
+        .world_mut()
+        .resource_mut::<RenetClient>()
+        .send_message(RELIABLE_CHANNEL, login.to_bytes().unwrap());
+
+    let mut steps = 0;
+    let mut received = None;
+    let delivered = harness.step_until(MAX_STEPS, |harness| {
+        steps += 1;
+        received = harness
+            .server
+            .world_mut()
+            .resource_mut::<RenetServer>()
+            .receive_message(sender, RELIABLE_CHANNEL)
+            .map(|bytes| NetworkMessage::from_bytes(&bytes).unwrap());
+
+        received.is_some()
+    });
+
+    assert!(matches!(
+        received,
+        Some(NetworkMessage::Login { protocol_version }) if protocol_version == PROTOCOL_VERSION
+    ));
+
+    delivered.then_some(steps)
+}
+
+#[test]
+fn reliable_messages_survive_a_lossy_link() {
+    let mut harness = lossy_harness(7);
+
+    assert!(harness.step_until(MAX_STEPS, all_connected));
+    assert!(deliver_login(&mut harness).is_some());
+}
+
+#[test]
+fn same_seed_replays_the_same_run() {
+    let mut first = lossy_harness(42);
+    let mut second = lossy_harness(42);
+
+    assert!(first.step_until(MAX_STEPS, all_connected));
+    assert!(second.step_until(MAX_STEPS, all_connected));
+    assert_eq!(deliver_login(&mut first), deliver_login(&mut second));
+}
+
+/// Returns a harness running the game plugins of a server and of two clients over a clean link.
+fn game_harness() -> LoopbackHarness {
+    let mut server = App::new();
+    server
+        .add_plugins((MinimalPlugins, TransformPlugin))
+        .add_plugins(ServerWorldPlugin {
+            seed: Some(7),
+            world_dir: None,
+        })
+        .add_plugins(ServerNetworkPlugin)
+        .add_plugins(CommandsPlugin {
+            operators: Vec::new(),
+        })
+        .insert_resource(ChunkLoadRadius {
+            horizontal: 1,
+            vertical: 1,
+        })
+        .init_resource::<BlockOnTerrainGen>();
+
+    let clients = (0..2).map(|_| {
+        let mut client = App::new();
+        client
+            .add_plugins((MinimalPlugins, TransformPlugin))
+            .insert_resource(ChunkMap::<Block, ChunkShape>::new(ChunkShape {}))
+            .add_plugins((ChunkingPlugin, BlockMaterialPlugin, BlockBaseMaterialsPlugin))
+            .add_plugins(ClientNetworkPlugin {
+                simulated_latency: Duration::ZERO,
+            });
+        client
+    });
+
+    let conditions = LinkConditions {
+        latency: Duration::from_millis(20),
+        ..Default::default()
+    };
+    LoopbackHarness::new(server, clients, conditions, 0, STEP)
+}
+
+fn chunks(app: &App) -> &ChunkMap<Block, ChunkShape> {
+    app.world().resource::<ChunkMap<Block, ChunkShape>>()
+}
+
+fn all_streamed(harness: &mut LoopbackHarness) -> bool {
+    harness.clients.iter().all(|client| {
+        STREAMED_CHUNKS.iter().all(|key| chunks(client).exists(*key))
+    })
+}
+
+#[test]
+fn chunks_are_streamed_to_every_client() {
+    let mut harness = game_harness();
+    assert!(harness.step_until(MAX_GAME_STEPS, all_streamed));
+
+    for key in STREAMED_CHUNKS {
+        let server_chunk = chunks(&harness.server).buffer_at(key).unwrap();
+        for client in harness.clients.iter() {
+            assert_eq!(chunks(client).buffer_at(key).unwrap().slice(), server_chunk.slice());
+        }
+    }
+}
+
+#[test]
+fn chunks_are_streamed_in_as_many_steps_every_run() {
+    let steps_to_stream = || {
+        let mut harness = game_harness();
+        let mut steps = 0;
+        assert!(harness.step_until(MAX_GAME_STEPS, |harness| {
+            steps += 1;
+            all_streamed(harness)
+        }));
+        steps
+    };
+
+    assert_eq!(steps_to_stream(), steps_to_stream());
+}
+
+#[test]
+fn block_edits_are_replicated_to_the_other_clients() {
+    let mut harness = game_harness();
+    assert!(harness.step_until(MAX_GAME_STEPS, |harness| {
+        harness.clients.iter().all(|client| chunks(client).exists(IVec3::ZERO))
+    }));
+
+    // Next to the players, who spawn at the same place high above the ground
+    let pos = IVec3::new(4, 158, 2);
+    let stone = Stone::into_block();
+    assert_eq!(chunks(&harness.server).block_at(pos), Some(Block::EMPTY_BLOCK));
+
+    harness.clients[0].world_mut().send_event(BlockEditRequest {
+        pos,
+        previous: Block::EMPTY_BLOCK,
+        block: stone,
+    });
+
+    // The first client predicted the edit, the second one only learns about it from the server
+    assert!(harness.step_until(MAX_GAME_STEPS, |harness| {
+        chunks(&harness.clients[1]).block_at(pos) == Some(stone)
+    }));
+}
+
+#[test]
+fn unbreakable_blocks_cant_be_placed() {
+    let mut harness = game_harness();
+    assert!(harness.step_until(MAX_GAME_STEPS, |harness| {
+        harness.clients.iter().all(|client| chunks(client).exists(IVec3::ZERO))
+    }));
+
+    let (bedrock_pos, stone_pos) = (IVec3::new(4, 158, 2), IVec3::new(5, 158, 2));
+    let stone = Stone::into_block();
+    for (pos, block) in [(bedrock_pos, Bedrock::into_block()), (stone_pos, stone)] {
+        harness.clients[0].world_mut().send_event(BlockEditRequest {
+            pos,
+            previous: Block::EMPTY_BLOCK,
+            block,
+        });
+    }
+
+    // Requests are handled in order, the bedrock was refused once the stone shows up
+    assert!(harness.step_until(MAX_GAME_STEPS, |harness| {
+        chunks(&harness.clients[1]).block_at(stone_pos) == Some(stone)
+    }));
+    assert_eq!(chunks(&harness.server).block_at(bedrock_pos), Some(Block::EMPTY_BLOCK));
+    assert_eq!(chunks(&harness.clients[1]).block_at(bedrock_pos), Some(Block::EMPTY_BLOCK));
+}
@@ -0,0 +1,60 @@
+use bevy::color::Color;
+use voxel_engine::{
+    BlockMaterial, BlockMaterialFlags, BlockMaterialRegistry, CoalOre, Stone, Water,
+    MAX_MATERIAL_ID,
+};
+
+struct Unmeshable;
+
+impl BlockMaterial for Unmeshable {
+    const ID: u64 = MAX_MATERIAL_ID + 1;
+
+    fn block_name() -> &'static str {
+        "unmeshable"
+    }
+    fn base_color() -> Color {
+        Color::WHITE
+    }
+    fn flags() -> BlockMaterialFlags {
+        BlockMaterialFlags::SOLID
+    }
+}
+
+#[test]
+fn applied_definitions_keep_the_names_of_the_materials() {
+    let mut server = BlockMaterialRegistry::default();
+    server.register::<Stone>();
+    server.register::<CoalOre>();
+
+    let mut client = BlockMaterialRegistry::default();
+    client.register::<Stone>();
+
+    // Syncing again, as every login does, leaves the registry as it was
+    for _ in 0..2 {
+        client.apply_definitions(&server.definitions()).unwrap();
+
+        let ore = client.get_by_id(CoalOre::ID).unwrap();
+        assert_eq!(ore.id, "rust_crafted::ore::coal");
+        assert_eq!(ore.namespace, "rust_crafted");
+        assert_eq!(ore.name, "ore");
+        assert_eq!(ore.variant.as_deref(), Some("coal"));
+
+        assert_eq!(client.get_id_for_type::<Stone>(), Some(Stone::ID));
+        assert_eq!(client.get_by_id(Stone::ID).unwrap().variant, None);
+    }
+}
+
+#[test]
+fn blocks_have_the_flags_of_their_material() {
+    let mut registry = BlockMaterialRegistry::default();
+    registry.register::<Water>();
+
+    assert_eq!(registry.block_flags(Water::into_block()), BlockMaterialFlags::LIQUID);
+    assert_eq!(registry.block_flags(Stone::into_block()), BlockMaterialFlags::SOLID);
+}
+
+#[test]
+#[should_panic(expected = "above 255")]
+fn materials_above_the_max_id_cant_be_registered() {
+    BlockMaterialRegistry::default().register::<Unmeshable>();
+}
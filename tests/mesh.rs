@@ -0,0 +1,205 @@
+use bevy::{
+    asset::RenderAssetUsages,
+    math::{IVec3, Vec3},
+    render::mesh::{Indices, Mesh, PrimitiveTopology, VertexAttributeValues},
+};
+use ilattice::glam::UVec3;
+use ndshape::ConstShape3u32;
+use voxel_engine::{
+    client::render::{mesh_buffer, BlockTerrainMesh, MeshBuffer},
+    Block, BlockBuffer, ChunkMap,
+};
+
+type TestShape = ConstShape3u32<4, 4, 4>;
+
+const STONE: u64 = 3;
+const WATER: u64 = 4;
+
+/// Normals of the faces by their index in the vertex data.
+const FACE_NORMALS: [Vec3; 6] = [Vec3::NEG_X, Vec3::NEG_Y, Vec3::NEG_Z, Vec3::X, Vec3::Y, Vec3::Z];
+
+/// Vertex of a meshed quad.
+#[derive(Clone, Copy, Debug)]
+struct Vertex {
+    pos: Vec3,
+    face: usize,
+    ao: u32,
+    material: u64,
+}
+
+/// Quads of a mesh, with their vertices and the vertices of their two triangles.
+struct Quads {
+    vertices: Vec<Vertex>,
+    triangles: Vec<[u32; 6]>,
+}
+
+impl Quads {
+    fn of(mesh: &Mesh) -> Self {
+        let Some(VertexAttributeValues::Float32x3(positions)) =
+            mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+        else {
+            panic!("No positions");
+        };
+        let Some(VertexAttributeValues::Uint32(data)) =
+            mesh.attribute(BlockTerrainMesh::ATTRIBUTE_DATA)
+        else {
+            panic!("No vertex data");
+        };
+        let Some(Indices::U32(indices)) = mesh.indices() else {
+            panic!("No indices");
+        };
+
+        let vertices = positions
+            .iter()
+            .zip(data)
+            .map(|(pos, data)| Vertex {
+                pos: Vec3::from_array(*pos),
+                face: (data >> 8 & 0x7) as usize,
+                ao: data >> 11 & 0x3,
+                material: (data & 0xFF) as u64,
+            })
+            .collect();
+        let triangles = indices
+            .chunks_exact(6)
+            .map(|quad| quad.try_into().unwrap())
+            .collect();
+
+        Self {
+            vertices,
+            triangles,
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.triangles.len()
+    }
+
+    /// Returns the vertices of every quad, with the indices of its two triangles.
+    fn iter(&self) -> impl Iterator<Item = ([Vertex; 4], [u32; 6])> + '_ {
+        self.triangles.iter().map(|triangles| {
+            let start = triangles.iter().min().unwrap();
+            let vertices = [0, 1, 2, 3].map(|corner| self.vertices[(start + corner) as usize]);
+            (vertices, *triangles)
+        })
+    }
+
+    /// Returns the sorted ambient occlusion of the corners of the quads facing along `face`.
+    fn occlusion_of_faces(&self, face: usize) -> Vec<[u32; 4]> {
+        let mut occlusion: Vec<_> = self
+            .iter()
+            .filter(|(vertices, _)| vertices[0].face == face)
+            .map(|(vertices, _)| {
+                let mut ao = vertices.map(|vertex| vertex.ao);
+                ao.sort();
+                ao
+            })
+            .collect();
+        occlusion.sort();
+        occlusion
+    }
+}
+
+/// Meshes a chunk holding `blocks` and nothing around it, returns its opaque and translucent
+/// quads.
+fn mesh(blocks: &[(UVec3, Block)]) -> (Quads, Quads) {
+    let mut buffer = BlockBuffer::<Block, TestShape>::new_empty(TestShape {});
+    for (pos, block) in blocks {
+        buffer.set_block(*pos, *block);
+    }
+
+    let neighbours = ChunkMap::<Block, TestShape>::new(TestShape {}).neighbour_borders(IVec3::ZERO);
+    let light = vec![0; 6 * 6 * 6];
+
+    let mut opaque = Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::default());
+    let mut translucent = Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::default());
+    mesh_buffer(
+        &buffer,
+        &neighbours,
+        &light,
+        &mut MeshBuffer::new(TestShape {}),
+        &mut opaque,
+        &mut translucent,
+        1.0,
+    );
+
+    (Quads::of(&opaque), Quads::of(&translucent))
+}
+
+/// Checks that the quads are split along their least occluded diagonal, and that both of their
+/// triangles still face outwards.
+fn assert_split_along_the_brightest_diagonal(quads: &Quads) {
+    for (vertices, triangles) in quads.iter() {
+        let start = triangles.iter().min().unwrap();
+        let vertex = |index: u32| vertices[(index - start) as usize];
+
+        let (first, second) = triangles.split_at(3);
+        let diagonal: u32 = first
+            .iter()
+            .filter(|index| second.contains(index))
+            .map(|index| vertex(*index).ao)
+            .sum();
+        let total: u32 = vertices.iter().map(|vertex| vertex.ao).sum();
+        assert!(diagonal >= total - diagonal, "{vertices:?} split along {triangles:?}");
+
+        let normal = FACE_NORMALS[vertices[0].face];
+        for triangle in [first, second] {
+            let [a, b, c] = [0, 1, 2].map(|corner| vertex(triangle[corner]).pos);
+            assert!((b - a).cross(c - a).dot(normal) > 0.0, "{vertices:?} wound backwards");
+        }
+    }
+}
+
+#[test]
+fn lone_blocks_are_unoccluded() {
+    let (opaque, translucent) = mesh(&[(UVec3::ONE, Block::new_opaque(STONE))]);
+
+    assert_eq!(opaque.len(), 6);
+    assert_eq!(translucent.len(), 0);
+    assert!(opaque.vertices.iter().all(|vertex| vertex.material == STONE));
+    for face in 0..6 {
+        assert_eq!(opaque.occlusion_of_faces(face), [[3; 4]]);
+    }
+}
+
+#[test]
+fn corner_blocks_darken_a_single_corner() {
+    let stone = Block::new_opaque(STONE);
+    let (opaque, _) = mesh(&[(UVec3::ONE, stone), (UVec3::new(2, 2, 2), stone)]);
+
+    // The top of the lower block only touches the upper block by a corner
+    assert_eq!(opaque.occlusion_of_faces(4), [[2, 3, 3, 3], [3, 3, 3, 3]]);
+    assert_split_along_the_brightest_diagonal(&opaque);
+}
+
+#[test]
+fn corners_between_two_sides_are_fully_occluded() {
+    let stone = Block::new_opaque(STONE);
+    let (opaque, _) = mesh(&[
+        (UVec3::ONE, stone),
+        (UVec3::new(2, 2, 1), stone),
+        (UVec3::new(1, 2, 2), stone),
+    ]);
+
+    // Corners touching both side blocks are as dark as can be, whatever lies in the corner
+    let top = opaque.occlusion_of_faces(4);
+    assert!(top.contains(&[0, 2, 2, 3]), "{top:?}");
+    assert_split_along_the_brightest_diagonal(&opaque);
+}
+
+#[test]
+fn translucent_blocks_are_meshed_apart_from_opaque_ones() {
+    let (opaque, translucent) = mesh(&[
+        (UVec3::ONE, Block::new_opaque(STONE)),
+        (UVec3::new(2, 1, 1), Block::new_transparent(WATER)),
+        (UVec3::new(3, 1, 1), Block::new_transparent(WATER)),
+    ]);
+
+    // The stone can be seen through the water, the water faces against the stone and between
+    // the water blocks are culled
+    assert_eq!(opaque.len(), 6);
+    assert!(opaque.vertices.iter().all(|vertex| vertex.material == STONE));
+
+    assert!(translucent.vertices.iter().all(|vertex| vertex.material == WATER));
+    assert!(translucent.vertices.iter().all(|vertex| vertex.face != 0));
+    assert_eq!(translucent.occlusion_of_faces(3).len(), 1);
+}
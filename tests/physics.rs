@@ -0,0 +1,108 @@
+use bevy::math::{IVec3, Vec3};
+use voxel_engine::{step_body, BodyState, Collider};
+
+const DT: f32 = 1. / 60.;
+
+/// Steps the body `steps` times in the world described by `is_solid`.
+fn simulate(is_solid: impl Fn(IVec3) -> bool, state: &mut BodyState, steps: usize) {
+    let collider = Collider::default();
+    for _ in 0..steps {
+        step_body(&is_solid, &collider, state, DT);
+    }
+}
+
+fn standing_at(position: Vec3) -> BodyState {
+    BodyState {
+        position,
+        on_ground: true,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn falling_bodies_land_on_the_ground() {
+    let floor = |pos: IVec3| pos.y < 0;
+    let mut state = BodyState {
+        position: Vec3::new(0.5, 3., 0.5),
+        ..Default::default()
+    };
+
+    simulate(floor, &mut state, 1);
+    assert!(!state.on_ground);
+    assert!(state.velocity.y < 0.);
+
+    simulate(floor, &mut state, 120);
+    assert!(state.on_ground);
+    assert_eq!(state.velocity.y, 0.);
+    assert!(state.position.y >= 0. && state.position.y < 0.01, "{}", state.position);
+}
+
+#[test]
+fn bodies_leave_the_ground_when_walking_off_a_ledge() {
+    let platform = |pos: IVec3| pos.y < 0 && pos.x < 1;
+    let mut state = standing_at(Vec3::new(0.5, 0., 0.5));
+
+    simulate(platform, &mut state, 1);
+    assert!(state.on_ground);
+
+    state.velocity.x = 4.;
+    simulate(platform, &mut state, 30);
+    assert!(!state.on_ground);
+    assert!(state.position.y < 0.);
+}
+
+#[test]
+fn walls_stop_horizontal_movement() {
+    let wall = |pos: IVec3| pos.y < 0 || (pos.x >= 2 && pos.y < 4);
+    let collider = Collider::default();
+    let mut state = standing_at(Vec3::new(0.5, 0., 0.5));
+
+    for _ in 0..60 {
+        state.velocity.x = 5.;
+        step_body(wall, &collider, &mut state, DT);
+    }
+
+    let touching = 2. - collider.half_width;
+    assert!(state.position.x <= touching, "{}", state.position);
+    assert!(state.position.x > touching - 0.01, "{}", state.position);
+    assert_eq!(state.velocity.x, 0.);
+    assert!(state.position.y < 0.01);
+}
+
+#[test]
+fn bodies_step_up_one_block_ledges() {
+    let ledge = |pos: IVec3| pos.y < 0 || (pos.x >= 2 && pos.y == 0);
+    let collider = Collider::default();
+    let mut state = standing_at(Vec3::new(0.5, 0., 0.5));
+
+    for _ in 0..60 {
+        state.velocity.x = 4.;
+        step_body(ledge, &collider, &mut state, DT);
+    }
+
+    assert!(state.position.x > 3., "{}", state.position);
+    assert!(state.position.y >= 1. && state.position.y < 1.01, "{}", state.position);
+    assert!(state.on_ground);
+}
+
+#[test]
+fn ceilings_stop_jumps() {
+    let tunnel = |pos: IVec3| !(0..3).contains(&pos.y);
+    let collider = Collider::default();
+    let mut state = standing_at(Vec3::new(0.5, 0., 0.5));
+    state.velocity.y = 10.;
+
+    let mut highest = state.position.y;
+    for _ in 0..60 {
+        step_body(tunnel, &collider, &mut state, DT);
+        highest = highest.max(state.position.y);
+
+        if state.velocity.y <= 0. {
+            break;
+        }
+    }
+
+    assert_eq!(state.velocity.y, 0.);
+    assert!(highest + collider.height <= 3., "{highest}");
+    assert!(highest + collider.height > 2.99, "{highest}");
+}
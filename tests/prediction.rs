@@ -0,0 +1,149 @@
+use bevy::math::Vec3;
+use std::time::Duration;
+use voxel_engine::{
+    client::network::{PredictionHistory, MAX_PREDICTED_INPUTS},
+    server::network::{InputQueue, MAX_BANKED_INPUTS, MAX_QUEUED_INPUTS},
+    BodyState, PlayerInput, MOVEMENT_RATE, TICK_RATE,
+};
+
+const TICK: Duration = Duration::from_millis(1000 / TICK_RATE as u64);
+
+fn input(sequence: u32) -> PlayerInput {
+    PlayerInput {
+        sequence,
+        direction: Vec3::X,
+        ..Default::default()
+    }
+}
+
+fn at(x: f32) -> BodyState {
+    BodyState {
+        position: Vec3::new(x, 0., 0.),
+        ..Default::default()
+    }
+}
+
+/// Moves the body one block along the direction of the input.
+fn step(input: &PlayerInput, state: &mut BodyState) {
+    state.position += input.direction;
+}
+
+/// Returns a history where each of the `count` inputs moved the player one block further.
+fn history_of(count: u32) -> PredictionHistory {
+    let mut history = PredictionHistory::default();
+    for sequence in 0..count {
+        history.push(input(sequence), at(sequence as f32 + 1.));
+    }
+    history
+}
+
+fn sequences(queue: &mut InputQueue) -> Vec<u32> {
+    queue.take(usize::MAX).map(|input| input.sequence).collect()
+}
+
+#[test]
+fn matching_predictions_are_kept() {
+    let mut history = history_of(5);
+
+    assert_eq!(history.reconcile(2, at(3.), step), None);
+    assert_eq!(history.reconcile(4, at(5.005), step), None);
+}
+
+#[test]
+fn mispredictions_replay_the_unacknowledged_inputs() {
+    let mut history = history_of(5);
+
+    assert_eq!(history.reconcile(2, at(10.), step), Some(at(12.)));
+
+    // The replayed inputs now predict the corrected positions
+    assert_eq!(history.reconcile(3, at(11.), step), None);
+    assert_eq!(history.reconcile(4, at(12.), step), None);
+}
+
+#[test]
+fn late_server_states_are_ignored() {
+    let mut history = history_of(5);
+
+    assert_eq!(history.reconcile(3, at(4.), step), None);
+    assert_eq!(history.reconcile(1, at(-20.), step), None);
+    assert_eq!(history.reconcile(3, at(-20.), step), None);
+}
+
+#[test]
+fn forgotten_predictions_snap_to_the_server() {
+    let extra = 10;
+    let mut history = history_of(MAX_PREDICTED_INPUTS as u32 + extra);
+
+    // The input the server acknowledges fell out of the history, every input kept is replayed
+    let corrected = history.reconcile(extra - 1, at(-5.), step);
+    assert_eq!(corrected, Some(at(MAX_PREDICTED_INPUTS as f32 - 5.)));
+
+    let mut empty = PredictionHistory::default();
+    assert_eq!(empty.reconcile(7, at(2.), step), Some(at(2.)));
+}
+
+#[test]
+fn queued_inputs_skip_duplicates() {
+    let mut queue = InputQueue::default();
+    queue.extend([0, 1, 2].map(input));
+    queue.extend([1, 2, 3].map(input));
+    queue.extend([2].map(input));
+
+    assert_eq!(sequences(&mut queue), [0, 1, 2, 3]);
+}
+
+#[test]
+fn applied_inputs_are_not_queued_again() {
+    let mut queue = InputQueue::default();
+    queue.extend([0, 1, 2].map(input));
+
+    let applied: Vec<_> = queue.take(2).map(|input| input.sequence).collect();
+    assert_eq!(applied, [0, 1]);
+    assert_eq!(sequences(&mut queue), [2]);
+
+    queue.extend([1, 2, 3].map(input));
+    assert_eq!(sequences(&mut queue), [3]);
+}
+
+#[test]
+fn input_queues_drop_the_oldest_inputs_when_full() {
+    let extra = 5;
+    let mut queue = InputQueue::default();
+    queue.extend((0..MAX_QUEUED_INPUTS as u32 + extra).map(input));
+
+    let kept = sequences(&mut queue);
+    assert_eq!(kept.len(), MAX_QUEUED_INPUTS);
+    assert_eq!(kept[0], extra);
+}
+
+#[test]
+fn flooding_inputs_doesnt_speed_players_up() {
+    let seconds = 10;
+    let mut queue = InputQueue::default();
+    let mut state = at(0.);
+
+    // The client sends ten times as many inputs as it should
+    let flood = (MOVEMENT_RATE / TICK_RATE) as u32 * 10;
+    for tick in 0..seconds * TICK_RATE as u32 {
+        queue.extend((tick * flood..(tick + 1) * flood).map(input));
+        for input in queue.take_due(TICK) {
+            step(&input, &mut state);
+        }
+    }
+
+    let expected = seconds as f32 * MOVEMENT_RATE as f32;
+    assert!(state.position.x >= expected - 1., "{}", state.position.x);
+    assert!(state.position.x <= expected + MAX_BANKED_INPUTS as f32, "{}", state.position.x);
+}
+
+#[test]
+fn idle_clients_bank_a_few_inputs() {
+    let mut queue = InputQueue::default();
+    for _ in 0..TICK_RATE as u32 {
+        assert_eq!(queue.take_due(TICK).count(), 0);
+    }
+
+    queue.extend((0..MAX_QUEUED_INPUTS as u32).map(input));
+    assert_eq!(queue.take_due(Duration::ZERO).count(), MAX_BANKED_INPUTS as usize);
+    assert_eq!(queue.take_due(Duration::ZERO).count(), 0);
+}
@@ -0,0 +1,102 @@
+use bevy::math::{IVec3, Vec3};
+use ndshape::ConstShape3u32;
+use voxel_engine::ChunkMap;
+
+type TestShape = ConstShape3u32<16, 16, 16>;
+
+const STONE: u8 = 1;
+
+/// Returns a map whose loaded chunks surround the origin on every side.
+fn map_around_origin() -> ChunkMap<u8, TestShape> {
+    let mut map = ChunkMap::new(TestShape {});
+    for x in [-16, 0] {
+        for y in [-16, 0] {
+            for z in [-16, 0] {
+                map.insert_empty(IVec3::new(x, y, z));
+            }
+        }
+    }
+    map
+}
+
+#[test]
+fn rays_report_the_hit_block_and_face() {
+    let mut map = map_around_origin();
+    map.set_block(IVec3::new(5, 0, 0), STONE);
+
+    let hit = map
+        .raycast(Vec3::splat(0.5), Vec3::X, 10.)
+        .expect("the ray should hit the block");
+
+    assert_eq!(hit.pos, IVec3::new(5, 0, 0));
+    assert_eq!(hit.normal, IVec3::NEG_X);
+    assert_eq!(hit.block, STONE);
+    assert!((hit.distance - 4.5).abs() < 1e-5);
+    assert_eq!(hit.adjacent_pos(), IVec3::new(4, 0, 0));
+}
+
+#[test]
+fn rays_stop_at_their_reach() {
+    let mut map = map_around_origin();
+    map.set_block(IVec3::new(5, 0, 0), STONE);
+
+    assert!(map.raycast(Vec3::splat(0.5), Vec3::X, 4.).is_none());
+    assert!(map.raycast(Vec3::splat(0.5), Vec3::X, 4.5).is_some());
+}
+
+#[test]
+fn rays_travel_along_negative_axes() {
+    let mut map = map_around_origin();
+    map.set_block(IVec3::new(-4, 0, 0), STONE);
+    map.set_block(IVec3::new(0, -3, 0), STONE);
+    map.set_block(IVec3::new(0, 0, -2), STONE);
+
+    for (dir, pos, normal, distance) in [
+        (Vec3::NEG_X, IVec3::new(-4, 0, 0), IVec3::X, 3.5),
+        (Vec3::NEG_Y, IVec3::new(0, -3, 0), IVec3::Y, 2.5),
+        (Vec3::NEG_Z, IVec3::new(0, 0, -2), IVec3::Z, 1.5),
+    ] {
+        let hit = map.raycast(Vec3::splat(0.5), dir, 10.).unwrap();
+
+        assert_eq!(hit.pos, pos);
+        assert_eq!(hit.normal, normal);
+        assert!((hit.distance - distance).abs() < 1e-5);
+    }
+}
+
+#[test]
+fn diagonal_rays_hit_the_face_they_cross() {
+    let mut map = map_around_origin();
+    for x in -16..16 {
+        map.set_block(IVec3::new(x, -3, 0), STONE);
+    }
+
+    let hit = map
+        .raycast(Vec3::new(0.5, 0.25, 0.5), Vec3::new(-1., -1., 0.), 10.)
+        .unwrap();
+
+    assert_eq!(hit.pos, IVec3::new(-2, -3, 0));
+    assert_eq!(hit.normal, IVec3::Y);
+    assert!((hit.distance - 2.25 * std::f32::consts::SQRT_2).abs() < 1e-4);
+}
+
+#[test]
+fn rays_starting_inside_a_block_hit_it_without_a_face() {
+    let mut map = map_around_origin();
+    map.set_block(IVec3::ZERO, STONE);
+
+    let hit = map.raycast(Vec3::splat(0.5), Vec3::Y, 10.).unwrap();
+
+    assert_eq!(hit.pos, IVec3::ZERO);
+    assert_eq!(hit.normal, IVec3::ZERO);
+    assert_eq!(hit.distance, 0.);
+}
+
+#[test]
+fn rays_miss_without_direction_or_loaded_blocks() {
+    let mut map = map_around_origin();
+    map.set_block(IVec3::new(20, 0, 0), STONE);
+
+    assert!(map.raycast(Vec3::splat(0.5), Vec3::ZERO, 10.).is_none());
+    assert!(map.raycast(Vec3::splat(0.5), Vec3::X, 30.).is_none());
+}
@@ -0,0 +1,157 @@
+use bevy::prelude::*;
+use ilattice::glam::UVec3;
+use std::path::PathBuf;
+use voxel_engine::{
+    region_location, Block, BlockBaseMaterialsPlugin, BlockBuffer, BlockMaterial,
+    BlockMaterialPlugin, BlockMaterialRegistry, ChunkShape, Dirt, MaterialIdTable, RegionStorage,
+    Stone, CHUNK_HEIGHT, CHUNK_SIZE, REGION_SIZE,
+};
+
+const CHUNK: i32 = CHUNK_SIZE as i32;
+const REGION: i32 = CHUNK * REGION_SIZE;
+
+/// Directory removed once the test using it is done.
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("region-{}-{name}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        Self(path)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+fn open_storage(dir: &TempDir) -> RegionStorage {
+    let mut app = App::new();
+    app.add_plugins((BlockMaterialPlugin, BlockBaseMaterialsPlugin));
+
+    let storage = RegionStorage::open(&dir.0).unwrap();
+    storage.set_materials(MaterialIdTable::from(app.world().resource::<BlockMaterialRegistry>()));
+    storage
+}
+
+fn buffer_with(blocks: &[(UVec3, Block)]) -> BlockBuffer<Block, ChunkShape> {
+    let mut buffer = BlockBuffer::new_empty(ChunkShape {});
+    for (pos, block) in blocks {
+        buffer.set_block(*pos, *block);
+    }
+    buffer
+}
+
+#[test]
+fn chunks_round_trip_through_region_files() {
+    let dir = TempDir::new("round-trip");
+    let storage = open_storage(&dir);
+    let chunks = [
+        (IVec3::ZERO, buffer_with(&[(UVec3::ZERO, Stone::into_block())])),
+        (
+            IVec3::new(-CHUNK, 0, -REGION - CHUNK),
+            buffer_with(&[(UVec3::new(3, 200, 31), Dirt::into_block())]),
+        ),
+        (IVec3::new(CHUNK, 0, 0), buffer_with(&[])),
+    ];
+
+    for (key, buffer) in &chunks {
+        storage.save_chunk(*key, buffer).unwrap();
+    }
+    storage.flush().unwrap();
+
+    // A fresh storage has to read the chunks back from disk
+    let reopened = open_storage(&dir);
+    for (key, buffer) in &chunks {
+        let loaded = reopened.load_chunk(*key).unwrap().expect("the chunk was saved");
+        assert_eq!(loaded.slice(), buffer.slice());
+    }
+}
+
+#[test]
+fn chunks_can_be_rewritten() {
+    let dir = TempDir::new("rewrite");
+    let storage = open_storage(&dir);
+    let key = IVec3::new(-CHUNK, 0, CHUNK);
+
+    let small = buffer_with(&[]);
+    let large = buffer_with(
+        &(0..64)
+            .map(|x| (UVec3::new(x % 32, x, 0), Stone::into_block()))
+            .collect::<Vec<_>>(),
+    );
+
+    for buffer in [&small, &large, &small] {
+        storage.save_chunk(key, buffer).unwrap();
+        assert_eq!(storage.load_chunk(key).unwrap().unwrap().slice(), buffer.slice());
+    }
+}
+
+#[test]
+fn unsaved_chunks_are_missing() {
+    let dir = TempDir::new("missing");
+    let storage = open_storage(&dir);
+
+    assert!(storage.load_chunk(IVec3::ZERO).unwrap().is_none());
+
+    storage.save_chunk(IVec3::ZERO, &buffer_with(&[])).unwrap();
+    assert!(storage.load_chunk(IVec3::new(CHUNK, 0, 0)).unwrap().is_none());
+}
+
+#[test]
+fn seeds_round_trip() {
+    let dir = TempDir::new("seed");
+    let storage = open_storage(&dir);
+
+    assert_eq!(storage.load_seed().unwrap(), None);
+    storage.save_seed(u64::MAX).unwrap();
+    assert_eq!(storage.load_seed().unwrap(), Some(u64::MAX));
+}
+
+#[test]
+fn region_location_of_positive_chunks() {
+    assert_eq!(region_location(IVec3::ZERO), (IVec3::ZERO, 0));
+    assert_eq!(region_location(IVec3::new(CHUNK, 0, 0)), (IVec3::ZERO, 1));
+    assert_eq!(
+        region_location(IVec3::new(0, 0, CHUNK)),
+        (IVec3::ZERO, REGION_SIZE as usize)
+    );
+    assert_eq!(
+        region_location(IVec3::new(REGION, CHUNK_HEIGHT as i32, REGION - CHUNK)),
+        (IVec3::new(1, 1, 0), (REGION_SIZE * (REGION_SIZE - 1)) as usize)
+    );
+}
+
+#[test]
+fn region_location_of_negative_chunks() {
+    let last = REGION_SIZE as usize - 1;
+
+    assert_eq!(region_location(IVec3::new(-CHUNK, 0, 0)), (IVec3::new(-1, 0, 0), last));
+    assert_eq!(
+        region_location(IVec3::new(0, 0, -CHUNK)),
+        (IVec3::new(0, 0, -1), last * REGION_SIZE as usize)
+    );
+    assert_eq!(region_location(IVec3::new(-REGION, 0, -REGION)), (IVec3::new(-1, 0, -1), 0));
+    assert_eq!(
+        region_location(IVec3::new(-REGION - CHUNK, -(CHUNK_HEIGHT as i32), 0)),
+        (IVec3::new(-2, -1, 0), last)
+    );
+}
+
+#[test]
+fn every_chunk_of_a_region_has_its_own_slot() {
+    let region_min = IVec3::new(-REGION, 0, -REGION);
+    let mut indices: Vec<usize> = (0..REGION_SIZE)
+        .flat_map(|x| (0..REGION_SIZE).map(move |z| region_min + IVec3::new(x, 0, z) * CHUNK))
+        .map(|key| {
+            let (region_key, index) = region_location(key);
+            assert_eq!(region_key, IVec3::new(-1, 0, -1));
+            index
+        })
+        .collect();
+    indices.sort_unstable();
+
+    assert_eq!(indices, (0..(REGION_SIZE * REGION_SIZE) as usize).collect::<Vec<_>>());
+}